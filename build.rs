@@ -80,6 +80,25 @@ fn main() -> Result<(), io::Error> {
             "dismiss-regular",
             "checkmark-regular",
             "caret-down-right-filled",
+            "grid-regular",
+            "blur-regular",
+            "shield-regular",
+            "zoom-in-regular",
+            "flashlight-regular",
+            "comment-regular",
+            "line-horizontal-1-regular",
+            "play-filled",
+            "chevron-right-regular",
+            "record-filled",
+            "divider-tall-regular",
+            "line-horizontal-1-dashes-regular",
+            "more-horizontal-regular",
+            "line-horizontal-1-dashes-filled",
+            "ruler-regular",
+            "eyedropper-regular",
+            "sticker-regular",
+            "image-regular",
+            "eraser",
         ],
     );
 
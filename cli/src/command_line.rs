@@ -17,7 +17,7 @@ pub struct CommandLine {
     #[arg(short, long)]
     pub config: Option<String>,
 
-    /// Path to input image or '-' to read from stdin
+    /// Path to input image or .satty project file, or '-' to read from stdin
     #[arg(short, long, required = true)]
     pub filename: Option<String>,
 
@@ -44,6 +44,20 @@ pub struct CommandLine {
     #[arg(short, long)]
     pub output_filename: Option<String>,
 
+    /// Image format to save in when the extension of the output filename names none, like
+    /// when printing to stdout, which defaults to png. A known extension always takes precedence
+    #[arg(long, value_enum)]
+    pub output_format: Option<OutputFormat>,
+
+    /// Add the annotations described in a JSON or TOML file on startup
+    #[arg(long, value_name = "FILE")]
+    pub annotations: Option<String>,
+
+    /// Render the image with its annotations to the output filename and exit
+    /// without opening a window. Still requires a display for OpenGL
+    #[arg(long)]
+    pub headless: bool,
+
     /// Exit directly after save action. 0.21.0: changed to accommodate different triggers
     #[arg(long, value_delimiter = ',', num_args=0.., default_missing_value = "all")]
     pub early_exit: Option<Vec<EarlyExitTriggers>>,
@@ -98,6 +112,21 @@ pub struct CommandLine {
     #[arg(long)]
     pub default_fill_shapes: bool,
 
+    /// The decoration at the start of new arrows.
+    /// defaults to none
+    #[arg(long)]
+    pub default_arrow_start: Option<ArrowHead>,
+
+    /// The decoration at the end of new arrows.
+    /// defaults to triangle
+    #[arg(long)]
+    pub default_arrow_end: Option<ArrowHead>,
+
+    /// The pattern outlines of new shapes are stroked with.
+    /// defaults to solid
+    #[arg(long)]
+    pub default_stroke_pattern: Option<StrokePattern>,
+
     /// Font family to use for text annotations
     #[arg(long)]
     pub font_family: Option<String>,
@@ -110,6 +139,34 @@ pub struct CommandLine {
     #[arg(long)]
     pub primary_highlighter: Option<Highlighters>,
 
+    /// The primary mode of the blur tool, secondary is accessible with CTRL
+    #[arg(long)]
+    pub primary_blur_mode: Option<BlurMode>,
+
+    /// The secondary mode of the blur tool, used when holding CTRL at the start of a blur
+    #[arg(long)]
+    pub secondary_blur_mode: Option<BlurMode>,
+
+    /// The initial enlargement of the magnifier tool, from 2 to 8.
+    /// defaults to 3.0
+    #[arg(long)]
+    pub magnifier_zoom: Option<f32>,
+
+    /// How much the spotlight tool darkens the image outside its spots, from 0 to 1.
+    /// defaults to 0.5
+    #[arg(long)]
+    pub spotlight_dim: Option<f32>,
+
+    /// How much the spotlight tool removes the colors outside its spots, from 0 to 1.
+    /// defaults to 0.0
+    #[arg(long)]
+    pub spotlight_desaturate: Option<f32>,
+
+    /// The width of the square of pixels the eyedropper averages, 1, 3 or 5.
+    /// defaults to 1
+    #[arg(long)]
+    pub eyedropper_sample_size: Option<u32>,
+
     /// Disable notifications
     #[arg(long)]
     pub disable_notifications: bool,
@@ -131,6 +188,16 @@ pub struct CommandLine {
     #[arg(long)]
     pub brush_smooth_history_size: Option<usize>,
 
+    /// How much the pressure of a tablet stylus varies the width of brush strokes, from 0 to 1.
+    /// 0 draws every stroke with constant width.
+    /// defaults to 1.0
+    #[arg(long)]
+    pub brush_pressure_sensitivity: Option<f32>,
+
+    /// Replace brush strokes closely matching a line, arrow, rectangle or ellipse with that shape.
+    #[arg(long)]
+    pub brush_snap_to_shape: bool,
+
     /// Experimental feature (0.20.1): The zoom factor to use for the image.
     /// 1.0 means no zoom.
     /// defaults to 1.1
@@ -235,6 +302,19 @@ pub enum NotificationThumbnail {
     //ScreenshotData
 }
 
+#[derive(Debug, Clone, Copy, Default, ValueEnum, Deserialize, PartialEq, Eq)]
+#[value(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+    Bmp,
+    Tiff,
+}
+
 #[derive(Debug, Clone, Copy, Default, ValueEnum)]
 pub enum Tools {
     #[default]
@@ -249,6 +329,14 @@ pub enum Tools {
     Blur,
     Highlight,
     Brush,
+    Redact,
+    Magnifier,
+    Spotlight,
+    Callout,
+    Measure,
+    Eyedropper,
+    Stamp,
+    Eraser,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -267,6 +355,43 @@ pub enum Highlighters {
     Freehand,
 }
 
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum BlurMode {
+    /// Gaussian blur
+    #[default]
+    Blur,
+    /// Mosaic of blocks following the tool size
+    Pixelate,
+    /// Solid fill with the selected color
+    Fill,
+    /// Gaussian blur covered with noise
+    Noise,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum StrokePattern {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+    DashDot,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ArrowHead {
+    /// A plain end of the shaft
+    #[default]
+    None,
+    /// A filled triangle
+    Triangle,
+    /// An open chevron
+    Chevron,
+    /// A filled dot
+    Circle,
+    /// A line across the shaft
+    Bar,
+}
+
 impl std::fmt::Display for Tools {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Tools::*;
@@ -282,6 +407,14 @@ impl std::fmt::Display for Tools {
             Blur => "blur",
             Highlight => "highlight",
             Brush => "brush",
+            Redact => "redact",
+            Magnifier => "magnifier",
+            Spotlight => "spotlight",
+            Callout => "callout",
+            Measure => "measure",
+            Eyedropper => "eyedropper",
+            Stamp => "stamp",
+            Eraser => "eraser",
         };
         f.write_str(s)
     }
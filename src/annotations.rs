@@ -0,0 +1,454 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use hex_color::HexColor;
+use serde::de::IgnoredAny;
+use serde_derive::Deserialize;
+
+use crate::{
+    configuration::APP_CONFIG,
+    math::Vec2D,
+    style::{ArrowHead, Size, StrokePattern, Style},
+    tools::{
+        Arrow, Blur, BlurMode, Ellipse, HighlightKind, Line, Magnifier, Marker, Measure,
+        MeasureMode, Polyline, Rectangle, Redact, SavedDrawable, SavedText, Spot, SpotShape,
+        Spotlight, Stamp, StampSymbol,
+    },
+};
+
+/// A declarative description of annotations, as read by `--annotations`.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AnnotationSpec {
+    /// Style applied to every annotation that doesn't override it.
+    #[serde(default)]
+    defaults: StyleSpec,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct StyleSpec {
+    color: Option<HexColor>,
+    size: Option<Size>,
+    fill: Option<bool>,
+    round_caps: Option<bool>,
+    arrow_start: Option<ArrowHead>,
+    arrow_end: Option<ArrowHead>,
+    stroke_pattern: Option<StrokePattern>,
+    annotation_size_factor: Option<f32>,
+    /// Keys not known to the style or the annotation it is flattened into.
+    #[serde(flatten)]
+    unknown: UnknownFields,
+}
+
+/// Keys no field is named after. `deny_unknown_fields` has no effect on flattened structs
+/// and would reject the style of tagged annotations, so they are collected and rejected
+/// after parsing instead.
+type UnknownFields = BTreeMap<String, IgnoredAny>;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Annotation {
+    Line {
+        from: [f32; 2],
+        to: [f32; 2],
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Polyline {
+        points: Vec<[f32; 2]>,
+        /// Connect the last point to the first one, filled if `fill` is set.
+        #[serde(default)]
+        closed: bool,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Arrow {
+        from: [f32; 2],
+        to: [f32; 2],
+        /// Control point bending the shaft into a curve.
+        control: Option<[f32; 2]>,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Rectangle {
+        pos: [f32; 2],
+        size: [f32; 2],
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Ellipse {
+        pos: [f32; 2],
+        size: [f32; 2],
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Blur {
+        pos: [f32; 2],
+        size: [f32; 2],
+        /// Defaults to the configured primary blur mode.
+        mode: Option<BlurMode>,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Redact {
+        pos: [f32; 2],
+        size: [f32; 2],
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Highlight {
+        pos: [f32; 2],
+        size: [f32; 2],
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Magnifier {
+        pos: [f32; 2],
+        size: [f32; 2],
+        /// Top left corner of the enlarged inset.
+        inset: [f32; 2],
+        /// Defaults to the configured magnifier zoom.
+        zoom: Option<f32>,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Spotlight {
+        pos: [f32; 2],
+        size: [f32; 2],
+        #[serde(default)]
+        shape: SpotShape,
+        #[serde(flatten)]
+        unknown: UnknownFields,
+    },
+    Measure {
+        from: [f32; 2],
+        to: [f32; 2],
+        #[serde(default)]
+        mode: MeasureMode,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Marker {
+        pos: [f32; 2],
+        /// Defaults to the number following the previous marker.
+        number: Option<u16>,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Stamp {
+        pos: [f32; 2],
+        #[serde(default)]
+        symbol: StampSymbol,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Text {
+        pos: [f32; 2],
+        text: String,
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+    Callout {
+        pos: [f32; 2],
+        text: String,
+        /// The point the tail of the box points at.
+        anchor: [f32; 2],
+        #[serde(flatten)]
+        style: StyleSpec,
+    },
+}
+
+fn check_unknown(unknown: &UnknownFields, context: &str) -> Result<()> {
+    match unknown.keys().next() {
+        Some(key) => Err(anyhow!("unknown field `{key}` in {context}")),
+        None => Ok(()),
+    }
+}
+
+impl StyleSpec {
+    fn apply(&self, style: Style) -> Style {
+        Style {
+            color: self.color.map(Into::into).unwrap_or(style.color),
+            size: self.size.unwrap_or(style.size),
+            fill: self.fill.unwrap_or(style.fill),
+            round_caps: self.round_caps.unwrap_or(style.round_caps),
+            annotation_size_factor: self
+                .annotation_size_factor
+                .unwrap_or(style.annotation_size_factor),
+            arrow_start: self.arrow_start.unwrap_or(style.arrow_start),
+            arrow_end: self.arrow_end.unwrap_or(style.arrow_end),
+            stroke_pattern: self.stroke_pattern.unwrap_or(style.stroke_pattern),
+        }
+    }
+}
+
+impl Annotation {
+    fn unknown(&self) -> &UnknownFields {
+        match self {
+            Self::Line { style, .. }
+            | Self::Polyline { style, .. }
+            | Self::Arrow { style, .. }
+            | Self::Rectangle { style, .. }
+            | Self::Ellipse { style, .. }
+            | Self::Blur { style, .. }
+            | Self::Redact { style, .. }
+            | Self::Highlight { style, .. }
+            | Self::Magnifier { style, .. }
+            | Self::Measure { style, .. }
+            | Self::Marker { style, .. }
+            | Self::Stamp { style, .. }
+            | Self::Text { style, .. }
+            | Self::Callout { style, .. } => &style.unknown,
+            Self::Spotlight { unknown, .. } => unknown,
+        }
+    }
+}
+
+fn vec2d([x, y]: [f32; 2]) -> Vec2D {
+    Vec2D::new(x, y)
+}
+
+impl AnnotationSpec {
+    /// Read a spec from a TOML file, or from JSON for any other extension.
+    pub fn load(filename: &str) -> Result<Self> {
+        let content = fs::read_to_string(filename).context("couldn't read annotations file")?;
+        let is_toml = Path::new(filename)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        Self::parse(&content, is_toml).context("couldn't parse annotations file")
+    }
+
+    fn parse(content: &str, is_toml: bool) -> Result<Self> {
+        let spec: Self = if is_toml {
+            toml::from_str(content)?
+        } else {
+            serde_json::from_str(content)?
+        };
+
+        check_unknown(&spec.defaults.unknown, "defaults")?;
+        for (index, annotation) in spec.annotations.iter().enumerate() {
+            check_unknown(annotation.unknown(), &format!("annotation {}", index + 1))?;
+        }
+        Ok(spec)
+    }
+
+    pub fn into_drawables(self) -> Vec<SavedDrawable> {
+        let defaults = self.defaults.apply(Style::default());
+        let mut next_marker = 1;
+
+        let drawables = self
+            .annotations
+            .into_iter()
+            .map(|annotation| match annotation {
+                Annotation::Line { from, to, style } => {
+                    SavedDrawable::Line(Line::new(vec2d(from), vec2d(to), style.apply(defaults)))
+                }
+                Annotation::Polyline {
+                    points,
+                    closed,
+                    style,
+                } => SavedDrawable::Polyline(Polyline::new(
+                    points.into_iter().map(vec2d).collect(),
+                    closed,
+                    style.apply(defaults),
+                )),
+                Annotation::Arrow {
+                    from,
+                    to,
+                    control,
+                    style,
+                } => {
+                    let arrow = Arrow::new(vec2d(from), vec2d(to), style.apply(defaults));
+                    SavedDrawable::Arrow(match control {
+                        Some(control) => arrow.with_control(vec2d(control)),
+                        None => arrow,
+                    })
+                }
+                Annotation::Rectangle { pos, size, style } => SavedDrawable::Rectangle(
+                    Rectangle::new(vec2d(pos), vec2d(size), style.apply(defaults)),
+                ),
+                Annotation::Ellipse { pos, size, style } => SavedDrawable::Ellipse(Ellipse::new(
+                    vec2d(pos),
+                    vec2d(size),
+                    style.apply(defaults),
+                )),
+                Annotation::Blur {
+                    pos,
+                    size,
+                    mode,
+                    style,
+                } => SavedDrawable::Blur(Blur::new(
+                    vec2d(pos),
+                    vec2d(size),
+                    style.apply(defaults),
+                    mode.unwrap_or(APP_CONFIG.read().primary_blur_mode()),
+                )),
+                Annotation::Redact { pos, size, style } => SavedDrawable::Redact(Redact::new(
+                    vec2d(pos),
+                    vec2d(size),
+                    style.apply(defaults),
+                )),
+                Annotation::Highlight { pos, size, style } => SavedDrawable::Highlight(
+                    HighlightKind::block(vec2d(pos), vec2d(size), style.apply(defaults)),
+                ),
+                Annotation::Magnifier {
+                    pos,
+                    size,
+                    inset,
+                    zoom,
+                    style,
+                } => {
+                    let zoom = zoom.unwrap_or_else(|| APP_CONFIG.read().magnifier_zoom());
+                    SavedDrawable::Magnifier(Magnifier::new(
+                        vec2d(pos),
+                        vec2d(size),
+                        vec2d(inset),
+                        zoom,
+                        style.apply(defaults),
+                    ))
+                }
+                Annotation::Spotlight {
+                    pos, size, shape, ..
+                } => SavedDrawable::Spotlight(Spotlight::new(vec![Spot::new(
+                    shape,
+                    vec2d(pos),
+                    vec2d(size),
+                )])),
+                Annotation::Measure {
+                    from,
+                    to,
+                    mode,
+                    style,
+                } => SavedDrawable::Measure(Measure::new(
+                    vec2d(from),
+                    vec2d(to),
+                    mode,
+                    style.apply(defaults),
+                )),
+                Annotation::Marker { pos, number, style } => {
+                    let number = number.unwrap_or(next_marker);
+                    next_marker = number.saturating_add(1);
+                    SavedDrawable::Marker(Marker::new(vec2d(pos), number, style.apply(defaults)))
+                }
+                Annotation::Stamp { pos, symbol, style } => {
+                    SavedDrawable::Stamp(Stamp::new(vec2d(pos), symbol, style.apply(defaults)))
+                }
+                Annotation::Text { pos, text, style } => {
+                    SavedDrawable::Text(SavedText::new(vec2d(pos), text, style.apply(defaults)))
+                }
+                Annotation::Callout {
+                    pos,
+                    text,
+                    anchor,
+                    style,
+                } => SavedDrawable::Text(
+                    SavedText::new(vec2d(pos), text, style.apply(defaults))
+                        .with_callout(vec2d(anchor)),
+                ),
+            });
+
+        // all spotlights form a single mask, so overlapping ones don't darken twice
+        let mut result: Vec<SavedDrawable> = Vec::new();
+        let mut spotlight_index = None;
+        for drawable in drawables {
+            match (drawable, spotlight_index) {
+                (SavedDrawable::Spotlight(spotlight), Some(index)) => {
+                    if let SavedDrawable::Spotlight(merged) = &mut result[index] {
+                        merged.merge(spotlight);
+                    }
+                }
+                (drawable, _) => {
+                    if matches!(drawable, SavedDrawable::Spotlight(_)) {
+                        spotlight_index = Some(result.len());
+                    }
+                    result.push(drawable);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnnotationSpec;
+    use crate::tools::SavedDrawable;
+
+    fn spots(drawable: &SavedDrawable) -> usize {
+        let value = serde_json::to_value(drawable).expect("serialize drawable");
+        value["spots"].as_array().map_or(0, Vec::len)
+    }
+
+    #[test]
+    fn parses_json_and_toml() {
+        let json = AnnotationSpec::parse(
+            r##"{
+                "defaults": { "color": "#ff0000", "size": "large" },
+                "annotations": [
+                    { "type": "line", "from": [0, 0], "to": [10, 10] },
+                    { "type": "marker", "pos": [5, 5], "fill": true }
+                ]
+            }"##,
+            false,
+        )
+        .expect("parse json");
+        let toml = AnnotationSpec::parse(
+            r##"
+            [defaults]
+            color = "#ff0000"
+
+            [[annotations]]
+            type = "rectangle"
+            pos = [1, 2]
+            size = [3, 4]
+            "##,
+            true,
+        )
+        .expect("parse toml");
+
+        assert_eq!(json.annotations.len(), 2);
+        assert_eq!(toml.annotations.len(), 1);
+        assert!(matches!(
+            json.into_drawables().as_slice(),
+            [SavedDrawable::Line(_), SavedDrawable::Marker(_)]
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        for content in [
+            r##"{ "annotations": [{ "type": "line", "from": [0, 0], "to": [1, 1], "colour": "#fff" }] }"##,
+            r##"{ "annotations": [{ "type": "spotlight", "pos": [0, 0], "size": [1, 1], "dim": 0.5 }] }"##,
+            r##"{ "defaults": { "colour": "#fff" } }"##,
+            r##"{ "annotation": [] }"##,
+        ] {
+            assert!(
+                AnnotationSpec::parse(content, false).is_err(),
+                "accepted {content}"
+            );
+        }
+    }
+
+    #[test]
+    fn merges_spotlights() {
+        let spec = AnnotationSpec::parse(
+            r##"{
+                "annotations": [
+                    { "type": "spotlight", "pos": [0, 0], "size": [10, 10] },
+                    { "type": "line", "from": [0, 0], "to": [10, 10] },
+                    { "type": "spotlight", "pos": [20, 20], "size": [10, 10], "shape": "ellipse" }
+                ]
+            }"##,
+            false,
+        )
+        .expect("parse spec");
+
+        let drawables = spec.into_drawables();
+        assert_eq!(drawables.len(), 2);
+        assert!(matches!(drawables[0], SavedDrawable::Spotlight(_)));
+        assert_eq!(spots(&drawables[0]), 2);
+    }
+}
@@ -15,13 +15,13 @@ use thiserror::Error;
 use xdg::{BaseDirectories, BaseDirectoriesError};
 
 use crate::{
-    style::Color,
-    tools::{Highlighters, Tools},
+    style::{ArrowHead, Color, StrokePattern},
+    tools::{BlurMode, Highlighters, Tools},
 };
 
 use satty_cli::command_line::{
     Action as CommandLineAction, CommandLine, EarlyExitTriggers, Fullscreen, NotificationThumbnail,
-    Resize,
+    OutputFormat, Resize,
 };
 
 pub static APP_CONFIG: SharedState<Configuration> = SharedState::new();
@@ -43,6 +43,13 @@ pub struct Configuration {
     license: bool,
     input_filename: Option<String>,
     output_filename: Option<String>,
+    output_format: Option<OutputFormat>,
+    jpeg_quality: u8,
+    webp_quality: u8,
+    png_compression: u8,
+    flatten_background: Color,
+    annotations_filename: Option<String>,
+    headless: bool,
     fullscreen: Option<Fullscreen>,
     resize: Option<Resize>,
     floating_hack: bool,
@@ -61,12 +68,25 @@ pub struct Configuration {
     focus_toggles_toolbars: bool,
     default_fill_shapes: bool,
     default_round_caps: bool,
+    default_arrow_start: ArrowHead,
+    default_arrow_end: ArrowHead,
+    default_stroke_pattern: StrokePattern,
     font: FontConfiguration,
     primary_highlighter: Highlighters,
+    primary_blur_mode: BlurMode,
+    secondary_blur_mode: BlurMode,
+    magnifier_zoom: f32,
+    magnifier_connector: bool,
+    magnifier_border: bool,
+    spotlight_dim: f32,
+    spotlight_desaturate: f32,
+    eyedropper_sample_size: u32,
     disable_notifications: bool,
     profile_startup: bool,
     no_window_decoration: bool,
     brush_smooth_history_size: usize,
+    brush_pressure_sensitivity: f32,
+    brush_snap_to_shape: bool,
     keybinds: HashMap<String, String>, // key_binding -> tool_or_command
     zoom_factor: f32,
     pan_step_size: f32,
@@ -296,6 +316,21 @@ impl Configuration {
         if let Some(v) = general.output_filename {
             self.output_filename = Some(v);
         }
+        if let Some(v) = general.output_format {
+            self.output_format = Some(v);
+        }
+        if let Some(v) = general.jpeg_quality {
+            self.jpeg_quality = v.min(100);
+        }
+        if let Some(v) = general.webp_quality {
+            self.webp_quality = v.min(100);
+        }
+        if let Some(v) = general.png_compression {
+            self.png_compression = v.min(9);
+        }
+        if let Some(v) = general.flatten_background {
+            self.flatten_background = v.into();
+        }
         if let Some(v) = general.annotation_size_factor {
             self.annotation_size_factor = v;
         }
@@ -326,9 +361,42 @@ impl Configuration {
         if let Some(v) = general.default_round_caps {
             self.default_round_caps = v;
         }
+        if let Some(v) = general.default_arrow_start {
+            self.default_arrow_start = v;
+        }
+        if let Some(v) = general.default_arrow_end {
+            self.default_arrow_end = v;
+        }
+        if let Some(v) = general.default_stroke_pattern {
+            self.default_stroke_pattern = v;
+        }
         if let Some(v) = general.primary_highlighter {
             self.primary_highlighter = v;
         }
+        if let Some(v) = general.primary_blur_mode {
+            self.primary_blur_mode = v;
+        }
+        if let Some(v) = general.secondary_blur_mode {
+            self.secondary_blur_mode = v;
+        }
+        if let Some(v) = general.magnifier_zoom {
+            self.magnifier_zoom = v;
+        }
+        if let Some(v) = general.magnifier_connector {
+            self.magnifier_connector = v;
+        }
+        if let Some(v) = general.magnifier_border {
+            self.magnifier_border = v;
+        }
+        if let Some(v) = general.spotlight_dim {
+            self.spotlight_dim = v;
+        }
+        if let Some(v) = general.spotlight_desaturate {
+            self.spotlight_desaturate = v;
+        }
+        if let Some(v) = general.eyedropper_sample_size {
+            self.eyedropper_sample_size = v;
+        }
         if let Some(v) = general.disable_notifications {
             self.disable_notifications = v;
         }
@@ -338,6 +406,12 @@ impl Configuration {
         if let Some(v) = general.brush_smooth_history_size {
             self.brush_smooth_history_size = v;
         }
+        if let Some(v) = general.brush_pressure_sensitivity {
+            self.brush_pressure_sensitivity = v;
+        }
+        if let Some(v) = general.brush_snap_to_shape {
+            self.brush_snap_to_shape = v;
+        }
         if let Some(v) = general.zoom_factor {
             self.zoom_factor = v;
         }
@@ -434,6 +508,15 @@ impl Configuration {
         if let Some(v) = command_line.output_filename {
             self.output_filename = Some(v);
         }
+        if let Some(v) = command_line.output_format {
+            self.output_format = Some(v);
+        }
+        if let Some(v) = command_line.annotations {
+            self.annotations_filename = Some(v);
+        }
+        if command_line.headless {
+            self.headless = command_line.headless;
+        }
         if let Some(v) = command_line.annotation_size_factor {
             self.annotation_size_factor = v;
         }
@@ -452,6 +535,15 @@ impl Configuration {
         if let Some(v) = command_line.actions_on_right_click {
             self.actions_on_right_click = v.iter().cloned().map(Into::into).collect();
         }
+        if let Some(v) = command_line.default_arrow_start {
+            self.default_arrow_start = v.into();
+        }
+        if let Some(v) = command_line.default_arrow_end {
+            self.default_arrow_end = v.into();
+        }
+        if let Some(v) = command_line.default_stroke_pattern {
+            self.default_stroke_pattern = v.into();
+        }
         if let Some(v) = command_line.font_family {
             self.font.family = Some(v);
         }
@@ -461,6 +553,24 @@ impl Configuration {
         if let Some(v) = command_line.primary_highlighter {
             self.primary_highlighter = v.into();
         }
+        if let Some(v) = command_line.primary_blur_mode {
+            self.primary_blur_mode = v.into();
+        }
+        if let Some(v) = command_line.secondary_blur_mode {
+            self.secondary_blur_mode = v.into();
+        }
+        if let Some(v) = command_line.magnifier_zoom {
+            self.magnifier_zoom = v;
+        }
+        if let Some(v) = command_line.spotlight_dim {
+            self.spotlight_dim = v;
+        }
+        if let Some(v) = command_line.spotlight_desaturate {
+            self.spotlight_desaturate = v;
+        }
+        if let Some(v) = command_line.eyedropper_sample_size {
+            self.eyedropper_sample_size = v;
+        }
         if command_line.disable_notifications {
             self.disable_notifications = command_line.disable_notifications;
         }
@@ -473,6 +583,12 @@ impl Configuration {
         if let Some(v) = command_line.brush_smooth_history_size {
             self.brush_smooth_history_size = v;
         }
+        if let Some(v) = command_line.brush_pressure_sensitivity {
+            self.brush_pressure_sensitivity = v;
+        }
+        if command_line.brush_snap_to_shape {
+            self.brush_snap_to_shape = command_line.brush_snap_to_shape;
+        }
         if let Some(v) = command_line.zoom_factor {
             self.zoom_factor = v;
         }
@@ -558,6 +674,34 @@ impl Configuration {
         self.output_filename.as_ref()
     }
 
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.output_format
+    }
+
+    pub fn jpeg_quality(&self) -> u8 {
+        self.jpeg_quality
+    }
+
+    pub fn webp_quality(&self) -> u8 {
+        self.webp_quality
+    }
+
+    pub fn png_compression(&self) -> u8 {
+        self.png_compression
+    }
+
+    pub fn flatten_background(&self) -> Color {
+        self.flatten_background
+    }
+
+    pub fn annotations_filename(&self) -> Option<&str> {
+        self.annotations_filename.as_deref()
+    }
+
+    pub fn headless(&self) -> bool {
+        self.headless
+    }
+
     pub fn input_filename(&self) -> &str {
         match self.input_filename {
             Some(ref v) => v,
@@ -609,10 +753,62 @@ impl Configuration {
         self.default_round_caps
     }
 
+    pub fn default_arrow_start(&self) -> ArrowHead {
+        self.default_arrow_start
+    }
+
+    pub fn default_arrow_end(&self) -> ArrowHead {
+        self.default_arrow_end
+    }
+
+    pub fn default_stroke_pattern(&self) -> StrokePattern {
+        self.default_stroke_pattern
+    }
+
     pub fn primary_highlighter(&self) -> Highlighters {
         self.primary_highlighter
     }
 
+    pub fn primary_blur_mode(&self) -> BlurMode {
+        self.primary_blur_mode
+    }
+
+    pub fn secondary_blur_mode(&self) -> BlurMode {
+        self.secondary_blur_mode
+    }
+
+    /// The initial enlargement of new magnifier insets, limited to 2x to 8x.
+    pub fn magnifier_zoom(&self) -> f32 {
+        self.magnifier_zoom.clamp(2.0, 8.0)
+    }
+
+    pub fn magnifier_connector(&self) -> bool {
+        self.magnifier_connector
+    }
+
+    pub fn magnifier_border(&self) -> bool {
+        self.magnifier_border
+    }
+
+    /// How much the spotlight darkens the image outside its spots, from 0 to 1.
+    pub fn spotlight_dim(&self) -> f32 {
+        self.spotlight_dim.clamp(0.0, 1.0)
+    }
+
+    /// How much the spotlight removes the colors outside its spots, from 0 to 1.
+    pub fn spotlight_desaturate(&self) -> f32 {
+        self.spotlight_desaturate.clamp(0.0, 1.0)
+    }
+
+    /// The width of the square the eyedropper averages, rounded to 1, 3 or 5 pixels.
+    pub fn eyedropper_sample_size(&self) -> u32 {
+        match self.eyedropper_sample_size {
+            0..=1 => 1,
+            2..=3 => 3,
+            _ => 5,
+        }
+    }
+
     pub fn disable_notifications(&self) -> bool {
         self.disable_notifications
     }
@@ -633,6 +829,15 @@ impl Configuration {
         self.brush_smooth_history_size
     }
 
+    /// How much the stylus pressure varies the width of brush strokes, from 0 to 1.
+    pub fn brush_pressure_sensitivity(&self) -> f32 {
+        self.brush_pressure_sensitivity.clamp(0.0, 1.0)
+    }
+
+    pub fn brush_snap_to_shape(&self) -> bool {
+        self.brush_snap_to_shape
+    }
+
     pub fn keybinds(&self) -> &HashMap<String, String> {
         &self.keybinds
     }
@@ -673,6 +878,13 @@ impl Default for Configuration {
             license: false,
             input_filename: Some(String::new()),
             output_filename: None,
+            output_format: None,
+            jpeg_quality: 90,
+            webp_quality: 90,
+            png_compression: 6,
+            flatten_background: Color::new(255, 255, 255, 255),
+            annotations_filename: None,
+            headless: false,
             fullscreen: None,
             resize: None,
             floating_hack: false,
@@ -691,12 +903,25 @@ impl Default for Configuration {
             focus_toggles_toolbars: false,
             default_fill_shapes: false,
             default_round_caps: true,
+            default_arrow_start: ArrowHead::None,
+            default_arrow_end: ArrowHead::Triangle,
+            default_stroke_pattern: StrokePattern::Solid,
             font: FontConfiguration::default(),
             primary_highlighter: Highlighters::Block,
+            primary_blur_mode: BlurMode::Blur,
+            secondary_blur_mode: BlurMode::Pixelate,
+            magnifier_zoom: 3.0,
+            magnifier_connector: true,
+            magnifier_border: true,
+            spotlight_dim: 0.5,
+            spotlight_desaturate: 0.0,
+            eyedropper_sample_size: 1,
             disable_notifications: false,
             profile_startup: false,
             no_window_decoration: false,
             brush_smooth_history_size: 0, // default to 0, no history
+            brush_pressure_sensitivity: 1.0,
+            brush_snap_to_shape: false,
             keybinds: HashMap::new(),
             zoom_factor: 1.1,
             pan_step_size: 50.,
@@ -757,6 +982,11 @@ struct ConfigurationFileGeneral {
     save_after_copy: Option<bool>,
     auto_copy: Option<bool>,
     output_filename: Option<String>,
+    output_format: Option<OutputFormat>,
+    jpeg_quality: Option<u8>,
+    webp_quality: Option<u8>,
+    png_compression: Option<u8>,
+    flatten_background: Option<HexColor>,
     actions_on_enter: Option<Vec<Action>>,
     actions_on_escape: Option<Vec<Action>>,
     actions_on_right_click: Option<Vec<Action>>,
@@ -764,10 +994,23 @@ struct ConfigurationFileGeneral {
     focus_toggles_toolbars: Option<bool>,
     default_fill_shapes: Option<bool>,
     default_round_caps: Option<bool>,
+    default_arrow_start: Option<ArrowHead>,
+    default_arrow_end: Option<ArrowHead>,
+    default_stroke_pattern: Option<StrokePattern>,
     primary_highlighter: Option<Highlighters>,
+    primary_blur_mode: Option<BlurMode>,
+    secondary_blur_mode: Option<BlurMode>,
+    magnifier_zoom: Option<f32>,
+    magnifier_connector: Option<bool>,
+    magnifier_border: Option<bool>,
+    spotlight_dim: Option<f32>,
+    spotlight_desaturate: Option<f32>,
+    eyedropper_sample_size: Option<u32>,
     disable_notifications: Option<bool>,
     no_window_decoration: Option<bool>,
     brush_smooth_history_size: Option<usize>,
+    brush_pressure_sensitivity: Option<f32>,
+    brush_snap_to_shape: Option<bool>,
     zoom_factor: Option<f32>,
     pan_step_size: Option<f32>,
     text_move_length: Option<f32>,
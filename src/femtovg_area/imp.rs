@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use glow::HasContext;
 use std::{
     cell::{RefCell, RefMut},
@@ -16,7 +16,7 @@ use femtovg::{
 };
 use fontconfig::Fontconfig;
 use gtk::{glib, prelude::*, subclass::prelude::*};
-use relm4::gtk::gdk_pixbuf::Pixbuf;
+use relm4::gtk::{gdk, gdk_pixbuf::Pixbuf};
 use relm4::{Sender, gtk};
 use resource::resource;
 
@@ -25,13 +25,19 @@ use crate::{
     configuration::Action,
     math::{Vec2D, rect_ensure_in_bounds, rect_round},
     sketch_board::SketchBoardInput,
-    tools::{CropTool, Drawable, Tool},
+    style::Color,
+    tools::{
+        CropTool, Drawable, PointerTool, SharedDrawables, Tool, Tools, ToolsManager,
+        apply_redactions, collect_redactions, draw_redaction,
+    },
 };
 
-use super::{font_stack, set_font_stack};
+use super::{ZOrder, font_stack, set_font_stack};
 
 const TRANSPARENCY_SQUARE_SIZE: usize = 64;
 
+static LOAD_FN: fn(&str) -> *const std::ffi::c_void = |s| epoxy::get_proc_addr(s) as *const _;
+
 #[derive(Default)]
 pub struct FemtoVGArea {
     canvas: RefCell<Option<femtovg::Canvas<femtovg::renderer::OpenGl>>>,
@@ -47,9 +53,10 @@ pub struct FemtoVgAreaMut {
     transparent_background_id: Option<femtovg::ImageId>,
     active_tool: Rc<RefCell<dyn Tool>>,
     crop_tool: Rc<RefCell<CropTool>>,
+    pointer_tool: Rc<RefCell<PointerTool>>,
     scale_factor: f32,
     offset: Vec2D,
-    drawables: Vec<Box<dyn Drawable>>,
+    drawables: SharedDrawables,
     undo_stack: Vec<HistoryEntry>,
     redo_stack: Vec<HistoryEntry>,
     zoom_scale: f32,
@@ -64,6 +71,16 @@ pub struct FemtoVgAreaMut {
 enum HistoryEntry {
     Drawable(Box<dyn Drawable>),
     ClearAll(Vec<Box<dyn Drawable>>),
+    // the other state of drawables edited in place, swapped in on undo and redo
+    Edit(Vec<(usize, Box<dyn Drawable>)>),
+    // drawables removed from the given indices, reinserted on undo
+    Delete(Vec<(usize, Box<dyn Drawable>)>),
+    // drawables present at the given indices, removed on undo
+    Insert(Vec<usize>),
+    // the order to restore, the drawable at position i is taken from index order[i]
+    Reorder(Vec<usize>),
+    // all drawables before an edit changing their number, swapped in on undo and redo
+    Replace(Vec<Box<dyn Drawable>>),
 }
 
 #[glib::object_subclass]
@@ -165,29 +182,18 @@ impl FemtoVGArea {
         &self,
         sender: Sender<SketchBoardInput>,
         crop_tool: Rc<RefCell<CropTool>>,
+        pointer_tool: Rc<RefCell<PointerTool>>,
+        drawables: SharedDrawables,
         active_tool: Rc<RefCell<dyn Tool>>,
         background_image: Pixbuf,
     ) {
-        let initial_scale = APP_CONFIG.read().input_scale().unwrap_or(0.0);
-        self.inner().replace(FemtoVgAreaMut {
-            background_image,
-            background_image_id: None,
-            transparent_background_id: None,
-            active_tool,
+        self.inner().replace(FemtoVgAreaMut::new(
             crop_tool,
-            scale_factor: 1.0,
-            offset: Vec2D::zero(),
-            drawables: Vec::new(),
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            zoom_scale: initial_scale,
-            pointer_offset: Vec2D::zero(),
-            last_offset: Vec2D::zero(),
-            drag_offset: Vec2D::zero(),
-            last_scale: initial_scale,
-            is_drag: false,
-            is_reset: false,
-        });
+            pointer_tool,
+            drawables,
+            active_tool,
+            background_image,
+        ));
         self.sender.borrow_mut().replace(sender);
     }
     fn ensure_canvas(&self) {
@@ -205,89 +211,10 @@ impl FemtoVGArea {
         }
     }
 
-    fn build_text_context(&self) -> Result<(femtovg::TextContext, Vec<FontId>)> {
-        let text_context = femtovg::TextContext::default();
-        let mut loaded_fonts = Vec::new();
-        let mut loaded_paths = HashSet::<(PathBuf, u32)>::new();
-
-        let app_config = APP_CONFIG.read();
-        let fontconfig = Fontconfig::new();
-
-        let mut load_font = |family: &str, style: Option<&str>| -> Result<FontId> {
-            let fc = fontconfig
-                .as_ref()
-                .ok_or(anyhow::anyhow!("Could not initialize Fontconfig"))?;
-
-            let font = fc
-                .find(family, style)
-                .map_err(|e| anyhow::anyhow!("Font family '{}' not found: {}", family, e))?;
-
-            let face_index = font.index.unwrap_or(0).max(0) as u32;
-
-            if !loaded_paths.insert((font.path.clone(), face_index)) {
-                return Err(anyhow::anyhow!("Font '{}' already loaded", family));
-            }
-            let data = std::fs::read(&font.path)
-                .map_err(|e| anyhow::anyhow!("Failed to read font file: {}", e))?;
-
-            text_context
-                .add_shared_font_with_index(data, face_index)
-                .map_err(|e| anyhow::anyhow!("Failed to load font: {}", e))
-        };
-
-        match load_font(
-            app_config.font().family().unwrap_or(""),
-            app_config.font().style(),
-        ) {
-            Ok(id) => {
-                loaded_fonts.push(id);
-            }
-            Err(e) => {
-                eprintln!("Primary font: {}", e);
-            }
-        }
-
-        if loaded_fonts.is_empty() {
-            let fallback = text_context
-                .add_font_mem(&resource!("src/assets/Roboto-Regular.ttf"))
-                .expect("Cannot add font");
-            loaded_fonts.push(fallback);
-        }
-
-        for family in app_config.font().fallback() {
-            match load_font(family, None) {
-                Ok(id) => {
-                    loaded_fonts.push(id);
-                }
-                Err(e) => {
-                    eprintln!("Fallback font: {}", e);
-                }
-            }
-        }
-
-        // Append a color emoji font as the last fallback so emoji render out of the box
-        // (femtovg's default image-loading feature draws their bitmap/COLR glyphs).
-        // fontconfig resolves the generic "emoji" family to the installed color emoji
-        // font, e.g. Noto Color Emoji. It is skipped if already loaded or unavailable.
-        // reconsider with #309
-        match load_font("emoji", None) {
-            Ok(id) => {
-                loaded_fonts.push(id);
-            }
-            Err(e) => {
-                eprintln!("Emoji font: {}", e);
-            }
-        }
-
-        Ok((text_context, loaded_fonts))
-    }
-
     fn setup_canvas(&self) -> Result<femtovg::Canvas<femtovg::renderer::OpenGl>> {
         let widget = self.obj();
         widget.attach_buffers();
 
-        static LOAD_FN: fn(&str) -> *const std::ffi::c_void =
-            |s| epoxy::get_proc_addr(s) as *const _;
         // SAFETY: Need to get the framebuffer id that gtk expects us to draw into, so
         // femtovg knows which framebuffer to bind. This is safe as long as we
         // call attach_buffers beforehand. Also unbind it here just in case,
@@ -303,7 +230,7 @@ impl FemtoVGArea {
         };
         renderer.set_screen_target(Some(fbo));
 
-        let (text_context, loaded_fonts) = self.build_text_context()?;
+        let (text_context, loaded_fonts) = build_text_context()?;
         let canvas = Canvas::new_with_text_context(renderer, text_context)?;
 
         set_font_stack(loaded_fonts.clone());
@@ -327,17 +254,61 @@ impl FemtoVGArea {
 }
 
 impl FemtoVgAreaMut {
+    fn new(
+        crop_tool: Rc<RefCell<CropTool>>,
+        pointer_tool: Rc<RefCell<PointerTool>>,
+        drawables: SharedDrawables,
+        active_tool: Rc<RefCell<dyn Tool>>,
+        background_image: Pixbuf,
+    ) -> Self {
+        let initial_scale = APP_CONFIG.read().input_scale().unwrap_or(0.0);
+        Self {
+            background_image,
+            background_image_id: None,
+            transparent_background_id: None,
+            active_tool,
+            crop_tool,
+            pointer_tool,
+            scale_factor: 1.0,
+            offset: Vec2D::zero(),
+            drawables,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            zoom_scale: initial_scale,
+            pointer_offset: Vec2D::zero(),
+            last_offset: Vec2D::zero(),
+            drag_offset: Vec2D::zero(),
+            last_scale: initial_scale,
+            is_drag: false,
+            is_reset: false,
+        }
+    }
+
     pub fn commit(&mut self, drawable: Box<dyn Drawable>) {
         self.undo_stack
             .push(HistoryEntry::Drawable(drawable.clone_box()));
-        self.drawables.push(drawable);
+        self.drawables.borrow_mut().push(drawable);
+        self.redo_stack.clear();
+    }
+
+    pub fn commit_edit(&mut self, edit: Vec<(usize, Box<dyn Drawable>)>) {
+        self.undo_stack.push(HistoryEntry::Edit(edit));
+        self.redo_stack.clear();
+    }
+
+    pub fn commit_replace(&mut self, previous: Vec<Box<dyn Drawable>>) {
+        self.undo_stack.push(HistoryEntry::Replace(previous));
         self.redo_stack.clear();
     }
 
     pub fn undo(&mut self) -> bool {
         match self.undo_stack.pop() {
             Some(HistoryEntry::Drawable(history_drawable)) => {
-                let mut drawable = self.drawables.pop().unwrap_or(history_drawable);
+                let mut drawable = self
+                    .drawables
+                    .borrow_mut()
+                    .pop()
+                    .unwrap_or(history_drawable);
                 drawable.handle_undo();
                 self.redo_stack.push(HistoryEntry::Drawable(drawable));
                 true
@@ -346,6 +317,11 @@ impl FemtoVgAreaMut {
                 self.restore_clear_all(drawables);
                 true
             }
+            Some(entry) => {
+                let entry = self.revert(entry);
+                self.redo_stack.push(entry);
+                true
+            }
             None => false,
         }
     }
@@ -356,19 +332,96 @@ impl FemtoVgAreaMut {
                 drawable.handle_redo();
                 self.undo_stack
                     .push(HistoryEntry::Drawable(drawable.clone_box()));
-                self.drawables.push(drawable);
+                self.drawables.borrow_mut().push(drawable);
                 true
             }
             Some(HistoryEntry::ClearAll(_)) => {
                 self.apply_clear_all();
                 true
             }
+            Some(entry) => {
+                let entry = self.revert(entry);
+                self.undo_stack.push(entry);
+                true
+            }
             None => false,
         }
     }
 
+    pub fn delete(&mut self, indices: Vec<usize>) -> bool {
+        let removed = self.remove_drawables(indices);
+        if removed.is_empty() {
+            return false;
+        }
+
+        self.undo_stack.push(HistoryEntry::Delete(removed));
+        self.redo_stack.clear();
+        true
+    }
+
+    /// Add drawables on top of all others and return their indices.
+    pub fn insert(&mut self, drawables: Vec<Box<dyn Drawable>>) -> Vec<usize> {
+        let start = self.drawables.borrow().len();
+        let indices = self.insert_drawables(
+            drawables
+                .into_iter()
+                .enumerate()
+                .map(|(i, d)| (start + i, d))
+                .collect(),
+        );
+        if !indices.is_empty() {
+            self.undo_stack.push(HistoryEntry::Insert(indices.clone()));
+            self.redo_stack.clear();
+        }
+        indices
+    }
+
+    /// Move the selected drawables in the drawing order and return their new indices,
+    /// or None if nothing moved.
+    pub fn reorder(&mut self, selection: &[usize], z_order: ZOrder) -> Option<Vec<usize>> {
+        let len = self.drawables.borrow().len();
+        let selected = |index: &usize| selection.contains(index);
+
+        let mut order: Vec<usize> = (0..len).collect();
+        match z_order {
+            ZOrder::Raise => {
+                for i in (1..len).rev() {
+                    if selected(&order[i - 1]) && !selected(&order[i]) {
+                        order.swap(i - 1, i);
+                    }
+                }
+            }
+            ZOrder::Lower => {
+                for i in 1..len {
+                    if selected(&order[i]) && !selected(&order[i - 1]) {
+                        order.swap(i - 1, i);
+                    }
+                }
+            }
+            // stable sorts keep the relative order within both groups
+            ZOrder::ToFront => order.sort_by_key(|index| selected(index)),
+            ZOrder::ToBack => order.sort_by_key(|index| !selected(index)),
+        }
+
+        if order.iter().enumerate().all(|(i, index)| i == *index) {
+            return None;
+        }
+
+        let new_selection = order
+            .iter()
+            .enumerate()
+            .filter(|(_, index)| selected(index))
+            .map(|(i, _)| i)
+            .collect();
+
+        let restore = self.apply_order(order);
+        self.undo_stack.push(HistoryEntry::Reorder(restore));
+        self.redo_stack.clear();
+        Some(new_selection)
+    }
+
     pub fn clear_all(&mut self) -> bool {
-        if self.drawables.is_empty() {
+        if self.drawables.borrow().is_empty() {
             return false;
         }
 
@@ -377,8 +430,12 @@ impl FemtoVgAreaMut {
         true
     }
 
+    pub fn background_image(&self) -> Pixbuf {
+        self.background_image.clone()
+    }
+
     fn apply_clear_all(&mut self) {
-        let mut drawables = std::mem::take(&mut self.drawables);
+        let mut drawables = std::mem::take(&mut *self.drawables.borrow_mut());
         Self::handle_drawables_undo(&mut drawables);
         self.undo_stack.push(HistoryEntry::ClearAll(drawables));
     }
@@ -387,7 +444,90 @@ impl FemtoVgAreaMut {
         Self::handle_drawables_redo(&mut drawables);
         self.redo_stack
             .push(HistoryEntry::ClearAll(Self::clone_drawables(&drawables)));
-        self.drawables = drawables;
+        *self.drawables.borrow_mut() = drawables;
+    }
+
+    /// Apply the inverse of an entry and return the entry restoring the current state.
+    fn revert(&mut self, entry: HistoryEntry) -> HistoryEntry {
+        match entry {
+            HistoryEntry::Edit(edit) => HistoryEntry::Edit(self.swap_edit(edit)),
+            HistoryEntry::Delete(removed) => HistoryEntry::Insert(self.insert_drawables(removed)),
+            HistoryEntry::Insert(indices) => HistoryEntry::Delete(self.remove_drawables(indices)),
+            HistoryEntry::Reorder(order) => HistoryEntry::Reorder(self.apply_order(order)),
+            HistoryEntry::Replace(drawables) => HistoryEntry::Replace(std::mem::replace(
+                &mut *self.drawables.borrow_mut(),
+                drawables,
+            )),
+            other => other,
+        }
+    }
+
+    /// Remove the drawables at the given indices and return them together with their index,
+    /// in ascending order.
+    fn remove_drawables(&mut self, mut indices: Vec<usize>) -> Vec<(usize, Box<dyn Drawable>)> {
+        let mut drawables = self.drawables.borrow_mut();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let mut removed = Vec::with_capacity(indices.len());
+        for index in indices.into_iter().rev() {
+            if index < drawables.len() {
+                removed.push((index, drawables.remove(index)));
+            }
+        }
+        removed.reverse();
+        removed
+    }
+
+    /// Insert drawables at their index, in ascending order, and return the indices.
+    fn insert_drawables(&mut self, inserted: Vec<(usize, Box<dyn Drawable>)>) -> Vec<usize> {
+        let mut drawables = self.drawables.borrow_mut();
+        inserted
+            .into_iter()
+            .map(|(index, drawable)| {
+                let index = index.min(drawables.len());
+                drawables.insert(index, drawable);
+                index
+            })
+            .collect()
+    }
+
+    /// Rearrange the drawables so that position i holds the one from index order[i] and
+    /// return the order restoring the previous arrangement.
+    fn apply_order(&mut self, order: Vec<usize>) -> Vec<usize> {
+        let mut drawables = self.drawables.borrow_mut();
+        if order.len() != drawables.len() {
+            return (0..drawables.len()).collect();
+        }
+
+        let mut previous: Vec<_> = std::mem::take(&mut *drawables)
+            .into_iter()
+            .map(Some)
+            .collect();
+        *drawables = order
+            .iter()
+            .filter_map(|index| previous.get_mut(*index)?.take())
+            .collect();
+
+        let mut restore = vec![0; order.len()];
+        for (i, index) in order.into_iter().enumerate() {
+            restore[index] = i;
+        }
+        restore
+    }
+
+    /// Swap the drawables of an edit with the current ones and return the replaced state.
+    fn swap_edit(
+        &mut self,
+        mut edit: Vec<(usize, Box<dyn Drawable>)>,
+    ) -> Vec<(usize, Box<dyn Drawable>)> {
+        let mut drawables = self.drawables.borrow_mut();
+        for (index, drawable) in edit.iter_mut() {
+            if let Some(d) = drawables.get_mut(*index) {
+                std::mem::swap(d, drawable);
+            }
+        }
+        edit
     }
 
     fn handle_drawables_undo(drawables: &mut [Box<dyn Drawable>]) {
@@ -463,7 +603,21 @@ impl FemtoVgAreaMut {
         canvas.set_render_target(femtovg::RenderTarget::Screen);
         canvas.delete_image(image_id);
 
-        Ok(result?)
+        let mut result = result?;
+        apply_redactions(&mut result, pos, &self.redactions());
+        Ok(result)
+    }
+
+    /// The redactions of the committed drawables and the active tool.
+    fn redactions(&self) -> Vec<((Vec2D, Vec2D), Color)> {
+        let drawables = self.drawables.borrow();
+        let active_tool = self.active_tool.borrow();
+        collect_redactions(
+            drawables
+                .iter()
+                .map(|d| d.as_ref())
+                .chain(active_tool.get_drawable()),
+        )
     }
 
     pub fn render_framebuffer(
@@ -514,13 +668,31 @@ impl FemtoVgAreaMut {
                 self.background_image.height() as f32,
             ),
         );
-        // render the whole stack
-        for d in &mut self.drawables {
+        // render the whole stack, backdrops change the image and go first
+        let drawables = self.drawables.borrow();
+        let (backdrops, others): (Vec<_>, Vec<_>) = drawables.iter().partition(|d| d.is_backdrop());
+        for d in backdrops.into_iter().chain(others) {
             d.draw(canvas, font, bounds)?;
         }
 
-        // render active tool
-        if let Some(d) = self.active_tool.borrow().get_drawable() {
+        // magnifiers enlarge the original pixels, obscure them again where they are blurred
+        for (source, inset) in drawables.iter().filter_map(|d| d.magnification()) {
+            for d in drawables.iter() {
+                d.draw_magnified(canvas, bounds, source, inset)?;
+            }
+        }
+        drop(drawables);
+
+        // render redactions again, nothing drawn after them may show on top
+        for (rect, color) in collect_redactions(self.drawables.borrow().iter().map(|d| d.as_ref()))
+        {
+            draw_redaction(canvas, rect, color);
+        }
+
+        // render active tool, previews are only shown on screen
+        if let Some(d) = self.active_tool.borrow().get_drawable()
+            && (onscreen || !d.is_preview())
+        {
             d.draw(canvas, font, bounds)?;
         }
 
@@ -529,6 +701,11 @@ impl FemtoVgAreaMut {
             c.draw(canvas, font, bounds)?;
         }
 
+        // render selection of the pointer tool
+        if onscreen {
+            self.pointer_tool.borrow().draw_selection(canvas)?;
+        }
+
         canvas.flush();
         Ok(())
     }
@@ -811,3 +988,214 @@ impl FemtoVgAreaMut {
         self.is_drag = is_drag;
     }
 }
+
+/// Render the committed drawables onto `background_image` at its native resolution without
+/// a widget. Uses a GL context of the default display that is not bound to any window.
+pub fn render_offscreen(tools: &ToolsManager, background_image: Pixbuf) -> Result<ImgVec<RGBA8>> {
+    let display = gdk::Display::default().ok_or(anyhow!("No display available"))?;
+    let context = display.create_gl_context()?;
+    context.realize()?;
+    context.make_current();
+
+    let result = (|| {
+        // SAFETY: the GL context created above is current
+        let renderer = unsafe { renderer::OpenGl::new_from_function(LOAD_FN) }?;
+        let (text_context, loaded_fonts) = build_text_context()?;
+        let mut canvas = Canvas::new_with_text_context(renderer, text_context)?;
+        canvas.set_size(
+            background_image.width() as u32,
+            background_image.height() as u32,
+            1.0,
+        );
+
+        set_font_stack(loaded_fonts.clone());
+        let font = *loaded_fonts
+            .first()
+            .ok_or(anyhow!("No font could be loaded"))?;
+
+        FemtoVgAreaMut::new(
+            tools.get_crop_tool(),
+            tools.get_pointer_tool(),
+            tools.get_drawables(),
+            tools.get(&Tools::Pointer),
+            background_image,
+        )
+        .render_native_resolution(&mut canvas, font)
+    })();
+
+    gdk::GLContext::clear_current();
+    result
+}
+
+fn build_text_context() -> Result<(femtovg::TextContext, Vec<FontId>)> {
+    let text_context = femtovg::TextContext::default();
+    let mut loaded_fonts = Vec::new();
+    let mut loaded_paths = HashSet::<(PathBuf, u32)>::new();
+
+    let app_config = APP_CONFIG.read();
+    let fontconfig = Fontconfig::new();
+
+    let mut load_font = |family: &str, style: Option<&str>| -> Result<FontId> {
+        let fc = fontconfig
+            .as_ref()
+            .ok_or(anyhow::anyhow!("Could not initialize Fontconfig"))?;
+
+        let font = fc
+            .find(family, style)
+            .map_err(|e| anyhow::anyhow!("Font family '{}' not found: {}", family, e))?;
+
+        let face_index = font.index.unwrap_or(0).max(0) as u32;
+
+        if !loaded_paths.insert((font.path.clone(), face_index)) {
+            return Err(anyhow::anyhow!("Font '{}' already loaded", family));
+        }
+        let data = std::fs::read(&font.path)
+            .map_err(|e| anyhow::anyhow!("Failed to read font file: {}", e))?;
+
+        text_context
+            .add_shared_font_with_index(data, face_index)
+            .map_err(|e| anyhow::anyhow!("Failed to load font: {}", e))
+    };
+
+    match load_font(
+        app_config.font().family().unwrap_or(""),
+        app_config.font().style(),
+    ) {
+        Ok(id) => {
+            loaded_fonts.push(id);
+        }
+        Err(e) => {
+            eprintln!("Primary font: {}", e);
+        }
+    }
+
+    if loaded_fonts.is_empty() {
+        let fallback = text_context
+            .add_font_mem(&resource!("src/assets/Roboto-Regular.ttf"))
+            .expect("Cannot add font");
+        loaded_fonts.push(fallback);
+    }
+
+    for family in app_config.font().fallback() {
+        match load_font(family, None) {
+            Ok(id) => {
+                loaded_fonts.push(id);
+            }
+            Err(e) => {
+                eprintln!("Fallback font: {}", e);
+            }
+        }
+    }
+
+    // Append a color emoji font as the last fallback so emoji render out of the box
+    // (femtovg's default image-loading feature draws their bitmap/COLR glyphs).
+    // fontconfig resolves the generic "emoji" family to the installed color emoji
+    // font, e.g. Noto Color Emoji. It is skipped if already loaded or unavailable.
+    // reconsider with #309
+    match load_font("emoji", None) {
+        Ok(id) => {
+            loaded_fonts.push(id);
+        }
+        Err(e) => {
+            eprintln!("Emoji font: {}", e);
+        }
+    }
+
+    Ok((text_context, loaded_fonts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FemtoVgAreaMut;
+    use crate::femtovg_area::ZOrder;
+    use crate::math::Vec2D;
+    use crate::style::Style;
+    use crate::tools::{CropTool, Drawable, Line, PointerTool, SharedDrawables};
+    use relm4::gtk::gdk_pixbuf::{Colorspace, Pixbuf};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// An area holding lines told apart by the x coordinate of their start, 0 to count - 1.
+    fn area(count: usize) -> FemtoVgAreaMut {
+        let drawables: SharedDrawables = Rc::new(RefCell::new(
+            (0..count)
+                .map(|i| {
+                    let start = Vec2D::new(i as f32, 0.0);
+                    Box::new(Line::new(
+                        start,
+                        start + Vec2D::new(0.0, 1.0),
+                        Style::default(),
+                    )) as Box<dyn Drawable>
+                })
+                .collect(),
+        ));
+        let pointer_tool = Rc::new(RefCell::new(PointerTool::new(drawables.clone())));
+        FemtoVgAreaMut::new(
+            Rc::new(RefCell::new(CropTool::default())),
+            pointer_tool.clone(),
+            drawables,
+            pointer_tool,
+            Pixbuf::new(Colorspace::Rgb, true, 8, 1, 1).expect("create background"),
+        )
+    }
+
+    fn order(area: &FemtoVgAreaMut) -> Vec<usize> {
+        area.drawables
+            .borrow()
+            .iter()
+            .map(|d| d.bounds().expect("line bounds").0.x as usize)
+            .collect()
+    }
+
+    #[test]
+    fn reorder_moves_selection_by_one() {
+        let mut area = area(4);
+
+        assert_eq!(area.reorder(&[1], ZOrder::Raise), Some(vec![2]));
+        assert_eq!(order(&area), [0, 2, 1, 3]);
+
+        assert_eq!(area.reorder(&[0, 3], ZOrder::Lower), Some(vec![0, 2]));
+        assert_eq!(order(&area), [0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn reorder_to_front_and_back_keeps_relative_order() {
+        let mut area = area(4);
+
+        assert_eq!(area.reorder(&[0, 2], ZOrder::ToFront), Some(vec![2, 3]));
+        assert_eq!(order(&area), [1, 3, 0, 2]);
+
+        assert_eq!(area.reorder(&[1, 3], ZOrder::ToBack), Some(vec![0, 1]));
+        assert_eq!(order(&area), [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn reorder_without_effect_is_not_recorded() {
+        let mut area = area(3);
+
+        assert_eq!(area.reorder(&[0], ZOrder::Lower), None);
+        assert_eq!(area.reorder(&[1, 2], ZOrder::ToFront), None);
+        assert!(!area.undo());
+    }
+
+    #[test]
+    fn reorder_round_trips_through_undo_and_redo() {
+        let mut area = area(5);
+        area.reorder(&[0, 3], ZOrder::ToFront);
+        let reordered = order(&area);
+
+        assert!(area.undo());
+        assert_eq!(order(&area), [0, 1, 2, 3, 4]);
+        assert!(area.redo());
+        assert_eq!(order(&area), reordered);
+    }
+
+    #[test]
+    fn apply_order_returns_the_inverse_order() {
+        let mut area = area(4);
+
+        let restore = area.apply_order(vec![2, 0, 3, 1]);
+        assert_eq!(order(&area), [2, 0, 3, 1]);
+        area.apply_order(restore);
+        assert_eq!(order(&area), [0, 1, 2, 3]);
+    }
+}
@@ -14,9 +14,20 @@ use crate::{
     configuration::Action,
     math::Vec2D,
     sketch_board::SketchBoardInput,
-    tools::{CropTool, Drawable, Tool},
+    tools::{CropTool, Drawable, PointerTool, SharedDrawables, Tool},
 };
 
+/// Where to move drawables in the drawing order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZOrder {
+    Raise,
+    Lower,
+    ToFront,
+    ToBack,
+}
+
+pub use imp::render_offscreen;
+
 static FONT_STACK: OnceLock<Vec<FontId>> = OnceLock::new();
 
 pub fn set_font_stack(fonts: Vec<FontId>) {
@@ -55,6 +66,46 @@ impl FemtoVGArea {
             .expect("Did you call init before using FemtoVgArea?")
             .commit(drawable);
     }
+
+    pub fn delete(&mut self, indices: Vec<usize>) -> bool {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .delete(indices)
+    }
+
+    pub fn insert(&mut self, drawables: Vec<Box<dyn Drawable>>) -> Vec<usize> {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .insert(drawables)
+    }
+
+    pub fn reorder(&mut self, selection: &[usize], z_order: ZOrder) -> Option<Vec<usize>> {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .reorder(selection, z_order)
+    }
+
+    pub fn commit_edit(&mut self, edit: Vec<(usize, Box<dyn Drawable>)>) {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .commit_edit(edit);
+    }
+    pub fn commit_replace(&mut self, previous: Vec<Box<dyn Drawable>>) {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .commit_replace(previous);
+    }
+
     pub fn undo(&mut self) -> bool {
         self.imp()
             .inner()
@@ -80,6 +131,15 @@ impl FemtoVGArea {
             .clear_all()
     }
 
+    /// The image being annotated, without any annotations.
+    pub fn background_image(&self) -> Pixbuf {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .background_image()
+    }
+
     pub fn abs_canvas_to_image_coordinates(&self, input: Vec2D) -> Vec2D {
         self.imp()
             .inner()
@@ -100,11 +160,19 @@ impl FemtoVGArea {
         &mut self,
         sender: Sender<SketchBoardInput>,
         crop_tool: Rc<RefCell<CropTool>>,
+        pointer_tool: Rc<RefCell<PointerTool>>,
+        drawables: SharedDrawables,
         active_tool: Rc<RefCell<dyn Tool>>,
         background_image: Pixbuf,
     ) {
-        self.imp()
-            .init(sender, crop_tool, active_tool, background_image);
+        self.imp().init(
+            sender,
+            crop_tool,
+            pointer_tool,
+            drawables,
+            active_tool,
+            background_image,
+        );
     }
 
     pub fn set_zoom_scale(&self, factor: f32) {
@@ -29,6 +29,16 @@ pub enum ShortcutCommand {
     PanDown,
     Zoom(i16),
     DeleteSelection,
+    RaiseSelection,
+    LowerSelection,
+    SelectionToFront,
+    SelectionToBack,
+    CopySelection,
+    CutSelection,
+    PasteSelection,
+    DuplicateSelection,
+    InsertImage,
+    PasteImage,
     RunConfiguredActions(ActionTrigger),
 
     // top toolbar
@@ -46,6 +56,7 @@ pub enum ShortcutCommand {
     FocusAnnotationSizeFactor,
     ToggleFill,
     ToggleRoundCaps,
+    CycleStrokePattern,
 }
 
 impl fmt::Display for ShortcutCommand {
@@ -62,6 +73,16 @@ impl fmt::Display for ShortcutCommand {
                 return Ok(());
             }
             ShortcutCommand::DeleteSelection => "delete-selection",
+            ShortcutCommand::RaiseSelection => "raise-selection",
+            ShortcutCommand::LowerSelection => "lower-selection",
+            ShortcutCommand::SelectionToFront => "selection-to-front",
+            ShortcutCommand::SelectionToBack => "selection-to-back",
+            ShortcutCommand::CopySelection => "copy-selection",
+            ShortcutCommand::CutSelection => "cut-selection",
+            ShortcutCommand::PasteSelection => "paste-selection",
+            ShortcutCommand::DuplicateSelection => "duplicate-selection",
+            ShortcutCommand::InsertImage => "insert-image",
+            ShortcutCommand::PasteImage => "paste-image",
             ShortcutCommand::RunConfiguredActions(ActionTrigger::Escape) => "run-actions-on-escape",
             ShortcutCommand::RunConfiguredActions(ActionTrigger::Enter) => "run-actions-on-enter",
             ShortcutCommand::ToggleToolbars => "toggle-toolbars",
@@ -100,6 +121,7 @@ impl fmt::Display for ShortcutCommand {
             ShortcutCommand::FocusAnnotationSizeFactor => "focus-annotation-size-factor",
             ShortcutCommand::ToggleFill => "toggle-fill",
             ShortcutCommand::ToggleRoundCaps => "toggle-round-caps",
+            ShortcutCommand::CycleStrokePattern => "cycle-stroke-pattern",
         };
         write!(f, "{}", name)
     }
@@ -128,6 +150,16 @@ impl FromStr for ShortcutCommand {
                 Err(ParseCommandError)
             }
             "delete-selection" => Ok(ShortcutCommand::DeleteSelection),
+            "raise-selection" => Ok(ShortcutCommand::RaiseSelection),
+            "lower-selection" => Ok(ShortcutCommand::LowerSelection),
+            "selection-to-front" => Ok(ShortcutCommand::SelectionToFront),
+            "selection-to-back" => Ok(ShortcutCommand::SelectionToBack),
+            "copy-selection" => Ok(ShortcutCommand::CopySelection),
+            "cut-selection" => Ok(ShortcutCommand::CutSelection),
+            "paste-selection" => Ok(ShortcutCommand::PasteSelection),
+            "duplicate-selection" => Ok(ShortcutCommand::DuplicateSelection),
+            "insert-image" => Ok(ShortcutCommand::InsertImage),
+            "paste-image" => Ok(ShortcutCommand::PasteImage),
             "run-actions-on-escape" => {
                 Ok(ShortcutCommand::RunConfiguredActions(ActionTrigger::Escape))
             }
@@ -171,6 +203,7 @@ impl FromStr for ShortcutCommand {
             "focus-annotation-size-factor" => Ok(ShortcutCommand::FocusAnnotationSizeFactor),
             "toggle-fill" => Ok(ShortcutCommand::ToggleFill),
             "toggle-round-caps" => Ok(ShortcutCommand::ToggleRoundCaps),
+            "cycle-stroke-pattern" => Ok(ShortcutCommand::CycleStrokePattern),
             _ => Err(ParseCommandError),
         }
     }
@@ -250,6 +283,18 @@ impl ShortcutRegistry {
         registry.add_key_binding("<Control>plus", SC::Zoom(1));
         registry.add_key_binding("<Control>minus", SC::Zoom(-1));
         registry.add_key_binding("Delete", SC::DeleteSelection);
+        registry.add_key_binding("<Control>bracketright", SC::RaiseSelection);
+        registry.add_key_binding("<Control>bracketleft", SC::LowerSelection);
+        registry.add_key_binding("<Shift><Control>bracketright", SC::SelectionToFront);
+        registry.add_key_binding("<Shift><Control>bracketleft", SC::SelectionToBack);
+        // <Control>c is taken by save-to-clipboard
+        registry.add_key_binding("<Control>Insert", SC::CopySelection);
+        registry.add_key_binding("<Control>x", SC::CutSelection);
+        registry.add_key_binding("<Control>v", SC::PasteSelection);
+        registry.add_key_binding("<Shift>Insert", SC::PasteSelection);
+        registry.add_key_binding("<Control>d", SC::DuplicateSelection);
+        registry.add_key_binding("<Control>o", SC::InsertImage);
+        registry.add_key_binding("<Shift><Control>v", SC::PasteImage);
         registry.add_key_binding("<Shift>Delete", SC::ClearAll);
         registry.add_key_binding("Escape", SC::RunConfiguredActions(ActionTrigger::Escape));
         registry.add_key_binding("Return", SC::RunConfiguredActions(ActionTrigger::Enter));
@@ -278,6 +323,14 @@ impl ShortcutRegistry {
         registry.add_key_binding("m", SC::SelectTool(Tools::Marker));
         registry.add_key_binding("u", SC::SelectTool(Tools::Blur));
         registry.add_key_binding("g", SC::SelectTool(Tools::Highlight));
+        registry.add_key_binding("x", SC::SelectTool(Tools::Redact));
+        registry.add_key_binding("o", SC::SelectTool(Tools::Magnifier));
+        registry.add_key_binding("l", SC::SelectTool(Tools::Spotlight));
+        registry.add_key_binding("q", SC::SelectTool(Tools::Callout));
+        registry.add_key_binding("n", SC::SelectTool(Tools::Measure));
+        registry.add_key_binding("y", SC::SelectTool(Tools::Eyedropper));
+        registry.add_key_binding("a", SC::SelectTool(Tools::Stamp));
+        registry.add_key_binding("w", SC::SelectTool(Tools::Eraser));
         registry.add_key_binding("<Control>c", SC::RunAction(A::SaveToClipboard));
         registry.add_key_binding("<Control><Alt>c", SC::RunAction(A::CopyFilepathToClipboard));
         registry.add_key_binding("<Control>s", SC::RunAction(A::SaveToFile));
@@ -292,6 +345,7 @@ impl ShortcutRegistry {
         registry.add_key_binding("minus", SC::CycleSize);
         registry.add_key_binding("s", SC::FocusAnnotationSizeFactor);
         registry.add_key_binding("f", SC::ToggleFill);
+        registry.add_key_binding("d", SC::CycleStrokePattern);
 
         // merge with config keybinds, allowing config to override defaults
         for (kb_str, tool_or_cmd) in APP_CONFIG.read().keybinds() {
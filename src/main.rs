@@ -2,7 +2,7 @@ use configuration::{APP_CONFIG, Configuration};
 use relm4::gtk::gdk_pixbuf::{Pixbuf, PixbufLoader};
 use relm4::gtk::gio::{Application, ApplicationFlags};
 use relm4::gtk::prelude::*;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::ops::Deref;
 use std::process::exit;
 use std::sync::{LazyLock, RwLock};
@@ -24,6 +24,7 @@ use sketch_board::SketchBoardOutput;
 use ui::toolbars::{StyleToolbar, StyleToolbarInput, ToolsToolbar, ToolsToolbarInput};
 use xdg::BaseDirectories;
 
+mod annotations;
 mod configuration;
 mod femtovg_area;
 mod icons;
@@ -31,14 +32,21 @@ mod ime;
 mod keybindings;
 mod math;
 mod notification;
+mod output_format;
+mod project;
 mod sketch_board;
 mod style;
+mod svg;
 mod tools;
 mod ui;
 
+use crate::annotations::AnnotationSpec;
+use crate::femtovg_area::render_offscreen;
+use crate::project::Project;
 use crate::sketch_board::{SketchBoard, SketchBoardInput};
-use crate::style::{Color, Size};
-use crate::tools::Tools;
+use crate::style::{Color, Size, StrokePattern};
+use crate::svg::SvgExport;
+use crate::tools::{Tools, ToolsManager};
 
 pub static START_TIME: LazyLock<chrono::DateTime<chrono::Local>> =
     LazyLock::new(chrono::Local::now);
@@ -83,6 +91,7 @@ enum AppInput {
     SetColor(Color),
     SetFill(bool),
     SetRoundCaps(bool),
+    SetStrokePattern(StrokePattern),
     SetSize(Size),
     FocusAnnotationSizeFactorShortcut,
     ScaleFactorChanged,
@@ -224,7 +233,7 @@ impl App {
 
 #[relm4::component]
 impl Component for App {
-    type Init = Pixbuf;
+    type Init = Project;
     type Input = AppInput;
     type Output = ();
     type CommandOutput = AppCommandOutput;
@@ -308,6 +317,11 @@ impl Component for App {
                     .sender()
                     .emit(StyleToolbarInput::SetRoundCaps(round_caps_enabled));
             }
+            AppInput::SetStrokePattern(pattern) => {
+                self.style_toolbar
+                    .sender()
+                    .emit(StyleToolbarInput::SetStrokePattern(pattern));
+            }
             AppInput::SetSize(size) => {
                 self.style_toolbar
                     .sender()
@@ -367,17 +381,17 @@ impl Component for App {
     }
 
     fn init(
-        image: Self::Init,
+        project: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         Self::apply_style();
-        let image_dimensions = (image.width(), image.height());
+        let image_dimensions = (project.background.width(), project.background.height());
 
         // SketchBoard
         let sketch_board =
             SketchBoard::builder()
-                .launch(image)
+                .launch(project)
                 .forward(sender.input_sender(), |t| match t {
                     SketchBoardOutput::ToggleToolbarsDisplay => AppInput::ToggleToolbarsDisplay,
                     SketchBoardOutput::ToolSwitchShortcut(tool) => {
@@ -391,6 +405,9 @@ impl Component for App {
                     SketchBoardOutput::SetRoundCaps(round_caps_enabled) => {
                         AppInput::SetRoundCaps(round_caps_enabled)
                     }
+                    SketchBoardOutput::SetStrokePattern(pattern) => {
+                        AppInput::SetStrokePattern(pattern)
+                    }
                     SketchBoardOutput::SetSize(size) => AppInput::SetSize(size),
                     SketchBoardOutput::FocusAnnotationSizeFactorShortcut => {
                         AppInput::FocusAnnotationSizeFactorShortcut
@@ -533,20 +550,41 @@ fn run_satty() -> Result<()> {
     let config = APP_CONFIG.read();
 
     generate_profile_output!("loading image");
-    // load input image
-    let image = if config.input_filename() == "-" {
+    // load input image or project
+    let mut project = if config.input_filename() == "-" {
         let mut buf = Vec::<u8>::new();
         io::stdin().lock().read_to_end(&mut buf)?;
-        let pb_loader = PixbufLoader::new();
-        pb_loader.write(&buf)?;
-        pb_loader.close()?;
-        pb_loader
-            .pixbuf()
-            .ok_or(anyhow!("Conversion to Pixbuf failed"))?
+        if Project::is_project_data(&buf) {
+            Project::from_bytes(&buf)?
+        } else {
+            let pb_loader = PixbufLoader::new();
+            pb_loader.write(&buf)?;
+            pb_loader.close()?;
+            Project::from_image(
+                pb_loader
+                    .pixbuf()
+                    .ok_or(anyhow!("Conversion to Pixbuf failed"))?,
+            )
+        }
+    } else if Project::is_project_file(config.input_filename()) {
+        Project::load(config.input_filename())?
     } else {
-        Pixbuf::from_file(config.input_filename()).context("couldn't load image")?
+        Project::from_image(
+            Pixbuf::from_file(config.input_filename()).context("couldn't load image")?,
+        )
     };
 
+    if let Some(annotations) = config.annotations_filename() {
+        project
+            .drawables
+            .extend(AnnotationSpec::load(annotations)?.into_drawables());
+    }
+
+    if config.headless() {
+        generate_profile_output!("image loaded, rendering headless");
+        return run_headless(project);
+    }
+
     generate_profile_output!("image loaded, starting gui");
     // start GUI
     let app = relm4::main_application();
@@ -569,7 +607,7 @@ fn run_satty() -> Result<()> {
         icons::icon_names::RESOURCE_PREFIX,
     );
 
-    app.run::<App>(image);
+    app.run::<App>(project);
 
     match TEMP_DIR.write() {
         Ok(mut temp_dir) => {
@@ -589,6 +627,47 @@ fn run_satty() -> Result<()> {
     Ok(())
 }
 
+/// Save the project to the output filename without starting the GUI.
+fn run_headless(project: Project) -> Result<()> {
+    gtk::init()?;
+
+    let output_filename = APP_CONFIG
+        .read()
+        .output_filename()
+        .and_then(|o| SketchBoard::resolve_output_filename(o))
+        .ok_or(anyhow!("Headless mode requires an output filename"))?;
+
+    let tools = ToolsManager::new(&project.background);
+    tools.restore(project.drawables);
+    if let Some(crop) = project.crop {
+        tools.get_crop_tool().borrow_mut().restore(crop);
+    }
+
+    let data = if Project::is_project_file(&output_filename) {
+        Project::to_bytes(
+            &project.background,
+            tools.saved_drawables(),
+            tools.saved_crop(),
+        )?
+    } else if svg::is_svg_file(&output_filename) {
+        // rendering measures the markers and breaks the texts into lines
+        render_offscreen(&tools, project.background.clone())?;
+        SvgExport::new(&tools, project.background).to_bytes()?
+    } else {
+        let format = output_format::for_output_filename(&output_filename)
+            .ok_or(anyhow!("Unsupported output format of '{output_filename}'"))?;
+        let image = render_offscreen(&tools, project.background)?;
+        output_format::encode(&SketchBoard::image_to_pixbuf(image), format)?
+    };
+
+    if output_filename == "-" {
+        io::stdout().lock().write_all(&data)?;
+    } else {
+        fs::write(&output_filename, data).context("couldn't write output file")?;
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let _ = *START_TIME;
     // populate the APP_CONFIG from commandline and
@@ -4,7 +4,9 @@ use std::{
     ops::{Add, AddAssign, Mul, Sub, SubAssign},
 };
 
-#[derive(Default, Debug, Copy, Clone, PartialEq)]
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Vec2D {
     pub x: f32,
     pub y: f32,
@@ -214,3 +216,346 @@ pub fn rect_round(rect: (Vec2D, Vec2D)) -> (Vec2D, Vec2D) {
 
     (pos, size)
 }
+
+/**
+ * Check whether a point lies within a rectangle (pos, size) grown by margin on every side.
+ * The rectangle may have a negative size.
+ */
+pub fn rect_contains(rect: (Vec2D, Vec2D), point: Vec2D, margin: f32) -> bool {
+    let (pos, size) = rect_ensure_positive_size(rect.0, rect.1);
+
+    pos.x - margin <= point.x
+        && point.x <= pos.x + size.x + margin
+        && pos.y - margin <= point.y
+        && point.y <= pos.y + size.y + margin
+}
+
+/**
+ * Check whether two rectangles (pos, size) overlap.
+ * The rectangles may have a negative size.
+ */
+pub fn rect_intersects(a: (Vec2D, Vec2D), b: (Vec2D, Vec2D)) -> bool {
+    let (a_pos, a_size) = rect_ensure_positive_size(a.0, a.1);
+    let (b_pos, b_size) = rect_ensure_positive_size(b.0, b.1);
+
+    a_pos.x <= b_pos.x + b_size.x
+        && b_pos.x <= a_pos.x + a_size.x
+        && a_pos.y <= b_pos.y + b_size.y
+        && b_pos.y <= a_pos.y + a_size.y
+}
+
+/**
+ * Get the overlapping part of two rectangles (pos, size), or None if they don't overlap.
+ * The rectangles may have a negative size.
+ */
+pub fn rect_intersection(a: (Vec2D, Vec2D), b: (Vec2D, Vec2D)) -> Option<(Vec2D, Vec2D)> {
+    let (a_pos, a_size) = rect_ensure_positive_size(a.0, a.1);
+    let (b_pos, b_size) = rect_ensure_positive_size(b.0, b.1);
+
+    let min = Vec2D::new(a_pos.x.max(b_pos.x), a_pos.y.max(b_pos.y));
+    let max = Vec2D::new(
+        (a_pos.x + a_size.x).min(b_pos.x + b_size.x),
+        (a_pos.y + a_size.y).min(b_pos.y + b_size.y),
+    );
+
+    (min.x < max.x && min.y < max.y).then(|| (min, max - min))
+}
+
+/**
+ * Get the smallest rectangle (pos, size) containing all points.
+ */
+pub fn rect_from_points(points: impl IntoIterator<Item = Vec2D>) -> Option<(Vec2D, Vec2D)> {
+    let mut points = points.into_iter();
+    let first = points.next()?;
+    let (min, max) = points.fold((first, first), |(min, max), p| {
+        (
+            Vec2D::new(min.x.min(p.x), min.y.min(p.y)),
+            Vec2D::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    });
+
+    Some((min, max - min))
+}
+
+/**
+ * Map a point from one rectangle (pos, size) onto another, keeping its relative position.
+ * Along an axis where the source rectangle has no extent the point is only translated.
+ */
+pub fn rect_map_point(point: Vec2D, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) -> Vec2D {
+    to.0 + rect_map_vector(point - from.0, from, to)
+}
+
+/**
+ * Scale a vector by the size ratio of two rectangles (pos, size).
+ * Along an axis where the source rectangle has no extent the vector is kept as is.
+ */
+pub fn rect_map_vector(vector: Vec2D, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) -> Vec2D {
+    let scale = |from: f32, to: f32| {
+        if from.abs() < f32::EPSILON {
+            1.0
+        } else {
+            to / from
+        }
+    };
+
+    Vec2D::new(
+        vector.x * scale(from.1.x, to.1.x),
+        vector.y * scale(from.1.y, to.1.y),
+    )
+}
+
+/**
+ * Get the shortest distance between a point and the line segment from start to end.
+ */
+pub fn distance_to_segment(point: Vec2D, start: Vec2D, end: Vec2D) -> f32 {
+    let segment = end - start;
+    let length2 = segment.norm2();
+    if length2 < f32::EPSILON {
+        return point.distance_to(&start);
+    }
+
+    let offset = point - start;
+    let t = ((offset.x * segment.x + offset.y * segment.y) / length2).clamp(0.0, 1.0);
+    point.distance_to(&(start + segment * t))
+}
+
+/**
+ * Get the shortest distance between a point and a polyline.
+ */
+pub fn distance_to_polyline(point: Vec2D, points: &[Vec2D]) -> f32 {
+    match points {
+        [] => f32::MAX,
+        [single] => point.distance_to(single),
+        _ => points
+            .windows(2)
+            .map(|w| distance_to_segment(point, w[0], w[1]))
+            .fold(f32::MAX, f32::min),
+    }
+}
+
+/**
+ * Erase everything within radius of the segment from start to end off a polyline. Segments
+ * longer than half the radius are subdivided first, so the cut follows the eraser closely.
+ * The widths of the points, if any, are interpolated for the points added in between.
+ * Returns None if no point is that close, otherwise the remaining pieces of at least two
+ * points with their widths.
+ */
+pub fn polyline_erase(
+    points: &[Vec2D],
+    widths: &[f32],
+    start: Vec2D,
+    end: Vec2D,
+    radius: f32,
+) -> Option<Vec<(Vec<Vec2D>, Vec<f32>)>> {
+    let step = (radius / 2.0).max(0.5);
+    let with_widths = widths.len() == points.len();
+    let mut dense = Vec::with_capacity(points.len());
+    let mut dense_widths = Vec::with_capacity(widths.len());
+    for (i, point) in points.iter().enumerate() {
+        if i > 0 {
+            let previous = points[i - 1];
+            let steps = (previous.distance_to(point) / step).ceil() as usize;
+            for k in 1..steps {
+                let t = k as f32 / steps as f32;
+                dense.push(previous + (*point - previous) * t);
+                if with_widths {
+                    dense_widths.push(widths[i - 1] + (widths[i] - widths[i - 1]) * t);
+                }
+            }
+        }
+        dense.push(*point);
+        if with_widths {
+            dense_widths.push(widths[i]);
+        }
+    }
+
+    let erased: Vec<bool> = dense
+        .iter()
+        .map(|point| distance_to_segment(*point, start, end) <= radius)
+        .collect();
+    if !erased.contains(&true) {
+        return None;
+    }
+
+    let mut pieces = Vec::new();
+    let mut piece_start = 0;
+    for i in 0..=dense.len() {
+        if i < dense.len() && !erased[i] {
+            continue;
+        }
+        if i - piece_start >= 2 {
+            pieces.push((
+                dense[piece_start..i].to_vec(),
+                dense_widths
+                    .get(piece_start..i)
+                    .unwrap_or_default()
+                    .to_vec(),
+            ));
+        }
+        piece_start = i + 1;
+    }
+    Some(pieces)
+}
+
+/**
+ * Check whether a point lies inside the polygon through the given points (even-odd rule).
+ */
+pub fn polygon_contains(points: &[Vec2D], point: Vec2D) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn distance_to_segment_measures_to_the_nearest_point() {
+        let (start, end) = (Vec2D::new(0.0, 0.0), Vec2D::new(10.0, 0.0));
+
+        // beside the segment, and beyond either end
+        assert_close(distance_to_segment(Vec2D::new(5.0, 3.0), start, end), 3.0);
+        assert_close(distance_to_segment(Vec2D::new(-3.0, 4.0), start, end), 5.0);
+        assert_close(distance_to_segment(Vec2D::new(13.0, -4.0), start, end), 5.0);
+    }
+
+    #[test]
+    fn distance_to_degenerate_segment_is_distance_to_point() {
+        let point = Vec2D::new(1.0, 1.0);
+        assert_close(distance_to_segment(Vec2D::new(4.0, 5.0), point, point), 5.0);
+    }
+
+    #[test]
+    fn rect_map_point_keeps_relative_position() {
+        let from = (Vec2D::new(10.0, 10.0), Vec2D::new(20.0, 10.0));
+        let to = (Vec2D::new(0.0, 0.0), Vec2D::new(40.0, 40.0));
+
+        assert_eq!(rect_map_point(from.0, from, to), to.0);
+        assert_eq!(
+            rect_map_point(Vec2D::new(30.0, 20.0), from, to),
+            Vec2D::new(40.0, 40.0)
+        );
+        assert_eq!(
+            rect_map_point(Vec2D::new(15.0, 12.5), from, to),
+            Vec2D::new(10.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn rect_map_point_translates_along_axis_without_extent() {
+        // a horizontal line has no height to scale
+        let from = (Vec2D::new(0.0, 5.0), Vec2D::new(10.0, 0.0));
+        let to = (Vec2D::new(0.0, 8.0), Vec2D::new(20.0, 0.0));
+
+        assert_eq!(
+            rect_map_point(Vec2D::new(10.0, 5.0), from, to),
+            Vec2D::new(20.0, 8.0)
+        );
+    }
+
+    #[test]
+    fn polygon_contains_uses_even_odd_rule() {
+        let square = [
+            Vec2D::new(0.0, 0.0),
+            Vec2D::new(10.0, 0.0),
+            Vec2D::new(10.0, 10.0),
+            Vec2D::new(0.0, 10.0),
+        ];
+        assert!(polygon_contains(&square, Vec2D::new(5.0, 5.0)));
+        assert!(!polygon_contains(&square, Vec2D::new(15.0, 5.0)));
+        assert!(!polygon_contains(&square, Vec2D::new(5.0, -1.0)));
+
+        // the center of a pentagram is covered twice and therefore outside
+        let star: Vec<_> = (0..5)
+            .map(|i| {
+                let angle = Angle::from_degrees(i as f32 * 144.0 - 90.0);
+                Vec2D::new(10.0 * angle.cos(), 10.0 * angle.sin())
+            })
+            .collect();
+        assert!(!polygon_contains(&star, Vec2D::zero()));
+        assert!(polygon_contains(&star, Vec2D::new(0.0, -8.0)));
+    }
+
+    #[test]
+    fn polygon_without_area_contains_nothing() {
+        assert!(!polygon_contains(&[], Vec2D::zero()));
+        assert!(!polygon_contains(
+            &[Vec2D::new(-1.0, 0.0), Vec2D::new(1.0, 0.0)],
+            Vec2D::zero()
+        ));
+    }
+
+    #[test]
+    fn polyline_erase_misses_distant_lines() {
+        let points = [Vec2D::new(0.0, 0.0), Vec2D::new(100.0, 0.0)];
+        let erased = polyline_erase(
+            &points,
+            &[],
+            Vec2D::new(0.0, 50.0),
+            Vec2D::new(100.0, 50.0),
+            10.0,
+        );
+        assert!(erased.is_none());
+    }
+
+    #[test]
+    fn polyline_erase_splits_at_the_eraser() {
+        let points = [Vec2D::new(0.0, 0.0), Vec2D::new(100.0, 0.0)];
+        let widths = [2.0, 4.0];
+        let pieces = polyline_erase(
+            &points,
+            &widths,
+            Vec2D::new(50.0, -20.0),
+            Vec2D::new(50.0, 20.0),
+            10.0,
+        )
+        .expect("line crosses the eraser");
+
+        assert_eq!(pieces.len(), 2);
+        let (left, left_widths) = &pieces[0];
+        let (right, right_widths) = &pieces[1];
+        assert_eq!(left[0], points[0]);
+        assert_eq!(right[right.len() - 1], points[1]);
+        assert!(left.iter().all(|p| p.x < 40.0));
+        assert!(right.iter().all(|p| p.x > 60.0));
+
+        // the widths follow the points and are interpolated in between
+        assert_eq!(left_widths.len(), left.len());
+        assert_eq!(right_widths.len(), right.len());
+        assert_close(left_widths[0], 2.0);
+        assert_close(right_widths[right_widths.len() - 1], 4.0);
+        for (point, width) in left.iter().zip(left_widths) {
+            assert_close(*width, 2.0 + point.x / 50.0);
+        }
+    }
+
+    #[test]
+    fn polyline_erase_drops_pieces_of_single_points() {
+        let points = [Vec2D::new(0.0, 0.0), Vec2D::new(10.0, 0.0)];
+        let pieces = polyline_erase(
+            &points,
+            &[],
+            Vec2D::new(-5.0, 0.0),
+            Vec2D::new(15.0, 0.0),
+            3.0,
+        )
+        .expect("line lies below the eraser");
+
+        assert!(pieces.is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use relm4::gtk::gdk_pixbuf::{Colorspace, InterpType, Pixbuf};
+use satty_cli::command_line::OutputFormat;
+
+use crate::{configuration::APP_CONFIG, style::Color};
+
+/// The format matching the extension of `filename`, if it is a supported image format.
+pub fn from_extension(filename: &str) -> Option<OutputFormat> {
+    let extension = Path::new(filename).extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some(OutputFormat::Png),
+        "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+        "webp" => Some(OutputFormat::Webp),
+        "avif" => Some(OutputFormat::Avif),
+        "bmp" => Some(OutputFormat::Bmp),
+        "tif" | "tiff" => Some(OutputFormat::Tiff),
+        _ => None,
+    }
+}
+
+/// The format to save to `filename` in, see `choose`.
+pub fn for_output_filename(filename: &str) -> Option<OutputFormat> {
+    choose(filename, APP_CONFIG.read().output_format())
+}
+
+/// The format matching the extension of `filename`, so that the content always matches the
+/// name, or the `configured` one for other extensions. Printing to stdout defaults to png.
+fn choose(filename: &str, configured: Option<OutputFormat>) -> Option<OutputFormat> {
+    from_extension(filename)
+        .or(configured)
+        .or((filename == "-").then_some(OutputFormat::Png))
+}
+
+/// Name of the gdk-pixbuf saver for the format.
+fn pixbuf_type(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Png => "png",
+        OutputFormat::Jpeg => "jpeg",
+        OutputFormat::Webp => "webp",
+        OutputFormat::Avif => "avif",
+        OutputFormat::Bmp => "bmp",
+        OutputFormat::Tiff => "tiff",
+    }
+}
+
+fn supports_alpha(format: OutputFormat) -> bool {
+    !matches!(format, OutputFormat::Jpeg | OutputFormat::Bmp)
+}
+
+/// Composite the image onto an opaque background.
+fn flatten(image: &Pixbuf, background: Color) -> Result<Pixbuf> {
+    let (width, height) = (image.width(), image.height());
+    let flat = Pixbuf::new(Colorspace::Rgb, false, 8, width, height)
+        .ok_or(anyhow!("Could not allocate image"))?;
+    flat.fill(background.with_alpha(255).to_rgba_u32());
+    image.composite(
+        &flat,
+        0,
+        0,
+        width,
+        height,
+        0.0,
+        0.0,
+        1.0,
+        1.0,
+        InterpType::Nearest,
+        255,
+    );
+    Ok(flat)
+}
+
+/// Encode the image in the given format, using the quality settings of the configuration.
+pub fn encode(image: &Pixbuf, format: OutputFormat) -> Result<Vec<u8>> {
+    let config = APP_CONFIG.read();
+    let image = if image.has_alpha() && !supports_alpha(format) {
+        flatten(image, config.flatten_background())?
+    } else {
+        image.clone()
+    };
+
+    let quality;
+    let options: Vec<(&str, &str)> = match format {
+        OutputFormat::Png => {
+            quality = config.png_compression().to_string();
+            vec![("compression", &quality)]
+        }
+        OutputFormat::Jpeg => {
+            quality = config.jpeg_quality().to_string();
+            vec![("quality", &quality)]
+        }
+        OutputFormat::Webp => {
+            quality = config.webp_quality().to_string();
+            vec![("quality", &quality)]
+        }
+        OutputFormat::Avif | OutputFormat::Bmp | OutputFormat::Tiff => Vec::new(),
+    };
+
+    image
+        .save_to_bufferv(pixbuf_type(format), &options)
+        .with_context(|| {
+            format!(
+                "couldn't encode {} image, is the gdk-pixbuf loader installed?",
+                pixbuf_type(format)
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{choose, flatten, from_extension};
+    use crate::style::Color;
+    use relm4::gtk::gdk_pixbuf::{Colorspace, Pixbuf};
+    use satty_cli::command_line::OutputFormat;
+
+    #[test]
+    fn from_extension_recognizes_aliases_in_any_case() {
+        assert_eq!(from_extension("shot.png"), Some(OutputFormat::Png));
+        assert_eq!(from_extension("shot.JPG"), Some(OutputFormat::Jpeg));
+        assert_eq!(from_extension("shot.jpeg"), Some(OutputFormat::Jpeg));
+        assert_eq!(from_extension("shot.WebP"), Some(OutputFormat::Webp));
+        assert_eq!(from_extension("shot.avif"), Some(OutputFormat::Avif));
+        assert_eq!(from_extension("shot.bmp"), Some(OutputFormat::Bmp));
+        assert_eq!(from_extension("shot.tif"), Some(OutputFormat::Tiff));
+        assert_eq!(
+            from_extension("~/shots/2024.01.01.tiff"),
+            Some(OutputFormat::Tiff)
+        );
+    }
+
+    #[test]
+    fn choose_prefers_the_extension_over_the_configured_format() {
+        assert_eq!(
+            choose("shot.png", Some(OutputFormat::Jpeg)),
+            Some(OutputFormat::Png)
+        );
+        assert_eq!(
+            choose("shot.screenshot", Some(OutputFormat::Jpeg)),
+            Some(OutputFormat::Jpeg)
+        );
+        assert_eq!(
+            choose("-", Some(OutputFormat::Webp)),
+            Some(OutputFormat::Webp)
+        );
+        assert_eq!(choose("-", None), Some(OutputFormat::Png));
+        assert_eq!(choose("shot.screenshot", None), None);
+    }
+
+    #[test]
+    fn from_extension_rejects_other_files() {
+        assert_eq!(from_extension("shot.gif"), None);
+        assert_eq!(from_extension("shot.svg"), None);
+        assert_eq!(from_extension("shot.satty"), None);
+        assert_eq!(from_extension("png"), None);
+        assert_eq!(from_extension("-"), None);
+    }
+
+    #[test]
+    fn flatten_composites_onto_opaque_background() {
+        let image = Pixbuf::new(Colorspace::Rgb, true, 8, 2, 2).expect("create image");
+        image.fill(0x00000000);
+
+        let flat = flatten(&image, Color::new(10, 20, 30, 0)).expect("flatten image");
+        assert!(!flat.has_alpha());
+        assert_eq!(&flat.read_pixel_bytes()[..3], [10, 20, 30]);
+    }
+}
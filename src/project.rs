@@ -0,0 +1,205 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use relm4::gtk::{
+    gdk_pixbuf::{Pixbuf, PixbufLoader},
+    glib,
+    prelude::*,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{math::Vec2D, tools::SavedDrawable};
+
+/// File extension of Satty project files.
+pub const PROJECT_EXTENSION: &str = "satty";
+
+/// Schema version written to new project files. Bump it whenever the serialized
+/// form of a drawable changes in a way older versions can't read.
+pub const PROJECT_VERSION: u32 = 1;
+
+/// An annotation session: the original background image and the editable drawables on top.
+pub struct Project {
+    pub background: Pixbuf,
+    pub drawables: Vec<SavedDrawable>,
+    /// The crop rectangle (pos, size), kept editable like the drawables.
+    pub crop: Option<(Vec2D, Vec2D)>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ProjectFile {
+    version: u32,
+    /// base64 encoded PNG
+    background: String,
+    #[serde(default)]
+    drawables: Vec<SavedDrawable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crop: Option<(Vec2D, Vec2D)>,
+}
+
+impl Project {
+    pub fn from_image(background: Pixbuf) -> Self {
+        Self {
+            background,
+            drawables: Vec::new(),
+            crop: None,
+        }
+    }
+
+    pub fn is_project_file(filename: &str) -> bool {
+        Path::new(filename)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(PROJECT_EXTENSION))
+    }
+
+    /// Whether `data` looks like a project file rather than an image.
+    pub fn is_project_data(data: &[u8]) -> bool {
+        data.trim_ascii_start().starts_with(b"{")
+    }
+
+    pub fn load(filename: &str) -> Result<Self> {
+        let data = fs::read(filename).context("couldn't read project file")?;
+        Self::from_bytes(&data)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let file: ProjectFile =
+            serde_json::from_slice(data).context("couldn't parse project file")?;
+        if file.version > PROJECT_VERSION {
+            return Err(anyhow!(
+                "project file version {} is newer than the supported version {}",
+                file.version,
+                PROJECT_VERSION
+            ));
+        }
+
+        Ok(Self {
+            background: decode_png(&file.background)?,
+            drawables: file.drawables,
+            crop: file.crop,
+        })
+    }
+
+    pub fn to_bytes(
+        background: &Pixbuf,
+        drawables: Vec<SavedDrawable>,
+        crop: Option<(Vec2D, Vec2D)>,
+    ) -> Result<Vec<u8>> {
+        let file = ProjectFile {
+            version: PROJECT_VERSION,
+            background: encode_png(background).context("couldn't encode background image")?,
+            drawables,
+            crop,
+        };
+        Ok(serde_json::to_vec(&file)?)
+    }
+}
+
+/// The image as base64 encoded PNG, as images are embedded in project files.
+pub fn encode_png(image: &Pixbuf) -> Result<String> {
+    let png = image.save_to_bufferv("png", &[])?;
+    Ok(glib::base64_encode(&png).to_string())
+}
+
+/// Decode an image embedded by `encode_png`.
+pub fn decode_png(data: &str) -> Result<Pixbuf> {
+    let loader = PixbufLoader::new();
+    loader.write(&glib::base64_decode(data))?;
+    loader.close()?;
+    loader
+        .pixbuf()
+        .ok_or(anyhow!("Conversion to Pixbuf failed"))
+}
+
+/// Serde helpers storing a `Pixbuf` field as base64 encoded PNG.
+pub mod embedded_png {
+    use relm4::gtk::gdk_pixbuf::Pixbuf;
+    use serde::{Deserialize, Deserializer, Serializer, de, ser};
+
+    pub fn serialize<S: Serializer>(image: &Pixbuf, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = super::encode_png(image).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pixbuf, D::Error> {
+        let data = String::deserialize(deserializer)?;
+        super::decode_png(&data).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PROJECT_VERSION, Project};
+    use crate::{
+        math::Vec2D,
+        style::Style,
+        tools::{Arrow, Line, SavedDrawable},
+    };
+    use relm4::gtk::gdk_pixbuf::{Colorspace, Pixbuf};
+
+    fn background() -> Pixbuf {
+        let image = Pixbuf::new(Colorspace::Rgb, true, 8, 3, 2).expect("create image");
+        image.fill(0x336699ff);
+        image
+    }
+
+    #[test]
+    fn round_trips_background_drawables_and_crop() {
+        let background = background();
+        let drawables = vec![
+            SavedDrawable::Line(Line::new(
+                Vec2D::new(0.0, 0.0),
+                Vec2D::new(2.0, 1.0),
+                Style::default(),
+            )),
+            SavedDrawable::Arrow(
+                Arrow::new(Vec2D::new(0.0, 1.0), Vec2D::new(3.0, 1.0), Style::default())
+                    .with_control(Vec2D::new(1.5, 0.0)),
+            ),
+        ];
+        let crop = Some((Vec2D::new(1.0, 0.0), Vec2D::new(2.0, 2.0)));
+
+        let data = Project::to_bytes(&background, drawables.clone(), crop).expect("serialize");
+        assert!(Project::is_project_data(&data));
+        let project = Project::from_bytes(&data).expect("deserialize");
+
+        assert_eq!(project.background.width(), 3);
+        assert_eq!(project.background.height(), 2);
+        assert_eq!(
+            project.background.read_pixel_bytes(),
+            background.read_pixel_bytes()
+        );
+        assert_eq!(
+            serde_json::to_value(&project.drawables).expect("serialize drawables"),
+            serde_json::to_value(&drawables).expect("serialize drawables")
+        );
+        assert_eq!(project.crop, crop);
+    }
+
+    #[test]
+    fn crop_is_optional() {
+        let data = Project::to_bytes(&background(), Vec::new(), None).expect("serialize");
+        let project = Project::from_bytes(&data).expect("deserialize");
+
+        assert!(project.drawables.is_empty());
+        assert_eq!(project.crop, None);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let data = format!(
+            r#"{{"version": {}, "background": ""}}"#,
+            PROJECT_VERSION + 1
+        );
+        assert!(Project::from_bytes(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn recognizes_project_files() {
+        assert!(Project::is_project_file("shot.satty"));
+        assert!(Project::is_project_file("shot.SATTY"));
+        assert!(!Project::is_project_file("shot.png"));
+        assert!(!Project::is_project_data(b"\x89PNG"));
+        assert!(Project::is_project_data(b"  {\"version\": 1}"));
+    }
+}
@@ -2,8 +2,8 @@ use anyhow::anyhow;
 
 use femtovg::imgref::Img;
 use femtovg::rgb::{ComponentBytes, RGBA};
-use relm4::gtk::gdk_pixbuf::Pixbuf;
 use relm4::gtk::gdk_pixbuf::glib::Bytes;
+use relm4::gtk::gdk_pixbuf::{Pixbuf, PixbufLoader};
 use std::cell::{Cell, RefCell};
 use std::io::Write;
 use std::panic;
@@ -14,21 +14,29 @@ use std::{fs, io};
 
 use gtk::prelude::*;
 
-use relm4::gtk::gdk::{DisplayManager, Key, ModifierType, Texture};
+use relm4::gtk::gdk::{
+    AxisUse, DisplayManager, DragAction, Event, FileList, Key, ModifierType, Texture,
+};
 use relm4::{Component, ComponentParts, ComponentSender, RelmWidgetExt, gtk};
 
 use crate::configuration::{APP_CONFIG, Action};
-use crate::femtovg_area::FemtoVGArea;
+use crate::femtovg_area::{FemtoVGArea, ZOrder};
 use crate::ime::pango_adapter::spans_from_pango_attrs;
 use crate::keybindings::{ActionTrigger, ShortcutCommand, ShortcutRegistry};
 use crate::math::Vec2D;
 use crate::notification::{log_result, log_result_with_pixbuf};
-use crate::style::{Color, Size, Style};
-use crate::tools::{Tool, ToolEvent, ToolUpdateResult, Tools, ToolsManager};
+use crate::output_format;
+use crate::project::{PROJECT_EXTENSION, Project};
+use crate::style::{Color, Size, StrokePattern, Style};
+use crate::svg::{self, SVG_EXTENSION, SvgExport};
+use crate::tools::{
+    Drawable, Picture, SavedDrawable, Tool, ToolEvent, ToolUpdateResult, Tools, ToolsManager,
+};
 use crate::ui::toolbars::ToolbarEvent;
 use xdg::BaseDirectories;
 
 type RenderedImage = Img<Vec<RGBA<u8>>>;
+const PASTE_OFFSET: f32 = 20.0;
 const SAVE_AS_LAST_DIR_FILE: &str = "save_as_last_dir";
 const SAVE_AS_LAST_DIR_MAX_BYTES: u64 = 10_000;
 
@@ -42,6 +50,8 @@ pub enum SketchBoardInput {
     RenderResult(RenderedImage, Vec<Action>),
     RenderResultFollowup(Option<Pixbuf>, Vec<Action>, Option<String>),
     CommitEvent(TextEventMsg),
+    /// An image to insert, at the given widget position or centered.
+    InsertImage(Pixbuf, Option<Vec2D>),
     Refresh,
     Exit,
     ScaleFactorChanged,
@@ -58,6 +68,7 @@ pub enum SketchBoardOutput {
     FocusAnnotationSizeFactorShortcut,
     SetFill(bool),
     SetRoundCaps(bool),
+    SetStrokePattern(StrokePattern),
     DimensionsUpdate(Option<(i32, i32)>),
     ToolEditingChanged(bool),
 }
@@ -118,6 +129,31 @@ pub struct MouseEventMsg {
     pub pos: Vec2D,
     pub n_pressed: i32,
     pub release: bool,
+    pub stylus: StylusAxes,
+}
+
+/// The axes a drawing tablet reports next to the position, None for other devices.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StylusAxes {
+    /// From 0 to 1.
+    pub pressure: Option<f32>,
+    /// From -1 to 1 in both directions.
+    pub tilt: Option<Vec2D>,
+}
+
+impl StylusAxes {
+    pub fn from_event(event: Option<Event>) -> Self {
+        let Some(event) = event else {
+            return Self::default();
+        };
+        let axis = |axis_use| event.axis(axis_use).map(|v| v as f32);
+        Self {
+            pressure: axis(AxisUse::Pressure),
+            tilt: axis(AxisUse::Xtilt)
+                .zip(axis(AxisUse::Ytilt))
+                .map(|(x, y)| Vec2D::new(x, y)),
+        }
+    }
 }
 
 impl SketchBoardInput {
@@ -126,6 +162,7 @@ impl SketchBoardInput {
         button: u32,
         n_pressed: i32,
         modifier: ModifierType,
+        stylus: StylusAxes,
         pos: Vec2D,
         release: bool,
     ) -> SketchBoardInput {
@@ -138,6 +175,7 @@ impl SketchBoardInput {
             pos,
             is_touchpad: false,
             release,
+            stylus,
         }))
     }
     pub fn new_key_event(event: KeyEventMsg) -> SketchBoardInput {
@@ -171,6 +209,7 @@ impl SketchBoardInput {
             pos: Vec2D::new(delta_x as f32, delta_y as f32),
             is_touchpad,
             release: false,
+            stylus: StylusAxes::default(),
         }))
     }
 }
@@ -198,7 +237,7 @@ impl InputEvent {
                     me.pos = renderer.abs_canvas_to_image_coordinates(me.pos);
                     None
                 }
-                MouseEventType::BeginDrag => {
+                MouseEventType::BeginDrag | MouseEventType::PointerPos => {
                     me.pos = renderer.abs_canvas_to_image_coordinates(me.pos);
                     None
                 }
@@ -262,7 +301,7 @@ impl InputEvent {
                     None
                 }
                 MouseEventType::PointerPos => {
-                    renderer.set_pointer_offset(me.pos);
+                    renderer.set_pointer_offset(me.screen_pos);
                     None
                 }
                 _ => None,
@@ -284,6 +323,9 @@ pub struct SketchBoard {
     style: Style,
     im_context: gtk::IMMulticontext,
     last_saved_filepath: RefCell<Option<String>>,
+    clipboard: Vec<Box<dyn Drawable>>,
+    // how often the clipboard content has to be offset when pasted next
+    paste_count: u16,
 }
 
 impl SketchBoard {
@@ -291,7 +333,7 @@ impl SketchBoard {
         self.renderer.queue_render();
     }
 
-    fn image_to_pixbuf(image: RenderedImage) -> Pixbuf {
+    pub fn image_to_pixbuf(image: RenderedImage) -> Pixbuf {
         let (buf, w, h) = image.into_contiguous_buf();
 
         Pixbuf::from_bytes(
@@ -305,15 +347,42 @@ impl SketchBoard {
         )
     }
 
+    /// Record a result changing the committed drawables as an undoable step. Returns None if
+    /// it did, any other result is handed back.
+    fn record_change(&mut self, result: ToolUpdateResult) -> Option<ToolUpdateResult> {
+        match result {
+            ToolUpdateResult::Commit(drawable) => self.renderer.commit(drawable),
+            ToolUpdateResult::Edit(edit) => self.renderer.commit_edit(edit),
+            ToolUpdateResult::Replace(previous) => {
+                self.renderer.commit_replace(previous);
+                // indices of the selection may no longer be valid
+                self.tools.get_pointer_tool().borrow_mut().clear_selection();
+            }
+            other => return Some(other),
+        }
+        None
+    }
+
+    /// Record a change like `record_change`, copying the image to the clipboard if configured,
+    /// and "downgrade" it to a simple redraw result.
+    fn commit_change(&mut self, result: ToolUpdateResult) -> ToolUpdateResult {
+        match self.record_change(result) {
+            Some(result) => result,
+            None => {
+                if APP_CONFIG.read().auto_copy() {
+                    self.renderer.request_render(&[Action::SaveToClipboard]);
+                }
+                ToolUpdateResult::Redraw
+            }
+        }
+    }
+
     fn deactivate_active_tool(&mut self) -> bool {
-        if self.active_tool.borrow().active()
-            && let ToolUpdateResult::Commit(result) =
-                self.active_tool.borrow_mut().handle_deactivated()
-        {
-            self.renderer.commit(result);
-            return true;
+        if !self.active_tool.borrow().active() {
+            return false;
         }
-        false
+        let result = self.active_tool.borrow_mut().handle_deactivated();
+        self.record_change(result).is_none()
     }
 
     fn handle_action(&mut self, actions: &[Action]) -> ToolUpdateResult {
@@ -406,7 +475,7 @@ impl SketchBoard {
         relm4::main_application().quit();
     }
 
-    fn resolve_output_filename(output_filename: &str) -> Option<String> {
+    pub fn resolve_output_filename(output_filename: &str) -> Option<String> {
         let delayed_format = chrono::Local::now().format(output_filename);
         let mut output_filename = if panic::catch_unwind(|| delayed_format.to_string()).is_ok() {
             delayed_format.to_string()
@@ -505,23 +574,44 @@ impl SketchBoard {
             return;
         };
 
-        // TODO: we could support more data types
-        if output_filename != "-" && !output_filename.ends_with(".png") {
+        if Project::is_project_file(&output_filename) {
+            match self.project_data() {
+                Ok(data) => self.write_output(&output_filename, data, image),
+                Err(e) => log_result(&format!("Error serializing project: {e:#}"), true),
+            }
+            return;
+        }
+
+        if svg::is_svg_file(&output_filename) {
+            match SvgExport::new(&self.tools, self.renderer.background_image()).to_bytes() {
+                Ok(data) => self.write_output(&output_filename, data, image),
+                Err(e) => log_result(&format!("Error serializing SVG: {e:#}"), true),
+            }
+            return;
+        }
+
+        let Some(format) = output_format::for_output_filename(&output_filename) else {
             log_result(
-                "The only supported format is png, but the filename does not end in png",
+                &format!(
+                    "Unsupported output format, the filename has to end in png, jpg, webp, avif, bmp, tiff, {SVG_EXTENSION} or {PROJECT_EXTENSION}"
+                ),
                 true,
             );
             return;
-        }
+        };
 
-        let data = match image.save_to_bufferv("png", &Vec::new()) {
+        let data = match output_format::encode(image, format) {
             Ok(d) => d,
             Err(e) => {
-                eprintln!("Error serializing image: {e}");
+                log_result(&format!("Error serializing image: {e:#}"), true);
                 return;
             }
         };
 
+        self.write_output(&output_filename, data, image);
+    }
+
+    fn write_output(&self, output_filename: &str, data: Vec<u8>, image: &Pixbuf) {
         if output_filename == "-" {
             // "-" means stdout
             let stdout = io::stdout();
@@ -531,11 +621,11 @@ impl SketchBoard {
             }
             return;
         }
-        match fs::write(&output_filename, data) {
+        match fs::write(output_filename, data) {
             Err(e) => log_result(&format!("Error while saving file: {e}"), true),
             Ok(_) => {
                 // Store the filepath for copy-filepath action
-                *self.last_saved_filepath.borrow_mut() = Some(output_filename.clone());
+                *self.last_saved_filepath.borrow_mut() = Some(output_filename.to_string());
                 log_result_with_pixbuf(
                     &format!("File saved to '{}'.", output_filename),
                     image.clone(),
@@ -544,6 +634,15 @@ impl SketchBoard {
         };
     }
 
+    /// Serialize the background image, the committed drawables and the crop as a project file.
+    fn project_data(&self) -> anyhow::Result<Vec<u8>> {
+        Project::to_bytes(
+            &self.renderer.background_image(),
+            self.tools.saved_drawables(),
+            self.tools.saved_crop(),
+        )
+    }
+
     fn handle_save_as(
         &self,
         is_modal: bool,
@@ -561,14 +660,8 @@ impl SketchBoard {
             .and_then(Path::file_name)
             .map(|name| name.to_string_lossy().into_owned());
 
-        let data = match pixbuf.save_to_bufferv("png", &Vec::new()) {
-            Ok(d) => d,
-            Err(e) => {
-                eprintln!("Error serializing image: {e}");
-                return;
-            }
-        };
-
+        let project_data = self.project_data();
+        let svg_export = SvgExport::new(&self.tools, self.renderer.background_image());
         let root = self.renderer.toplevel_window();
 
         relm4::spawn_local(async move {
@@ -607,7 +700,38 @@ impl SketchBoard {
                         None => return,
                     };
 
-                    match fs::write(&output_filename, &data) {
+                    let data = if Project::is_project_file(&output_filename) {
+                        match &project_data {
+                            Ok(project_data) => project_data.clone(),
+                            Err(e) => {
+                                return log_result(
+                                    &format!("Error serializing project: {e:#}"),
+                                    true,
+                                );
+                            }
+                        }
+                    } else if svg::is_svg_file(&output_filename) {
+                        match svg_export.to_bytes() {
+                            Ok(d) => d,
+                            Err(e) => {
+                                return log_result(&format!("Error serializing SVG: {e:#}"), true);
+                            }
+                        }
+                    } else {
+                        let format = output_format::for_output_filename(&output_filename)
+                            .unwrap_or_default();
+                        match output_format::encode(&pixbuf, format) {
+                            Ok(d) => d,
+                            Err(e) => {
+                                return log_result(
+                                    &format!("Error serializing image: {e:#}"),
+                                    true,
+                                );
+                            }
+                        }
+                    };
+
+                    match fs::write(&output_filename, data) {
                         Err(e) => log_result(&format!("Error while saving file: {e}"), true),
                         Ok(_) => {
                             exit_app = APP_CONFIG.read().early_exit_save_as();
@@ -730,6 +854,8 @@ impl SketchBoard {
         if self.active_tool.borrow().active() {
             self.active_tool.borrow_mut().handle_undo()
         } else if self.renderer.undo() {
+            // indices of the selection may no longer be valid
+            self.tools.get_pointer_tool().borrow_mut().clear_selection();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
@@ -740,15 +866,259 @@ impl SketchBoard {
         if self.active_tool.borrow().active() {
             self.active_tool.borrow_mut().handle_redo()
         } else if self.renderer.redo() {
+            self.tools.get_pointer_tool().borrow_mut().clear_selection();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
         }
     }
 
+    fn handle_delete_selection(&mut self) -> ToolUpdateResult {
+        let selection = self.tools.get_pointer_tool().borrow_mut().take_selection();
+        if self.renderer.delete(selection) {
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_reorder_selection(&mut self, z_order: ZOrder) -> ToolUpdateResult {
+        let pointer_tool = self.tools.get_pointer_tool();
+        let selection = pointer_tool.borrow_mut().take_selection();
+        match self.renderer.reorder(&selection, z_order) {
+            Some(new_selection) => {
+                pointer_tool.borrow_mut().set_selection(new_selection);
+                ToolUpdateResult::Redraw
+            }
+            None => {
+                pointer_tool.borrow_mut().set_selection(selection);
+                ToolUpdateResult::Unmodified
+            }
+        }
+    }
+
+    fn selected_drawables(&self) -> Vec<Box<dyn Drawable>> {
+        let drawables = self.tools.get_drawables();
+        let drawables = drawables.borrow();
+        self.tools
+            .get_pointer_tool()
+            .borrow()
+            .selection()
+            .iter()
+            .filter_map(|index| drawables.get(*index))
+            .map(|d| d.clone_box())
+            .collect()
+    }
+
+    fn copy_selection(&mut self) -> bool {
+        let selected = self.selected_drawables();
+        if selected.is_empty() {
+            return false;
+        }
+
+        self.clipboard = selected;
+        self.paste_count = 1;
+        true
+    }
+
+    fn handle_paste_selection(&mut self) -> ToolUpdateResult {
+        let drawables = self.clipboard.iter().map(|d| d.clone_box()).collect();
+        let offset = PASTE_OFFSET * self.paste_count as f32;
+        self.paste_count = self.paste_count.saturating_add(1);
+        self.insert_drawables(drawables, Vec2D::new(offset, offset))
+    }
+
+    fn handle_duplicate_selection(&mut self) -> ToolUpdateResult {
+        let drawables = self.selected_drawables();
+        self.insert_drawables(drawables, Vec2D::new(PASTE_OFFSET, PASTE_OFFSET))
+    }
+
+    /// Add copies of drawables moved by `offset` and select them if the pointer tool is active.
+    fn insert_drawables(
+        &mut self,
+        mut drawables: Vec<Box<dyn Drawable>>,
+        offset: Vec2D,
+    ) -> ToolUpdateResult {
+        if drawables.is_empty() {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        // commit whatever is currently being drawn first
+        self.deactivate_active_tool();
+
+        for d in drawables.iter_mut() {
+            if let Some((pos, size)) = d.bounds() {
+                d.transform((pos, size), (pos + offset, size));
+                d.refresh_capture();
+            }
+        }
+
+        let inserts_spotlight = drawables
+            .iter()
+            .any(|d| matches!(d.to_saved(), Some(SavedDrawable::Spotlight(_))));
+        let indices = if inserts_spotlight {
+            self.insert_merging_spotlights(drawables)
+        } else {
+            self.renderer.insert(drawables)
+        };
+        if self.active_tool.borrow().get_tool_type() == Tools::Pointer {
+            self.tools
+                .get_pointer_tool()
+                .borrow_mut()
+                .set_selection(indices);
+        }
+        ToolUpdateResult::Redraw
+    }
+
+    /// Insert the drawables, adding the spots of the spotlights among them to the topmost
+    /// existing spotlight, as all spotlights form a single mask. Returns the indices of the
+    /// inserted drawables and the spotlight they were merged into.
+    fn insert_merging_spotlights(&mut self, drawables: Vec<Box<dyn Drawable>>) -> Vec<usize> {
+        let shared = self.tools.get_drawables();
+        let mut stack = shared.borrow_mut();
+        let existing = stack
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, d)| match d.to_saved() {
+                Some(SavedDrawable::Spotlight(s)) => Some((index, s)),
+                _ => None,
+            });
+        let Some((index, mut merged)) = existing else {
+            drop(stack);
+            return self.renderer.insert(drawables);
+        };
+
+        let previous = stack.iter().map(|d| d.clone_box()).collect();
+        let mut indices = vec![index];
+        for d in drawables {
+            match d.to_saved() {
+                Some(SavedDrawable::Spotlight(spotlight)) => merged.merge(spotlight),
+                _ => {
+                    indices.push(stack.len());
+                    stack.push(d);
+                }
+            }
+        }
+        stack[index] = Box::new(merged);
+        drop(stack);
+
+        // inserting and merging are undone together
+        self.renderer.commit_replace(previous);
+        indices
+    }
+
+    /// Ask for an image file to insert on top of the screenshot.
+    fn handle_open_image(&self, sender: ComponentSender<Self>) {
+        let root = self.renderer.toplevel_window();
+
+        relm4::spawn_local(async move {
+            let builder = gtk::FileChooserNative::builder()
+                .modal(true)
+                .title("Insert Image")
+                .action(gtk::FileChooserAction::Open)
+                .accept_label("Insert")
+                .cancel_label("Cancel");
+
+            let dialog = match root {
+                Some(w) => builder.transient_for(&w),
+                None => builder,
+            }
+            .build();
+
+            let filter = gtk::FileFilter::new();
+            filter.set_name(Some("Images"));
+            filter.add_pixbuf_formats();
+            dialog.add_filter(&filter);
+
+            dialog.connect_response(move |dialog, response| {
+                if response == gtk::ResponseType::Accept
+                    && let Some(path) = dialog.file().and_then(|file| file.path())
+                {
+                    match Pixbuf::from_file(&path) {
+                        Ok(image) => sender.input(SketchBoardInput::InsertImage(image, None)),
+                        Err(e) => log_result(&format!("Error loading image: {e}"), true),
+                    }
+                }
+            });
+
+            dialog.show();
+        });
+    }
+
+    /// Insert the image in the clipboard on top of the screenshot.
+    fn handle_paste_image(&self, sender: ComponentSender<Self>) {
+        let Some(display) = DisplayManager::get().default_display() else {
+            return log_result("Cannot open default display for clipboard.", true);
+        };
+        display
+            .clipboard()
+            .read_texture_async(None::<&gtk::gio::Cancellable>, move |result| {
+                match result.map_err(anyhow::Error::from).and_then(|texture| {
+                    let texture = texture.ok_or(anyhow!("the clipboard holds no image"))?;
+                    Self::pixbuf_from_texture(&texture)
+                }) {
+                    Ok(image) => sender.input(SketchBoardInput::InsertImage(image, None)),
+                    Err(e) => log_result(&format!("Error pasting image: {e}"), true),
+                }
+            });
+    }
+
+    fn pixbuf_from_texture(texture: &Texture) -> anyhow::Result<Pixbuf> {
+        let loader = PixbufLoader::new();
+        loader.write(&texture.save_to_png_bytes())?;
+        loader.close()?;
+        loader
+            .pixbuf()
+            .ok_or(anyhow!("Conversion to Pixbuf failed"))
+    }
+
+    /// Load the first image among dropped files.
+    fn pixbuf_from_files(files: &FileList) -> anyhow::Result<Pixbuf> {
+        files
+            .files()
+            .iter()
+            .filter_map(|file| file.path())
+            .find_map(|path| Pixbuf::from_file(path).ok())
+            .ok_or(anyhow!("none of the dropped files is an image"))
+    }
+
+    /// Add the image centered at `pos` (in widget coordinates) or the image center, and select
+    /// it with the pointer tool to move and scale it.
+    fn handle_insert_image(
+        &mut self,
+        image: Pixbuf,
+        pos: Option<Vec2D>,
+        sender: ComponentSender<Self>,
+    ) -> ToolUpdateResult {
+        let background = self.renderer.background_image();
+        let background_size = Vec2D::new(background.width() as f32, background.height() as f32);
+        let center = match pos {
+            Some(pos) => self.renderer.abs_canvas_to_image_coordinates(pos),
+            None => background_size * 0.5,
+        };
+
+        // commit whatever is currently being drawn first
+        self.deactivate_active_tool();
+
+        let picture = Picture::centered(image, center, background_size * 0.5);
+        let indices = self.renderer.insert(vec![Box::new(picture)]);
+        self.tools
+            .get_pointer_tool()
+            .borrow_mut()
+            .set_selection(indices);
+        if self.active_tool.borrow().get_tool_type() != Tools::Pointer {
+            sender
+                .output_sender()
+                .emit(SketchBoardOutput::ToolSwitchShortcut(Tools::Pointer));
+        }
+        ToolUpdateResult::Redraw
+    }
+
     fn handle_clear_all(&mut self) -> ToolUpdateResult {
         // can't use lazy || here
         if self.deactivate_active_tool() | self.renderer.clear_all() {
+            self.tools.get_pointer_tool().borrow_mut().clear_selection();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
@@ -786,19 +1156,12 @@ impl SketchBoard {
             ToolbarEvent::ToolSelected(tool) => {
                 // deactivate old tool and save drawable, if any
                 let old_tool = self.active_tool.clone();
-                let mut deactivate_result =
-                    old_tool.borrow_mut().handle_event(ToolEvent::Deactivated);
+                let deactivate_result = old_tool.borrow_mut().handle_event(ToolEvent::Deactivated);
 
                 old_tool.borrow_mut().set_im_context(None);
 
-                if let ToolUpdateResult::Commit(d) = deactivate_result {
-                    self.renderer.commit(d);
-                    if APP_CONFIG.read().auto_copy() {
-                        self.renderer.request_render(&[Action::SaveToClipboard]);
-                    }
-                    // we handle commit directly and "downgrade" to a simple redraw result
-                    deactivate_result = ToolUpdateResult::Redraw;
-                }
+                // we handle changes directly, only one result is passed on
+                let deactivate_result = self.commit_change(deactivate_result);
 
                 // change active tool
                 self.active_tool = self.tools.get(&tool);
@@ -817,9 +1180,11 @@ impl SketchBoard {
                     .set_sender(sender.input_sender().clone());
 
                 // send style event
-                self.active_tool
+                let style_result = self
+                    .active_tool
                     .borrow_mut()
                     .handle_event(ToolEvent::StyleChanged(self.style));
+                let style_result = self.commit_change(style_result);
 
                 // send activated event
                 let activate_result = self
@@ -827,10 +1192,14 @@ impl SketchBoard {
                     .borrow_mut()
                     .handle_event(ToolEvent::Activated);
 
-                match activate_result {
-                    ToolUpdateResult::Unmodified => deactivate_result,
-                    _ => activate_result,
-                }
+                [activate_result, style_result, deactivate_result]
+                    .into_iter()
+                    .find(|r| !matches!(r, ToolUpdateResult::Unmodified))
+                    .unwrap_or(ToolUpdateResult::Unmodified)
+            }
+            ToolbarEvent::BlurModeSelected(mode) => {
+                self.tools.get_blur_tool().borrow_mut().set_mode(mode);
+                ToolUpdateResult::Unmodified
             }
             ToolbarEvent::ColorSelected(color) => {
                 self.style.color = color;
@@ -870,6 +1239,39 @@ impl SketchBoard {
                     .borrow_mut()
                     .handle_event(ToolEvent::StyleChanged(self.style))
             }
+            ToolbarEvent::CycleStrokePattern => {
+                self.style.stroke_pattern = self.style.stroke_pattern.next();
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::SetStrokePattern(
+                        self.style.stroke_pattern,
+                    ));
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
+            ToolbarEvent::ArrowStartSelected(head) => {
+                self.style.arrow_start = head;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
+            ToolbarEvent::ArrowEndSelected(head) => {
+                self.style.arrow_end = head;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
+            ToolbarEvent::StampSelected(symbol) => {
+                self.tools.get_stamp_tool().borrow_mut().set_symbol(symbol);
+                // picking a symbol is meant to place it
+                if self.active_tool.borrow().get_tool_type() != Tools::Stamp {
+                    sender
+                        .output_sender()
+                        .emit(SketchBoardOutput::ToolSwitchShortcut(Tools::Stamp));
+                }
+                ToolUpdateResult::Unmodified
+            }
             ToolbarEvent::AnnotationSizeFactorChanged(value) => {
                 self.style.annotation_size_factor = value;
                 self.active_tool
@@ -878,12 +1280,20 @@ impl SketchBoard {
             }
             ToolbarEvent::SetFill(fill_enabled) => {
                 self.style.fill = fill_enabled;
-                self.active_tool
+                match self
+                    .active_tool
                     .borrow_mut()
-                    .handle_event(ToolEvent::StyleChanged(self.style));
-                ToolUpdateResult::Redraw
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+                {
+                    ToolUpdateResult::Unmodified => ToolUpdateResult::Redraw,
+                    result => result,
+                }
             }
             ToolbarEvent::SaveFileAs => self.handle_action(&[Action::SaveToFileAs]),
+            ToolbarEvent::InsertImage => {
+                self.handle_open_image(sender);
+                ToolUpdateResult::Unmodified
+            }
             ToolbarEvent::ScaleFitToWindow => self.handle_scale(0),
             ToolbarEvent::ScaleOriginal => self.handle_scale(1),
             ToolbarEvent::ToolCommit => self.active_tool.borrow_mut().handle_deactivated(),
@@ -906,9 +1316,7 @@ impl SketchBoard {
                 // Here we're basically bypassing the IMMulticontext. If the text tool is active
                 // and wants text inputs, we're interested in the single-letter keypress as a text character.
                 // If not, we parse it as a shortcut event.
-                if self.active_tool_type() == Tools::Text
-                    && self.active_tool.borrow().input_enabled()
-                {
+                if self.text_input_active() {
                     sender.input(SketchBoardInput::new_text_event(TextEventMsg::Commit(
                         txt.to_string(),
                     )));
@@ -919,9 +1327,7 @@ impl SketchBoard {
                 cursor_chars,
                 spans,
             } => {
-                if self.active_tool_type() == Tools::Text
-                    && self.active_tool.borrow().input_enabled()
-                {
+                if self.text_input_active() {
                     sender.input(SketchBoardInput::new_text_event(TextEventMsg::Preedit {
                         text,
                         cursor_chars,
@@ -930,9 +1336,7 @@ impl SketchBoard {
                 }
             }
             TextEventMsg::PreeditEnd => {
-                if self.active_tool_type() == Tools::Text
-                    && self.active_tool.borrow().input_enabled()
-                {
+                if self.text_input_active() {
                     sender.input(SketchBoardInput::new_text_event(TextEventMsg::PreeditEnd));
                 }
             }
@@ -944,6 +1348,12 @@ impl SketchBoard {
         self.active_tool.borrow().get_tool_type()
     }
 
+    /// Whether the active tool is a text tool waiting for typed text.
+    fn text_input_active(&self) -> bool {
+        matches!(self.active_tool_type(), Tools::Text | Tools::Callout)
+            && self.active_tool.borrow().input_enabled()
+    }
+
     fn dispatch_key_shortcut_command(
         &mut self,
         command: ShortcutCommand,
@@ -999,6 +1409,12 @@ impl SketchBoard {
                 ));
                 ToolUpdateResult::Unmodified
             }
+            ShortcutCommand::CycleStrokePattern => {
+                sender.input(SketchBoardInput::ToolbarEvent(
+                    ToolbarEvent::CycleStrokePattern,
+                ));
+                ToolUpdateResult::Unmodified
+            }
             ShortcutCommand::Undo => self.handle_undo(),
             ShortcutCommand::Redo => self.handle_redo(),
             ShortcutCommand::ToggleToolbars => self.handle_toggle_toolbars_display(sender),
@@ -1042,8 +1458,32 @@ impl SketchBoard {
                 ToolUpdateResult::Unmodified
             }
             ShortcutCommand::Scale(scale) => self.handle_scale(scale),
-            ShortcutCommand::DeleteSelection => {
-                // Placeholder for future delete selection implementation
+            ShortcutCommand::DeleteSelection => self.handle_delete_selection(),
+            ShortcutCommand::RaiseSelection => self.handle_reorder_selection(ZOrder::Raise),
+            ShortcutCommand::LowerSelection => self.handle_reorder_selection(ZOrder::Lower),
+            ShortcutCommand::SelectionToFront => self.handle_reorder_selection(ZOrder::ToFront),
+            ShortcutCommand::SelectionToBack => self.handle_reorder_selection(ZOrder::ToBack),
+            ShortcutCommand::CopySelection => {
+                self.copy_selection();
+                ToolUpdateResult::Unmodified
+            }
+            ShortcutCommand::CutSelection => {
+                if self.copy_selection() {
+                    // a cut selection is pasted back at its original position first
+                    self.paste_count = 0;
+                    self.handle_delete_selection()
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            ShortcutCommand::PasteSelection => self.handle_paste_selection(),
+            ShortcutCommand::DuplicateSelection => self.handle_duplicate_selection(),
+            ShortcutCommand::InsertImage => {
+                self.handle_open_image(sender);
+                ToolUpdateResult::Unmodified
+            }
+            ShortcutCommand::PasteImage => {
+                self.handle_paste_image(sender);
                 ToolUpdateResult::Unmodified
             }
             ShortcutCommand::ClearAll => self.handle_clear_all(),
@@ -1066,7 +1506,7 @@ impl Component for SketchBoard {
     type CommandOutput = ();
     type Input = SketchBoardInput;
     type Output = SketchBoardOutput;
-    type Init = Pixbuf;
+    type Init = Project;
 
     view! {
         gtk::Box {
@@ -1087,6 +1527,7 @@ impl Component for SketchBoard {
                                 controller.current_button(),
                                 1,
                                 controller.current_event_state(),
+                                StylusAxes::from_event(controller.current_event()),
                                 Vec2D::new(x as f32, y as f32),
                                 false,
                             ));
@@ -1098,6 +1539,7 @@ impl Component for SketchBoard {
                                 controller.current_button(),
                                 1,
                                 controller.current_event_state(),
+                                StylusAxes::from_event(controller.current_event()),
                                 Vec2D::new(x as f32, y as f32),
                                 false,
                             ));
@@ -1108,6 +1550,7 @@ impl Component for SketchBoard {
                                 controller.current_button(),
                                 1,
                                 controller.current_event_state(),
+                                StylusAxes::from_event(controller.current_event()),
                                 Vec2D::new(x as f32, y as f32),
                                 false
                             ));
@@ -1122,6 +1565,7 @@ impl Component for SketchBoard {
                             controller.current_button(),
                             n_pressed,
                             controller.current_event_state(),
+                            StylusAxes::from_event(controller.current_event()),
                             Vec2D::new(x as f32, y as f32),
                             false,
                         ));
@@ -1132,6 +1576,7 @@ impl Component for SketchBoard {
                             controller.current_button(),
                             n_released,
                             controller.current_event_state(),
+                            StylusAxes::from_event(controller.current_event()),
                             Vec2D::new(x as f32, y as f32),
                             true,
                         ));
@@ -1200,6 +1645,32 @@ impl Component for SketchBoard {
                     },
                 },
 
+                add_controller = gtk::DropTarget::new(gtk::glib::Type::INVALID, DragAction::COPY) {
+                    set_types: &[FileList::static_type(), Texture::static_type()],
+                    connect_drop[sender] => move |_, value, x, y| {
+                        let image = if let Ok(files) = value.get::<FileList>() {
+                            Self::pixbuf_from_files(&files)
+                        } else if let Ok(texture) = value.get::<Texture>() {
+                            Self::pixbuf_from_texture(&texture)
+                        } else {
+                            Err(anyhow!("unsupported drop"))
+                        };
+                        match image {
+                            Ok(image) => {
+                                sender.input(SketchBoardInput::InsertImage(
+                                    image,
+                                    Some(Vec2D::new(x as f32, y as f32)),
+                                ));
+                                true
+                            }
+                            Err(e) => {
+                                log_result(&format!("Error inserting image: {e}"), true);
+                                false
+                            }
+                        }
+                    }
+                },
+
                 add_controller = gtk::EventControllerMotion {
                     connect_motion[sender] => move |controller, x, y| {
                         sender.input(SketchBoardInput::new_mouse_event(
@@ -1207,6 +1678,7 @@ impl Component for SketchBoard {
                             0,
                             0,
                             controller.current_event_state(),
+                            StylusAxes::default(),
                             Vec2D::new(x as f32, y as f32),
                             false
                         ));
@@ -1217,8 +1689,7 @@ impl Component for SketchBoard {
     }
 
     fn update(&mut self, msg: SketchBoardInput, sender: ComponentSender<Self>, _root: &Self::Root) {
-        let ime_should_be_enabled =
-            self.active_tool_type() == Tools::Text && self.active_tool.borrow().input_enabled();
+        let ime_should_be_enabled = self.text_input_active();
 
         if !self.ime_enabled.get() && ime_should_be_enabled {
             self.ime_enabled.set(true);
@@ -1321,6 +1792,9 @@ impl Component for SketchBoard {
                 self.handle_text_commit(txt, sender);
                 ToolUpdateResult::Unmodified
             }
+            SketchBoardInput::InsertImage(image, pos) => {
+                self.handle_insert_image(image, pos, sender)
+            }
             SketchBoardInput::Refresh => ToolUpdateResult::Redraw,
             SketchBoardInput::Exit => {
                 self.handle_exit();
@@ -1344,28 +1818,19 @@ impl Component for SketchBoard {
                 .emit(SketchBoardOutput::ToolEditingChanged(editing));
         }
 
-        match result {
-            ToolUpdateResult::Commit(drawable) => {
-                self.renderer.commit(drawable);
-                if APP_CONFIG.read().auto_copy() {
-                    self.renderer.request_render(&[Action::SaveToClipboard]);
-                }
-                self.refresh_screen();
-            }
+        match self.commit_change(result) {
             ToolUpdateResult::Unmodified | ToolUpdateResult::StopPropagation => (),
-            ToolUpdateResult::Redraw | ToolUpdateResult::RedrawAndStopPropagation => {
-                self.refresh_screen()
-            }
+            _ => self.refresh_screen(),
         };
     }
 
     fn init(
-        image: Self::Init,
+        project: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let config = APP_CONFIG.read();
-        let tools = ToolsManager::new();
+        let tools = ToolsManager::new(&project.background);
 
         let im_context = gtk::IMMulticontext::new();
 
@@ -1385,15 +1850,23 @@ impl Component for SketchBoard {
             tools,
             im_context,
             last_saved_filepath: RefCell::new(None),
+            clipboard: Vec::new(),
+            paste_count: 0,
         };
 
         let area = &mut model.renderer;
         area.init(
             sender.input_sender().clone(),
             model.tools.get_crop_tool(),
+            model.tools.get_pointer_tool(),
+            model.tools.get_drawables(),
             model.active_tool.clone(),
-            image,
+            project.background,
         );
+        model.tools.restore(project.drawables);
+        if let Some(crop) = project.crop {
+            model.tools.get_crop_tool().borrow_mut().restore(crop);
+        }
 
         let widgets = view_output!();
 
@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt};
 
 use femtovg::{LineCap, Paint};
 use hex_color::HexColor;
@@ -8,19 +8,29 @@ use relm4::gtk::gdk_pixbuf::{
     prelude::{StaticVariantType, ToVariant},
 };
 use relm4::gtk::glib::variant::FromVariant;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::configuration::APP_CONFIG;
 
-#[derive(Clone, Copy, Debug)]
+use satty_cli::command_line;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Style {
     pub color: Color,
     pub size: Size,
     pub fill: bool,
     pub round_caps: bool,
     pub annotation_size_factor: f32,
+    #[serde(default)]
+    pub arrow_start: ArrowHead,
+    #[serde(default = "ArrowHead::default_end")]
+    pub arrow_end: ArrowHead,
+    #[serde(default)]
+    pub stroke_pattern: StrokePattern,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -28,7 +38,8 @@ pub struct Color {
     pub a: u8,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Size {
     Small = 0,
     #[default]
@@ -36,6 +47,29 @@ pub enum Size {
     Large = 2,
 }
 
+/// The pattern of dashes outlines are stroked with.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StrokePattern {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+    DashDot,
+}
+
+/// The decoration at either end of an arrow.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArrowHead {
+    #[default]
+    None = 0,
+    Triangle = 1,
+    Chevron = 2,
+    Circle = 3,
+    Bar = 4,
+}
+
 impl Default for Style {
     fn default() -> Self {
         Self {
@@ -44,7 +78,49 @@ impl Default for Style {
             fill: APP_CONFIG.read().default_fill_shapes(),
             round_caps: APP_CONFIG.read().default_round_caps(),
             annotation_size_factor: APP_CONFIG.read().annotation_size_factor(),
+            arrow_start: APP_CONFIG.read().default_arrow_start(),
+            arrow_end: APP_CONFIG.read().default_arrow_end(),
+            stroke_pattern: APP_CONFIG.read().default_stroke_pattern(),
+        }
+    }
+}
+
+impl Style {
+    /// Returns this style with every property that differs between `old` and `new`
+    /// taken over from `new`.
+    pub fn with_changes(self, old: &Style, new: &Style) -> Self {
+        fn changed<T: PartialEq>(current: T, old: T, new: T) -> T {
+            if old != new { new } else { current }
         }
+
+        Self {
+            color: changed(self.color, old.color, new.color),
+            size: changed(self.size, old.size, new.size),
+            fill: changed(self.fill, old.fill, new.fill),
+            round_caps: changed(self.round_caps, old.round_caps, new.round_caps),
+            annotation_size_factor: changed(
+                self.annotation_size_factor,
+                old.annotation_size_factor,
+                new.annotation_size_factor,
+            ),
+            arrow_start: changed(self.arrow_start, old.arrow_start, new.arrow_start),
+            arrow_end: changed(self.arrow_end, old.arrow_end, new.arrow_end),
+            stroke_pattern: changed(self.stroke_pattern, old.stroke_pattern, new.stroke_pattern),
+        }
+    }
+
+    /// The lengths of the alternating dashes and gaps outlines are stroked with, empty if
+    /// they are solid.
+    pub fn line_dash(&self) -> Vec<f32> {
+        self.stroke_pattern.dashes(
+            self.size.to_line_width(self.annotation_size_factor),
+            self.round_caps,
+        )
+    }
+
+    /// The paint for outlines, dashed following the stroke pattern.
+    pub fn dashed_paint(&self) -> Paint {
+        Paint::from(*self).with_line_dash(&self.line_dash())
     }
 }
 
@@ -210,6 +286,145 @@ impl From<Style> for Paint {
     }
 }
 
+impl StrokePattern {
+    /// The pattern following this one when cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Solid => Self::Dashed,
+            Self::Dashed => Self::Dotted,
+            Self::Dotted => Self::DashDot,
+            Self::DashDot => Self::Solid,
+        }
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::Solid => "line-horizontal-1-regular",
+            Self::Dashed => "line-horizontal-1-dashes-regular",
+            Self::Dotted => "more-horizontal-regular",
+            Self::DashDot => "line-horizontal-1-dashes-filled",
+        }
+    }
+
+    /// The lengths of the alternating dashes and gaps for a line of the given width, empty
+    /// for a solid line.
+    pub fn dashes(self, line_width: f32, round_caps: bool) -> Vec<f32> {
+        let pattern: &[f32] = match self {
+            Self::Solid => &[],
+            Self::Dashed => &[3.0, 2.0],
+            Self::Dotted => &[1.0, 1.0],
+            Self::DashDot => &[4.0, 2.0, 1.0, 2.0],
+        };
+        // round caps reach half the line width beyond both ends of every dash, a dot
+        // shrinks to (almost) nothing and is drawn by its caps alone
+        let caps = if round_caps { 1.0 } else { 0.0 };
+        pattern
+            .iter()
+            .enumerate()
+            .map(|(i, length)| {
+                if i % 2 == 0 {
+                    ((length - caps) * line_width).max(line_width * 0.05)
+                } else {
+                    (length + caps) * line_width
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for StrokePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Solid => "Solid",
+            Self::Dashed => "Dashed",
+            Self::Dotted => "Dotted",
+            Self::DashDot => "Dash-Dot",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<command_line::StrokePattern> for StrokePattern {
+    fn from(pattern: command_line::StrokePattern) -> Self {
+        match pattern {
+            command_line::StrokePattern::Solid => Self::Solid,
+            command_line::StrokePattern::Dashed => Self::Dashed,
+            command_line::StrokePattern::Dotted => Self::Dotted,
+            command_line::StrokePattern::DashDot => Self::DashDot,
+        }
+    }
+}
+
+impl ArrowHead {
+    pub const ALL: [ArrowHead; 5] = [
+        Self::None,
+        Self::Triangle,
+        Self::Chevron,
+        Self::Circle,
+        Self::Bar,
+    ];
+
+    /// The head drawn at the end of arrows saved before heads could be chosen.
+    fn default_end() -> Self {
+        Self::Triangle
+    }
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::None => "line-horizontal-1-regular",
+            Self::Triangle => "play-filled",
+            Self::Chevron => "chevron-right-regular",
+            Self::Circle => "record-filled",
+            Self::Bar => "divider-tall-regular",
+        }
+    }
+}
+
+impl fmt::Display for ArrowHead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::None => "None",
+            Self::Triangle => "Triangle",
+            Self::Chevron => "Chevron",
+            Self::Circle => "Dot",
+            Self::Bar => "Bar",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<command_line::ArrowHead> for ArrowHead {
+    fn from(head: command_line::ArrowHead) -> Self {
+        match head {
+            command_line::ArrowHead::None => Self::None,
+            command_line::ArrowHead::Triangle => Self::Triangle,
+            command_line::ArrowHead::Chevron => Self::Chevron,
+            command_line::ArrowHead::Circle => Self::Circle,
+            command_line::ArrowHead::Bar => Self::Bar,
+        }
+    }
+}
+
+impl StaticVariantType for ArrowHead {
+    fn static_variant_type() -> Cow<'static, VariantTy> {
+        Cow::Borrowed(VariantTy::UINT32)
+    }
+}
+
+impl ToVariant for ArrowHead {
+    fn to_variant(&self) -> Variant {
+        Variant::from(*self as u32)
+    }
+}
+
+impl FromVariant for ArrowHead {
+    fn from_variant(variant: &Variant) -> Option<Self> {
+        variant
+            .get::<u32>()
+            .and_then(|v| Self::ALL.into_iter().find(|head| *head as u32 == v))
+    }
+}
+
 impl StaticVariantType for Size {
     fn static_variant_type() -> Cow<'static, VariantTy> {
         Cow::Borrowed(VariantTy::UINT32)
@@ -274,6 +489,14 @@ impl Size {
         }
     }
 
+    pub fn to_pixelate_block_size(self, size_factor: f32) -> f32 {
+        match self {
+            Size::Small => 8.0 * size_factor,
+            Size::Medium => 16.0 * size_factor,
+            Size::Large => 24.0 * size_factor,
+        }
+    }
+
     pub fn to_highlight_width(self, size_factor: f32) -> f32 {
         match self {
             Size::Small => 15.0 * size_factor,
@@ -282,3 +505,56 @@ impl Size {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Size, StrokePattern, Style};
+
+    #[test]
+    fn solid_lines_have_no_dashes() {
+        assert!(StrokePattern::Solid.dashes(4.0, false).is_empty());
+        assert!(StrokePattern::Solid.dashes(4.0, true).is_empty());
+    }
+
+    #[test]
+    fn dashes_scale_with_line_width() {
+        assert_eq!(StrokePattern::Dashed.dashes(2.0, false), [6.0, 4.0]);
+        assert_eq!(
+            StrokePattern::DashDot.dashes(1.0, false),
+            [4.0, 2.0, 1.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn round_caps_move_a_line_width_from_dashes_to_gaps() {
+        assert_eq!(StrokePattern::Dashed.dashes(2.0, true), [4.0, 6.0]);
+
+        let dash_dot = StrokePattern::DashDot.dashes(2.0, true);
+        assert_eq!(dash_dot[..2], [6.0, 6.0]);
+        assert_eq!(dash_dot[3], 6.0);
+    }
+
+    #[test]
+    fn dots_with_round_caps_keep_a_minimal_dash() {
+        let dashes = StrokePattern::Dotted.dashes(2.0, true);
+        assert_eq!(dashes.len(), 2);
+        assert!(dashes[0] > 0.0 && dashes[0] <= 0.1);
+        assert_eq!(dashes[1], 4.0);
+    }
+
+    #[test]
+    fn line_dash_follows_the_style() {
+        let style = Style {
+            size: Size::Large,
+            annotation_size_factor: 2.0,
+            round_caps: true,
+            stroke_pattern: StrokePattern::DashDot,
+            ..Style::default()
+        };
+
+        assert_eq!(
+            style.line_dash(),
+            StrokePattern::DashDot.dashes(Size::Large.to_line_width(2.0), true)
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::fmt::{Display, Write};
+
+use anyhow::{Result, anyhow};
+use relm4::gtk::{gdk_pixbuf::Pixbuf, glib};
+
+use crate::{
+    configuration::APP_CONFIG,
+    math::{self, Vec2D},
+    style::{Color, Style},
+    tools::{Drawable, ToolsManager},
+};
+
+/// File extension of SVG exports.
+pub const SVG_EXTENSION: &str = "svg";
+
+pub fn is_svg_file(filename: &str) -> bool {
+    std::path::Path::new(filename)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(SVG_EXTENSION))
+}
+
+/// An SVG document with the screenshot embedded as image, drawables add their
+/// vector elements on top using `Drawable::draw_svg`.
+pub struct SvgDocument {
+    background: Pixbuf,
+    /// The exported part (pos, size) of the background, the crop or the whole image.
+    bounds: (Vec2D, Vec2D),
+    body: String,
+    next_id: usize,
+}
+
+/// A single SVG element, attribute values and text are escaped.
+pub struct Element {
+    name: &'static str,
+    attributes: String,
+    content: String,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+impl Element {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            attributes: String::new(),
+            content: String::new(),
+        }
+    }
+
+    pub fn attr(mut self, name: &str, value: impl Display) -> Self {
+        let _ = write!(
+            self.attributes,
+            " {}=\"{}\"",
+            name,
+            escape(&value.to_string())
+        );
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.content.push_str(&escape(text));
+        self
+    }
+
+    pub fn child(mut self, element: Element) -> Self {
+        element.write(&mut self.content);
+        self
+    }
+
+    fn write(&self, out: &mut String) {
+        let _ = write!(out, "<{}{}", self.name, self.attributes);
+        if self.content.is_empty() {
+            out.push_str("/>\n");
+        } else {
+            let _ = writeln!(out, ">{}</{}>", self.content, self.name);
+        }
+    }
+
+    pub fn fill(self, color: Color) -> Self {
+        self.attr("fill", hex(color))
+            .attr("fill-opacity", color.a as f32 / 255.0)
+    }
+
+    /// Stroke with the color, line width and caps of the style, matching `Paint::from(Style)`.
+    pub fn stroke(self, style: &Style) -> Self {
+        self.stroke_color(style.color)
+            .attr(
+                "stroke-width",
+                style.size.to_line_width(style.annotation_size_factor),
+            )
+            .attr(
+                "stroke-linecap",
+                if style.round_caps { "round" } else { "butt" },
+            )
+    }
+
+    /// Dash the stroke following the stroke pattern of the style, matching `Style::line_dash`.
+    pub fn dashes(self, style: &Style) -> Self {
+        let dashes = style.line_dash();
+        if dashes.is_empty() {
+            return self;
+        }
+        let dasharray: Vec<String> = dashes.iter().map(ToString::to_string).collect();
+        self.attr("stroke-dasharray", dasharray.join(" "))
+    }
+
+    pub fn stroke_color(self, color: Color) -> Self {
+        self.attr("stroke", hex(color))
+            .attr("stroke-opacity", color.a as f32 / 255.0)
+    }
+
+    /// Fill with the style color if the style is filled, stroke the outline otherwise.
+    pub fn paint(self, style: &Style) -> Self {
+        if style.fill {
+            self.fill(style.color)
+        } else {
+            self.attr("fill", "none").stroke(style)
+        }
+    }
+
+    /// A rectangle with the corners rounded like the femtovg renderer does.
+    pub fn rounded_rect(pos: Vec2D, size: Vec2D) -> Self {
+        let (pos, size) = math::rect_ensure_positive_size(pos, size);
+        let radius = APP_CONFIG
+            .read()
+            .corner_roundness()
+            .min(size.x / 2.0)
+            .min(size.y / 2.0);
+        Self::new("rect")
+            .attr("x", pos.x)
+            .attr("y", pos.y)
+            .attr("width", size.x)
+            .attr("height", size.y)
+            .attr("rx", radius)
+    }
+
+    /// The configured font family and its fallbacks at the given size.
+    pub fn font(self, size: f32) -> Self {
+        let config = APP_CONFIG.read();
+        let families = config
+            .font()
+            .family()
+            .into_iter()
+            .chain(config.font().fallback().iter().map(String::as_str))
+            .map(|family| format!("'{family}'"))
+            .chain(std::iter::once("sans-serif".to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.attr("font-family", families).attr("font-size", size)
+    }
+
+    /// A path through the given polylines, closing them if requested.
+    pub fn path(polylines: &[Vec<Vec2D>], close: bool) -> Self {
+        let mut data = String::new();
+        for points in polylines {
+            for (i, p) in points.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                let _ = write!(data, "{command}{} {} ", p.x, p.y);
+            }
+            if close {
+                data.push_str("Z ");
+            }
+        }
+        Self::new("path").attr("d", data.trim_end())
+    }
+}
+
+impl SvgDocument {
+    pub fn new(background: Pixbuf, bounds: (Vec2D, Vec2D)) -> Self {
+        Self {
+            background,
+            bounds,
+            body: String::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn push(&mut self, element: Element) {
+        element.write(&mut self.body);
+    }
+
+    /// A document wide unique id for elements referenced by others.
+    pub fn unique_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}-{}", self.next_id)
+    }
+
+    /// The size of the background image.
+    pub fn background_size(&self) -> Vec2D {
+        Vec2D::new(
+            self.background.width() as f32,
+            self.background.height() as f32,
+        )
+    }
+
+    /// Embed a raster image as base64 encoded PNG.
+    pub fn image(&mut self, image: &Pixbuf, pos: Vec2D, size: Vec2D) -> Result<()> {
+        self.push(Self::image_element(image, pos, size)?);
+        Ok(())
+    }
+
+    /// An image element embedding the raster image, for callers that add attributes.
+    pub fn image_element(image: &Pixbuf, pos: Vec2D, size: Vec2D) -> Result<Element> {
+        let data = image.save_to_bufferv("png", &[])?;
+        Ok(Element::new("image")
+            .attr("x", pos.x)
+            .attr("y", pos.y)
+            .attr("width", size.x)
+            .attr("height", size.y)
+            .attr(
+                "href",
+                format!("data:image/png;base64,{}", glib::base64_encode(&data)),
+            ))
+    }
+
+    /// The area (pos, size) of the background and the area it covers, clamped to the exported
+    /// part of the image so that no cropped away pixels are embedded. The returned pixbuf
+    /// shares its pixels with the background.
+    pub fn background_area(&self, pos: Vec2D, size: Vec2D) -> Option<((Vec2D, Vec2D), Pixbuf)> {
+        let (pos, size) = math::rect_round(math::rect_ensure_in_bounds(
+            math::rect_ensure_positive_size(pos, size),
+            self.bounds,
+        ));
+        if size.x < 1.0 || size.y < 1.0 {
+            return None;
+        }
+        let area =
+            self.background
+                .new_subpixbuf(pos.x as i32, pos.y as i32, size.x as i32, size.y as i32);
+        Some(((pos, size), area))
+    }
+
+    /// Serialize the document, showing the `viewport` (pos, size) of the image.
+    pub fn finish(self, viewport: (Vec2D, Vec2D)) -> Vec<u8> {
+        let (pos, size) = viewport;
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n{}</svg>\n",
+            size.x, size.y, pos.x, pos.y, size.x, size.y, self.body
+        )
+        .into_bytes()
+    }
+}
+
+/// A snapshot of the annotated image to export as SVG.
+pub struct SvgExport {
+    background: Pixbuf,
+    drawables: Vec<Box<dyn Drawable>>,
+    /// The crop rectangle (pos, size), the whole image is exported without.
+    crop: Option<(Vec2D, Vec2D)>,
+}
+
+impl SvgExport {
+    /// Copy the committed drawables and the crop of the tools.
+    pub fn new(tools: &ToolsManager, background: Pixbuf) -> Self {
+        let drawables = tools
+            .get_drawables()
+            .borrow()
+            .iter()
+            .map(|d| d.clone_box())
+            .collect();
+        let crop = tools
+            .get_crop_tool()
+            .borrow()
+            .get_crop()
+            .map(|c| c.get_rectangle());
+        Self {
+            background,
+            drawables,
+            crop,
+        }
+    }
+
+    /// The drawables as vector graphics over the embedded background image, limited to
+    /// the crop rectangle like the rendered image.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let bounds = image_bounds(&self.background);
+        let viewport = self
+            .crop
+            .map(|rect| math::rect_round(math::rect_ensure_in_bounds(rect, bounds)))
+            .filter(|(_, size)| !size.is_zero())
+            .unwrap_or(bounds);
+
+        let background = self.redacted_background()?;
+        let mut svg = SvgDocument::new(background, viewport);
+        // only the cropped part of the background is embedded, not just shown
+        if let Some(((pos, size), area)) = svg.background_area(viewport.0, viewport.1) {
+            svg.image(&area, pos, size)?;
+        }
+        let (backdrops, others): (Vec<_>, Vec<_>) =
+            self.drawables.iter().partition(|d| d.is_backdrop());
+        for d in backdrops.into_iter().chain(others) {
+            d.draw_svg(&mut svg)?;
+        }
+        Ok(svg.finish(viewport))
+    }
+
+    /// A copy of the background with the redacted areas filled and the blurred ones obscured,
+    /// so that the embedded image doesn't contain the hidden pixels.
+    fn redacted_background(&self) -> Result<Pixbuf> {
+        let background = self
+            .background
+            .copy()
+            .ok_or_else(|| anyhow!("Cannot copy the background image"))?;
+        let svg = SvgDocument::new(background.clone(), image_bounds(&background));
+        for (rect, color) in self.drawables.iter().filter_map(|d| d.redaction()) {
+            // the sub pixbuf shares its pixels with the background
+            if let Some((_, area)) = svg.background_area(rect.0, rect.1) {
+                area.fill(color.to_rgba_u32());
+            }
+        }
+        for d in &self.drawables {
+            d.obscure_background(&svg)?;
+        }
+        Ok(background)
+    }
+}
+
+/// The rectangle (pos, size) covered by the whole image.
+fn image_bounds(image: &Pixbuf) -> (Vec2D, Vec2D) {
+    (
+        Vec2D::zero(),
+        Vec2D::new(image.width() as f32, image.height() as f32),
+    )
+}
@@ -4,30 +4,64 @@ use relm4::{
     Sender,
     gtk::gdk::{Key, ModifierType},
 };
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::{Angle, Vec2D},
+    math::{self, Angle, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
-    style::Style,
+    style::{ArrowHead, Style},
+    svg::{Element, SvgDocument},
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools,
+    handles::{self, HANDLE_BORDER, HANDLE_RADIUS},
+};
+
+/// The number of straight segments a curved shaft is approximated with.
+const CURVE_SEGMENTS: usize = 32;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Arrow {
     start: Vec2D,
     end: Option<Vec2D>,
     style: Style,
+    /// The control point of a quadratic Bezier curve bending the shaft, straight if None.
+    #[serde(default)]
+    control: Option<Vec2D>,
+    /// Placed with Ctrl held and showing the handle to bend it.
+    #[serde(skip)]
+    bending: bool,
 }
 
 #[derive(Default)]
 pub struct ArrowTool {
     arrow: Option<Arrow>,
+    /// Where the drag of the bending handle started.
+    handle_drag: Option<Vec2D>,
     style: Style,
     input_enabled: bool,
     sender: Option<Sender<SketchBoardInput>>,
 }
 
+impl ArrowTool {
+    /// Commit the arrow whose curve is being bent.
+    fn finish_bending(&mut self) -> ToolUpdateResult {
+        match self.arrow.take() {
+            Some(mut arrow) if arrow.bending => {
+                arrow.bending = false;
+                self.handle_drag = None;
+                ToolUpdateResult::Commit(arrow.clone_box())
+            }
+            arrow => {
+                self.arrow = arrow;
+                ToolUpdateResult::Unmodified
+            }
+        }
+    }
+}
+
 impl Tool for ArrowTool {
     fn input_enabled(&self) -> bool {
         self.input_enabled
@@ -45,6 +79,10 @@ impl Tool for ArrowTool {
         Tools::Arrow
     }
 
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.finish_bending()
+    }
+
     fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
         match event.type_ {
             MouseEventType::BeginDrag => {
@@ -52,20 +90,43 @@ impl Tool for ArrowTool {
                     return ToolUpdateResult::Unmodified;
                 }
 
+                if let Some(a) = &self.arrow
+                    && a.bending
+                    && a.curve_midpoint().is_some_and(|midpoint| {
+                        midpoint.distance_to(&event.pos) <= HANDLE_RADIUS + HANDLE_BORDER
+                    })
+                {
+                    self.handle_drag = Some(event.pos);
+                    return ToolUpdateResult::Redraw;
+                }
+
+                // a press anywhere else keeps the bent arrow as it is
+                let result = match self.finish_bending() {
+                    ToolUpdateResult::Unmodified => ToolUpdateResult::Redraw,
+                    result => result,
+                };
+
                 // start new
                 self.arrow = Some(Arrow {
                     start: event.pos,
                     end: None,
                     style: self.style,
+                    control: None,
+                    bending: event.modifier.intersects(ModifierType::CONTROL_MASK),
                 });
 
-                ToolUpdateResult::Redraw
+                result
             }
             MouseEventType::EndDrag => {
                 if event.button == MouseButton::Middle {
                     return ToolUpdateResult::Unmodified;
                 }
 
+                if let (Some(a), Some(origin)) = (&mut self.arrow, self.handle_drag.take()) {
+                    a.set_curve_midpoint(origin + event.pos);
+                    return ToolUpdateResult::Redraw;
+                }
+
                 if let Some(a) = &mut self.arrow {
                     if event.pos == Vec2D::zero() {
                         self.arrow = None;
@@ -77,6 +138,12 @@ impl Tool for ArrowTool {
                         } else {
                             a.end = Some(a.start + event.pos);
                         }
+
+                        if a.bending {
+                            // keep it to bend the shaft with the handle in its middle
+                            return ToolUpdateResult::Redraw;
+                        }
+
                         let result = a.clone_box();
                         self.arrow = None;
 
@@ -91,6 +158,11 @@ impl Tool for ArrowTool {
                     return ToolUpdateResult::Unmodified;
                 }
 
+                if let (Some(a), Some(origin)) = (&mut self.arrow, self.handle_drag) {
+                    a.set_curve_midpoint(origin + event.pos);
+                    return ToolUpdateResult::Redraw;
+                }
+
                 if let Some(a) = &mut self.arrow {
                     if event.pos == Vec2D::zero() {
                         return ToolUpdateResult::Unmodified;
@@ -113,7 +185,10 @@ impl Tool for ArrowTool {
     fn handle_key_event(&mut self, event: crate::sketch_board::KeyEventMsg) -> ToolUpdateResult {
         if event.key == Key::Escape && self.arrow.is_some() {
             self.arrow = None;
+            self.handle_drag = None;
             ToolUpdateResult::Redraw
+        } else if event.key == Key::Return || event.key == Key::KP_Enter {
+            self.finish_bending()
         } else {
             ToolUpdateResult::Unmodified
         }
@@ -128,7 +203,14 @@ impl Tool for ArrowTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        if let Some(a) = &mut self.arrow
+            && a.bending
+        {
+            a.style = style;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
@@ -136,17 +218,269 @@ impl Tool for ArrowTool {
     }
 }
 
-impl Drawable for Arrow {
-    fn draw(
-        &self,
-        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: FontId,
-        _bounds: (Vec2D, Vec2D),
-    ) -> Result<()> {
-        let end = match self.end {
-            Some(e) => e,
-            None => return Ok(()), // exit if no end
+impl Arrow {
+    pub fn new(start: Vec2D, end: Vec2D, style: Style) -> Self {
+        Self {
+            start,
+            end: Some(end),
+            style,
+            control: None,
+            bending: false,
+        }
+    }
+
+    /// Bend the shaft into a quadratic Bezier curve with the given control point.
+    pub fn with_control(mut self, control: Vec2D) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    fn curve(&self) -> Option<Curve> {
+        Some(Curve::new(self.start, self.control?, self.end?))
+    }
+
+    /// The point halfway along the shaft, where the handle bending it is placed.
+    fn curve_midpoint(&self) -> Option<Vec2D> {
+        let end = self.end?;
+        let control = self.control.unwrap_or((self.start + end) * 0.5);
+        Some((self.start + end) * 0.25 + control * 0.5)
+    }
+
+    /// Bend the shaft so that its middle passes through the given point.
+    fn set_curve_midpoint(&mut self, midpoint: Vec2D) {
+        if let Some(end) = self.end {
+            self.control = Some(midpoint * 2.0 - (self.start + end) * 0.5);
+        }
+    }
+}
+
+/// A quadratic Bezier curve approximated by a polyline, measured along its length.
+struct Curve {
+    points: Vec<Vec2D>,
+    lengths: Vec<f32>,
+}
+
+impl Curve {
+    fn new(start: Vec2D, control: Vec2D, end: Vec2D) -> Self {
+        let points: Vec<Vec2D> = (0..=CURVE_SEGMENTS)
+            .map(|i| {
+                let t = i as f32 / CURVE_SEGMENTS as f32;
+                start * ((1.0 - t) * (1.0 - t)) + control * (2.0 * (1.0 - t) * t) + end * (t * t)
+            })
+            .collect();
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut length = 0.0;
+        for (i, p) in points.iter().enumerate() {
+            if i > 0 {
+                length += p.distance_to(&points[i - 1]);
+            }
+            lengths.push(length);
+        }
+        Self { points, lengths }
+    }
+
+    fn length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// The point `along` the curve, moved by `side` to the left of it (seen in y-down
+    /// coordinates). Beyond its ends the curve is continued in a straight line.
+    fn point_at(&self, along: f32, side: f32) -> Vec2D {
+        let i = self
+            .lengths
+            .partition_point(|&l| l <= along)
+            .clamp(1, self.points.len() - 1);
+        let segment = self.points[i] - self.points[i - 1];
+        let segment_length = self.lengths[i] - self.lengths[i - 1];
+        let direction = if segment_length > f32::EPSILON {
+            segment * (1.0 / segment_length)
+        } else {
+            Vec2D::new(1.0, 0.0)
         };
+        self.points[i - 1]
+            + direction * (along - self.lengths[i - 1])
+            + Vec2D::new(-direction.y, direction.x) * side
+    }
+
+    /// Bend points given relative to a straight shaft along the x-axis onto the curve. Long
+    /// segments are subdivided so that they follow it.
+    fn bend(&self, points: &[Vec2D]) -> Vec<Vec2D> {
+        let origin = self.points[0];
+        let step = self.length() / CURVE_SEGMENTS as f32;
+        let mut bent = Vec::new();
+        for (i, p) in points.iter().enumerate() {
+            if i > 0 && step > f32::EPSILON {
+                let previous = points[i - 1];
+                let steps = ((p.x - previous.x).abs() / step).ceil() as usize;
+                for s in 1..steps {
+                    let q = previous + (*p - previous) * (s as f32 / steps as f32);
+                    bent.push(self.point_at(q.x, q.y) - origin);
+                }
+            }
+            bent.push(self.point_at(p.x, p.y) - origin);
+        }
+        bent
+    }
+}
+
+/// The shape of an arrow in a coordinate system with the start at the origin and the end on
+/// the positive x-axis.
+struct Outline {
+    angle: Angle,
+    /// Closed shapes, each filled and outlined separately.
+    filled: Vec<Vec<Vec2D>>,
+    /// Lines stroked with the line width.
+    stroked: Vec<Vec<Vec2D>>,
+    /// The shaft of a thin arrow, stroked following the stroke pattern.
+    shaft: Option<Vec<Vec2D>>,
+}
+
+/// The dimensions shared by the heads at both ends of an arrow.
+struct HeadSize {
+    /// The width filled shapes are outlined with, 0 if they are only filled.
+    line_width: f32,
+    round_caps: bool,
+    /// Half the width of the tail of a fat arrow where it joins a head.
+    tail_half_width: f32,
+    /// Half the width of the tail of a fat arrow at an end without a head.
+    tail_half_width_end: f32,
+    /// How far back from the point the sides of a triangle head reach.
+    length: f32,
+    /// Half the width of a triangle head.
+    half_width: f32,
+    /// How far the tail of a fat arrow reaches into a triangle head.
+    midpoint_offset: f32,
+}
+
+/// A head in a coordinate system with its point at the origin and the shaft along the positive
+/// x-axis.
+#[derive(Default)]
+struct Cap {
+    /// Where the shaft of a thin arrow ends.
+    shaft_end: f32,
+    /// One side of a fat arrow from where its tail joins the head to the point. The other side
+    /// is mirrored.
+    profile: Vec<Vec2D>,
+    filled: Vec<Vec<Vec2D>>,
+    stroked: Vec<Vec<Vec2D>>,
+}
+
+fn polylines_path(polylines: &[Vec<Vec2D>]) -> Path {
+    let mut path = Path::new();
+    for points in polylines {
+        path.move_to(points[0].x, points[0].y);
+        for p in &points[1..] {
+            path.line_to(p.x, p.y);
+        }
+    }
+    path
+}
+
+/// A closed polygon approximating a circle.
+fn circle(center: Vec2D, radius: f32) -> Vec<Vec2D> {
+    const STEPS: usize = 24;
+    (0..=STEPS)
+        .map(|i| {
+            center
+                + Vec2D::from_angle(Angle::from_degrees(i as f32 * 360.0 / STEPS as f32)) * radius
+        })
+        .collect()
+}
+
+fn head_cap(head: ArrowHead, size: &HeadSize, fat: bool) -> Cap {
+    let line_width = size.line_width;
+    let length = size.length;
+    let half_width = size.half_width;
+    let tail_half_width = size.tail_half_width;
+    let dot_radius = half_width * 0.6;
+
+    match (head, fat) {
+        (ArrowHead::None, true) => Cap {
+            profile: vec![Vec2D::new(0.0, size.tail_half_width_end)],
+            ..Default::default()
+        },
+        (ArrowHead::None, false) => Cap {
+            // round caps reach beyond the end of the line
+            shaft_end: if size.round_caps {
+                0.0
+            } else {
+                -line_width / 2.0
+            },
+            ..Default::default()
+        },
+        (ArrowHead::Triangle, true) => Cap {
+            profile: vec![
+                Vec2D::new(length - size.midpoint_offset, tail_half_width),
+                Vec2D::new(length, half_width),
+                Vec2D::zero(),
+            ],
+            ..Default::default()
+        },
+        (ArrowHead::Triangle, false) => Cap {
+            shaft_end: length,
+            filled: vec![vec![
+                Vec2D::new(length, half_width),
+                Vec2D::new(line_width / 2.0, 0.0),
+                Vec2D::new(length, -half_width),
+                Vec2D::new(length, half_width),
+            ]],
+            ..Default::default()
+        },
+        (ArrowHead::Chevron, true) => Cap {
+            // a barbed head, the tail reaching far into it
+            profile: vec![
+                Vec2D::new(length * 0.5, tail_half_width),
+                Vec2D::new(length, half_width),
+                Vec2D::zero(),
+            ],
+            ..Default::default()
+        },
+        (ArrowHead::Chevron, false) => Cap {
+            shaft_end: line_width / 2.0,
+            stroked: vec![vec![
+                Vec2D::new(length, half_width),
+                Vec2D::new(line_width / 2.0, 0.0),
+                Vec2D::new(length, -half_width),
+            ]],
+            ..Default::default()
+        },
+        (ArrowHead::Circle, _) => Cap {
+            shaft_end: dot_radius,
+            profile: vec![Vec2D::new(dot_radius, tail_half_width)],
+            filled: vec![circle(
+                Vec2D::new(dot_radius, 0.0),
+                dot_radius - line_width / 2.0,
+            )],
+            ..Default::default()
+        },
+        (ArrowHead::Bar, true) => {
+            let back = line_width / 2.0 + tail_half_width;
+            Cap {
+                profile: vec![Vec2D::new(back / 2.0, tail_half_width)],
+                filled: vec![vec![
+                    Vec2D::new(line_width / 2.0, half_width),
+                    Vec2D::new(line_width / 2.0, -half_width),
+                    Vec2D::new(back, -half_width),
+                    Vec2D::new(back, half_width),
+                    Vec2D::new(line_width / 2.0, half_width),
+                ]],
+                ..Default::default()
+            }
+        }
+        (ArrowHead::Bar, false) => Cap {
+            shaft_end: line_width / 2.0,
+            stroked: vec![vec![
+                Vec2D::new(line_width / 2.0, half_width),
+                Vec2D::new(line_width / 2.0, -half_width),
+            ]],
+            ..Default::default()
+        },
+    }
+}
+
+impl Arrow {
+    fn outline(&self) -> Option<Outline> {
+        let end = self.end?;
 
         // Fat arrow:
         //          C
@@ -177,10 +511,14 @@ impl Drawable for Arrow {
         // Arrow length: the distance from the start to the end (1 - 2).
         // Head angle: the angle of the head point at end (2).
         // Tail width: the distance from tail side to tail side (5 - 6).
+        //
+        // The heads at both ends are built pointing left with their point at the origin, and
+        // then moved to the start or flipped to the end.
 
         let round_caps = self.style.round_caps;
+        let fat = self.style.fill;
 
-        let line_width = if round_caps || !self.style.fill {
+        let line_width = if round_caps || !fat {
             self.style
                 .size
                 .to_line_width(self.style.annotation_size_factor)
@@ -189,16 +527,13 @@ impl Drawable for Arrow {
         };
 
         let arrow_offset = end - self.start;
-        let arrow_length = arrow_offset.norm() - line_width / 2.0;
+        let curve = self.curve();
+        let shaft_length = curve
+            .as_ref()
+            .map_or_else(|| arrow_offset.norm(), Curve::length);
+        let arrow_length = shaft_length - line_width / 2.0;
         let arrow_direction = arrow_offset * (1.0 / arrow_length);
 
-        // We rotate the canvas so that we can draw the arrow on the x-axis.
-        // start will be at (0,0)
-        // end will be at (length, 0)
-        canvas.save();
-        canvas.translate(self.start.x, self.start.y);
-        canvas.rotate(arrow_direction.angle().radians);
-
         // The width of the tail (double distance from start to head side)
         let tail_width = self
             .style
@@ -220,59 +555,256 @@ impl Drawable for Arrow {
         let midpoint_offset = head_side_length * 0.1;
 
         let head_angle = Angle::from_degrees(60.0); // The angle of the point of the arrow head.
+        let head_half_angle = head_angle * 0.5;
 
-        let tail_half_width_head = tail_width / 2.0;
-        let tail_half_width_end = if round_caps {
-            line_width / 10.0
-        } else {
-            tail_width / 2.0
+        let size = HeadSize {
+            line_width,
+            round_caps,
+            tail_half_width: tail_width / 2.0,
+            tail_half_width_end: if round_caps {
+                line_width / 10.0
+            } else {
+                tail_width / 2.0
+            },
+            length: head_half_angle.cos() * head_side_length,
+            half_width: head_half_angle.sin() * head_side_length,
+            midpoint_offset,
         };
-        let head_half_angle = head_angle * 0.5;
-        let head_left =
-            Vec2D::new(arrow_length, 0.0) - Vec2D::from_angle(head_half_angle) * head_side_length;
-        let midpoint_x = head_left.x + midpoint_offset;
-
-        if self.style.fill {
-            // Draw a 'fat' arrow.
-            let mut path = Path::new();
-            path.move_to(midpoint_x, tail_half_width_head); // G
-            path.line_to(head_left.x, -head_left.y); // C
-            path.line_to(arrow_length, 0.0); // B
-            path.line_to(head_left.x, head_left.y); // C (mirrored)
-            path.line_to(midpoint_x, -tail_half_width_head); // G (mirrored)
-            if midpoint_x > line_width / 2.0 {
-                // If the midpoint is placed _before_ the start, there is only a head and no tail.
-                // We can skip the beginning of the tail.
-                path.line_to(line_width / 2.0, -tail_half_width_end); // F
-                path.line_to(line_width / 2.0, tail_half_width_end); // E
+        let start_cap = head_cap(self.style.arrow_start, &size, fat);
+        let end_cap = head_cap(self.style.arrow_end, &size, fat);
+
+        let start_tip = shaft_length - arrow_length;
+        let at_start = |p: &Vec2D| Vec2D::new(start_tip + p.x, p.y);
+        let at_end = |p: &Vec2D| Vec2D::new(arrow_length - p.x, p.y);
+        let mirrored = |p: &Vec2D| Vec2D::new(p.x, -p.y);
+
+        let mut filled: Vec<Vec<Vec2D>> = end_cap
+            .filled
+            .iter()
+            .map(|points| points.iter().map(at_end).collect())
+            .chain(
+                start_cap
+                    .filled
+                    .iter()
+                    .map(|points| points.iter().map(at_start).collect()),
+            )
+            .collect();
+        let stroked: Vec<Vec<Vec2D>> = end_cap
+            .stroked
+            .iter()
+            .map(|points| points.iter().map(at_end).collect())
+            .chain(
+                start_cap
+                    .stroked
+                    .iter()
+                    .map(|points| points.iter().map(at_start).collect()),
+            )
+            .collect();
+
+        let mut shaft = None;
+        if fat {
+            // A 'fat' arrow, going around from the head at the end to the one at the start.
+            let mut points: Vec<Vec2D> = end_cap.profile.iter().map(at_end).collect();
+            points.extend(
+                end_cap
+                    .profile
+                    .iter()
+                    .rev()
+                    .filter(|p| p.y != 0.0)
+                    .map(|p| at_end(&mirrored(p))),
+            );
+            if at_start(&start_cap.profile[0]).x < points[0].x {
+                // If the head at the end reaches _beyond_ where the tail starts, there is only
+                // a head and no tail. We can skip the beginning of the tail.
+                points.extend(start_cap.profile.iter().map(|p| at_start(&mirrored(p))));
+                points.extend(
+                    start_cap
+                        .profile
+                        .iter()
+                        .rev()
+                        .filter(|p| p.y != 0.0)
+                        .map(at_start),
+                );
             }
-            path.line_to(midpoint_x, tail_half_width_head); // G
+            points.push(points[0]);
+            filled.insert(0, points);
+        } else {
+            // A 'thin' arrow, a line between the heads.
+            shaft = Some(vec![
+                at_start(&Vec2D::new(start_cap.shaft_end, 0.0)),
+                at_end(&Vec2D::new(end_cap.shaft_end, 0.0)),
+            ]);
+        }
+
+        // a curved arrow is drawn as if straight and then bent along the curve, which keeps
+        // the heads pointing in the direction the curve ends in
+        match curve {
+            Some(curve) => Some(Outline {
+                angle: Angle::from_radians(0.0),
+                filled: filled.iter().map(|points| curve.bend(points)).collect(),
+                stroked: stroked.iter().map(|points| curve.bend(points)).collect(),
+                shaft: shaft.map(|points| curve.bend(&points)),
+            }),
+            None => Some(Outline {
+                angle: arrow_direction.angle(),
+                filled,
+                stroked,
+                shaft,
+            }),
+        }
+    }
+}
+
+impl Drawable for Arrow {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some(outline) = self.outline() else {
+            return Ok(()); // exit if no end
+        };
+
+        // We rotate the canvas so that we can draw the arrow on the x-axis.
+        // start will be at (0,0)
+        // end will be at (length, 0)
+        canvas.save();
+        canvas.translate(self.start.x, self.start.y);
+        canvas.rotate(outline.angle.radians);
 
-            if round_caps {
+        let outlined = self.style.round_caps || !self.style.fill;
+        for points in &outline.filled {
+            let path = polylines_path(std::slice::from_ref(points));
+            if outlined {
                 let mut paint: Paint = self.style.into();
                 paint.set_line_join(LineJoin::Round);
                 // paint.set_color(femtovg::Color::white()); // to debug
                 canvas.stroke_path(&path, &paint);
-                canvas.fill_path(&path, &self.style.into());
-            } else {
-                canvas.fill_path(&path, &self.style.into());
             }
-        } else {
-            // Draw a 'thin' arrow head.
-            let arrow_length = arrow_length - line_width / 2.0;
-            let arrow_start = if round_caps { line_width / 2.0 } else { 0.0 };
-            let mut path = Path::new();
-            path.move_to(head_left.x, -head_left.y); // C
-            path.line_to(arrow_length, 0.0); // B
-            path.line_to(head_left.x, head_left.y); // C (mirrored)
-
-            path.move_to(arrow_start, 0.0); // A
-            path.line_to(arrow_length, 0.0); // B
-
-            canvas.stroke_path(&path, &self.style.into());
+            canvas.fill_path(&path, &self.style.into());
+        }
+
+        if !outline.stroked.is_empty() {
+            canvas.stroke_path(&polylines_path(&outline.stroked), &self.style.into());
+        }
+        if let Some(shaft) = outline.shaft {
+            canvas.stroke_path(
+                &polylines_path(std::slice::from_ref(&shaft)),
+                &self.style.dashed_paint(),
+            );
         }
 
         canvas.restore();
+
+        if self.bending
+            && let Some(midpoint) = self.curve_midpoint()
+        {
+            let scale = canvas.transform().average_scale();
+            handles::draw_handle(canvas, midpoint, scale);
+        }
         Ok(())
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        let Some(outline) = self.outline() else {
+            return Ok(());
+        };
+
+        let transform = format!(
+            "translate({} {}) rotate({})",
+            self.start.x,
+            self.start.y,
+            outline.angle.radians.to_degrees()
+        );
+        for points in &outline.filled {
+            let path = Element::path(std::slice::from_ref(points), false)
+                .attr("transform", &transform)
+                .fill(self.style.color);
+            svg.push(if self.style.round_caps || !self.style.fill {
+                path.stroke(&self.style)
+                    .attr("stroke-linejoin", "round")
+                    .attr("paint-order", "stroke")
+            } else {
+                path
+            });
+        }
+        if !outline.stroked.is_empty() {
+            svg.push(
+                Element::path(&outline.stroked, false)
+                    .attr("transform", &transform)
+                    .attr("fill", "none")
+                    .stroke(&self.style),
+            );
+        }
+        if let Some(shaft) = outline.shaft {
+            svg.push(
+                Element::path(&[shaft], false)
+                    .attr("transform", &transform)
+                    .attr("fill", "none")
+                    .stroke(&self.style)
+                    .dashes(&self.style),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        if let Some(curve) = self.curve() {
+            return math::rect_from_points(curve.points);
+        }
+        self.end
+            .and_then(|end| math::rect_from_points([self.start, end]))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let Some(end) = self.end else {
+            return false;
+        };
+        let size = self.style.size;
+        let factor = self.style.annotation_size_factor;
+        let tail_margin = tolerance
+            + size
+                .to_line_width(factor)
+                .max(size.to_arrow_tail_width(factor))
+                / 2.0;
+
+        let shaft_distance = match self.curve() {
+            Some(curve) => math::distance_to_polyline(pos, &curve.points),
+            None => math::distance_to_segment(pos, self.start, end),
+        };
+        let head_margin = tolerance + size.to_arrow_head_length(factor);
+        shaft_distance <= tail_margin
+            || (self.style.arrow_end != ArrowHead::None && pos.distance_to(&end) <= head_margin)
+            || (self.style.arrow_start != ArrowHead::None
+                && pos.distance_to(&self.start) <= head_margin)
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        self.start = math::rect_map_point(self.start, from, to);
+        self.end = self.end.map(|end| math::rect_map_point(end, from, to));
+        self.control = self
+            .control
+            .map(|control| math::rect_map_point(control, from, to));
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn control_handle(&self) -> Option<Vec2D> {
+        self.curve_midpoint()
+    }
+
+    fn set_control_handle(&mut self, pos: Vec2D) {
+        self.set_curve_midpoint(pos);
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Arrow(*self))
+    }
 }
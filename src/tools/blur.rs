@@ -1,37 +1,140 @@
-use std::cell::RefCell;
+use std::{borrow::Cow, cell::RefCell, fmt};
 
-use anyhow::Result;
-use femtovg::{Color, ImageFilter, ImageFlags, ImageId, Paint, Path, imgref::Img};
+use anyhow::{Result, anyhow};
+use femtovg::{
+    Color, ImageFilter, ImageFlags, ImageId, Paint, Path,
+    imgref::{Img, ImgVec},
+    rgb::{ComponentBytes, RGBA8},
+};
 
 use relm4::{
     Sender,
-    gtk::gdk::{Key, ModifierType},
+    gtk::{
+        gdk::{Key, ModifierType},
+        gdk_pixbuf::{Colorspace, InterpType, Pixbuf},
+        glib::{self, Bytes, Variant, VariantTy, prelude::*, variant::FromVariant},
+    },
 };
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
     math::{self, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
-    style::Style,
+    style::{self, Style},
+    svg::SvgDocument,
 };
 
+use satty_cli::command_line;
+
 use super::{
-    Drawable, DrawableClone, Tool, ToolUpdateResult, Tools,
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools,
     drag_box::{DragBox, draw_center_marker},
+    redact::pixel_rect,
 };
 
-#[derive(Clone, Debug)]
+/// How the blur tool obscures the area.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlurMode {
+    #[default]
+    Blur = 0,
+    Pixelate = 1,
+    Fill = 2,
+    Noise = 3,
+}
+
+impl BlurMode {
+    pub const ALL: [BlurMode; 4] = [Self::Blur, Self::Pixelate, Self::Fill, Self::Noise];
+
+    pub fn icon_name(self) -> &'static str {
+        match self {
+            Self::Blur => "drop-regular",
+            Self::Pixelate => "grid-regular",
+            Self::Fill => "square-filled",
+            Self::Noise => "blur-regular",
+        }
+    }
+}
+
+impl fmt::Display for BlurMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Blur => "Blur",
+            Self::Pixelate => "Pixelate",
+            Self::Fill => "Solid Fill",
+            Self::Noise => "Blur + Noise",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl From<command_line::BlurMode> for BlurMode {
+    fn from(mode: command_line::BlurMode) -> Self {
+        match mode {
+            command_line::BlurMode::Blur => Self::Blur,
+            command_line::BlurMode::Pixelate => Self::Pixelate,
+            command_line::BlurMode::Fill => Self::Fill,
+            command_line::BlurMode::Noise => Self::Noise,
+        }
+    }
+}
+
+impl StaticVariantType for BlurMode {
+    fn static_variant_type() -> Cow<'static, VariantTy> {
+        Cow::Borrowed(VariantTy::UINT32)
+    }
+}
+
+impl ToVariant for BlurMode {
+    fn to_variant(&self) -> Variant {
+        Variant::from(*self as u32)
+    }
+}
+
+impl FromVariant for BlurMode {
+    fn from_variant(variant: &Variant) -> Option<Self> {
+        variant
+            .get::<u32>()
+            .and_then(|v| Self::ALL.into_iter().find(|mode| *mode as u32 == v))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Blur {
+    #[serde(skip)]
     origin: Vec2D,
     top_left: Vec2D,
     size: Option<Vec2D>,
     style: Style,
+    #[serde(default)]
+    mode: BlurMode,
+    #[serde(skip)]
     centered: bool,
+    #[serde(skip)]
     editing: bool,
+    #[serde(skip)]
     cached_image: RefCell<Option<ImageId>>,
+    #[serde(skip)]
+    cached_noise: RefCell<Option<ImageId>>,
 }
 
 impl Blur {
+    pub fn new(top_left: Vec2D, size: Vec2D, style: Style, mode: BlurMode) -> Self {
+        Self {
+            origin: top_left,
+            top_left,
+            size: Some(size),
+            style,
+            mode,
+            centered: false,
+            editing: false,
+            cached_image: RefCell::new(None),
+            cached_noise: RefCell::new(None),
+        }
+    }
+
     fn calculate_shape(&mut self, pos: Vec2D, modifier: ModifierType) {
         let drag_box = DragBox::from_origin_delta(self.origin, pos, modifier);
         self.centered = drag_box.centered;
@@ -39,12 +142,24 @@ impl Blur {
         self.size = Some(drag_box.size);
     }
 
-    fn blur(
+    fn blur_factor(&self) -> f32 {
+        self.style
+            .size
+            .to_blur_factor(self.style.annotation_size_factor)
+    }
+
+    fn pixelate_block_size(&self) -> f32 {
+        self.style
+            .size
+            .to_pixelate_block_size(self.style.annotation_size_factor)
+    }
+
+    /// The area of the canvas as rendered so far, in device pixels.
+    fn capture(
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
         pos: Vec2D,
         size: Vec2D,
-        sigma: f32,
-    ) -> Result<ImageId> {
+    ) -> Result<ImgVec<RGBA8>> {
         let img = canvas.screenshot()?;
 
         let transformed_pos = canvas.transform().transform_point(pos.x, pos.y);
@@ -58,7 +173,16 @@ impl Blur {
                 (transformed_size.y as usize).max(1),
             )
             .to_contiguous_buf();
-        let sub = Img::new(buf.into_owned(), width, height);
+        Ok(Img::new(buf.into_owned(), width, height))
+    }
+
+    fn blur(
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        pos: Vec2D,
+        size: Vec2D,
+        sigma: f32,
+    ) -> Result<ImageId> {
+        let sub = Self::capture(canvas, pos, size)?;
 
         let src_image_id = canvas.create_image(sub.as_ref(), ImageFlags::empty())?;
         let dst_image_id = canvas.create_image_empty(
@@ -73,10 +197,87 @@ impl Blur {
             ImageFilter::GaussianBlur { sigma },
             src_image_id,
         );
-        //canvas.delete_image(src_image_id);
+        // the filter only runs on flush, the source is needed until then
+        canvas.flush();
+        canvas.delete_image(src_image_id);
 
         Ok(dst_image_id)
     }
+
+    fn pixelate(
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        pos: Vec2D,
+        size: Vec2D,
+        block_size: f32,
+    ) -> Result<ImageId> {
+        let mut sub = Self::capture(canvas, pos, size)?;
+        let block_size =
+            ((block_size * canvas.transform().average_scale()).round() as usize).max(1);
+        let (width, height) = (sub.width(), sub.height());
+        let pixels = sub.buf_mut();
+
+        // replace every block with its average color
+        for block_y in (0..height).step_by(block_size) {
+            for block_x in (0..width).step_by(block_size) {
+                let rows = block_y..(block_y + block_size).min(height);
+                let columns = block_x..(block_x + block_size).min(width);
+
+                let mut sum = [0u32; 4];
+                for y in rows.clone() {
+                    for p in &pixels[y * width + columns.start..y * width + columns.end] {
+                        sum[0] += p.r as u32;
+                        sum[1] += p.g as u32;
+                        sum[2] += p.b as u32;
+                        sum[3] += p.a as u32;
+                    }
+                }
+                let count = (rows.len() * columns.len()) as u32;
+                let average = RGBA8::new(
+                    (sum[0] / count) as u8,
+                    (sum[1] / count) as u8,
+                    (sum[2] / count) as u8,
+                    (sum[3] / count) as u8,
+                );
+                for y in rows {
+                    pixels[y * width + columns.start..y * width + columns.end].fill(average);
+                }
+            }
+        }
+
+        Ok(canvas.create_image(sub.as_ref(), ImageFlags::empty())?)
+    }
+
+    fn draw_image(
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        path: &Path,
+        image: ImageId,
+        pos: Vec2D,
+        size: Vec2D,
+    ) {
+        canvas.fill_path(
+            path,
+            &Paint::image(image, pos.x, pos.y, size.x, size.y, 0f32, 1f32),
+        );
+    }
+}
+
+/// Grain laid over a blurred area, so the blur can't be reversed. Every pixel is a random gray
+/// of the same translucency.
+fn noise_pixels(width: usize, height: usize) -> Vec<RGBA8> {
+    const NOISE_ALPHA: u8 = 140;
+
+    // xorshift from a fresh random seed for every image, which is never stored: knowing the
+    // noise would allow subtracting it again. Xorshift never leaves a zero state.
+    let mut state: u32 = glib::random_int() | 1;
+    (0..width * height)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let gray = (state >> 24) as u8;
+            RGBA8::new(gray, gray, gray, NOISE_ALPHA)
+        })
+        .collect()
 }
 
 impl Drawable for Blur {
@@ -94,6 +295,17 @@ impl Drawable for Blur {
             math::rect_ensure_positive_size(self.top_left, size),
             bounds,
         );
+
+        // make rect
+        let mut path = Path::new();
+        path.rounded_rect(
+            pos.x,
+            pos.y,
+            size.x,
+            size.y,
+            APP_CONFIG.read().corner_roundness(),
+        );
+
         if self.editing {
             if self.centered {
                 draw_center_marker(canvas, self.origin);
@@ -104,16 +316,6 @@ impl Drawable for Blur {
             color.set_alphaf(0.6);
             let paint = Paint::color(color);
 
-            // make rect
-            let mut path = Path::new();
-            path.rounded_rect(
-                pos.x,
-                pos.y,
-                size.x,
-                size.y,
-                APP_CONFIG.read().corner_roundness(),
-            );
-
             // draw
             canvas.fill_path(&path, &paint);
         } else {
@@ -121,56 +323,226 @@ impl Drawable for Blur {
                 return Ok(());
             }
 
+            if self.mode == BlurMode::Fill {
+                // a translucent fill would not hide anything
+                canvas.fill_path(
+                    &path,
+                    &Paint::color(self.style.color.with_alpha(255).into()),
+                );
+                return Ok(());
+            }
+
             canvas.save();
             canvas.flush();
 
             // create new cached image
             if self.cached_image.borrow().is_none() {
-                self.cached_image.borrow_mut().replace(Self::blur(
+                let image = match self.mode {
+                    BlurMode::Pixelate => {
+                        Self::pixelate(canvas, pos, size, self.pixelate_block_size())?
+                    }
+                    _ => Self::blur(canvas, pos, size, self.blur_factor())?,
+                };
+                self.cached_image.borrow_mut().replace(image);
+            }
+            if self.mode == BlurMode::Noise && self.cached_noise.borrow().is_none() {
+                let (width, height) = (size.x.ceil() as usize, size.y.ceil() as usize);
+                let noise = Img::new(noise_pixels(width, height), width, height);
+                self.cached_noise
+                    .borrow_mut()
+                    .replace(canvas.create_image(noise.as_ref(), ImageFlags::NEAREST)?);
+            }
+
+            // these unwraps are safe because we placed them above
+            Self::draw_image(
+                canvas,
+                &path,
+                self.cached_image.borrow().unwrap(),
+                pos,
+                size,
+            );
+            if self.mode == BlurMode::Noise {
+                Self::draw_image(
                     canvas,
+                    &path,
+                    self.cached_noise.borrow().unwrap(),
                     pos,
                     size,
-                    self.style
-                        .size
-                        .to_blur_factor(self.style.annotation_size_factor),
-                )?);
+                );
             }
+            canvas.restore();
+        }
+        Ok(())
+    }
 
-            let mut path = Path::new();
-            path.rounded_rect(
-                pos.x,
-                pos.y,
-                size.x,
-                size.y,
-                APP_CONFIG.read().corner_roundness(),
-            );
+    fn draw_magnified(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        bounds: (Vec2D, Vec2D),
+        source: (Vec2D, Vec2D),
+        inset: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        // filled areas are redactions, which are magnified along with them
+        if self.editing || self.mode == BlurMode::Fill {
+            return Ok(());
+        }
+        let (Some(size), Some(image)) = (self.size, *self.cached_image.borrow()) else {
+            return Ok(());
+        };
+        // the same area the cached image was captured from
+        let area = math::rect_ensure_in_bounds(
+            math::rect_ensure_positive_size(self.top_left, size),
+            bounds,
+        );
+        if math::rect_intersection(area, source).is_none() {
+            return Ok(());
+        }
 
-            canvas.fill_path(
-                &path,
-                &Paint::image(
-                    self.cached_image.borrow().unwrap(), // this unwrap is safe because we placed it above
-                    pos.x,
-                    pos.y,
-                    size.x,
-                    size.y,
-                    0f32,
-                    1f32,
-                ),
+        let pos = math::rect_map_point(area.0, source, inset);
+        let size = math::rect_map_vector(area.1, source, inset);
+        let mut path = Path::new();
+        path.rect(pos.x, pos.y, size.x, size.y);
+
+        canvas.save();
+        canvas.intersect_scissor(inset.0.x, inset.0.y, inset.1.x, inset.1.y);
+        Self::draw_image(canvas, &path, image, pos, size);
+        if let Some(noise) = *self.cached_noise.borrow() {
+            Self::draw_image(canvas, &path, noise, pos, size);
+        }
+        canvas.restore();
+        Ok(())
+    }
+
+    fn draw_svg(&self, _svg: &mut SvgDocument) -> Result<()> {
+        // the embedded background holds the obscured area already, see obscure_background
+        Ok(())
+    }
+
+    fn obscure_background(&self, svg: &SvgDocument) -> Result<()> {
+        let Some(size) = self.size else {
+            return Ok(());
+        };
+        // filled areas are redactions
+        if self.mode == BlurMode::Fill {
+            return Ok(());
+        }
+
+        // the sub pixbuf shares its pixels with the background
+        let Some((_, area)) = svg.background_area(self.top_left, size) else {
+            return Ok(());
+        };
+        let (width, height) = (area.width(), area.height());
+
+        // SVG viewers don't agree on filters, so the area is rasterized: scaling down and up
+        // again smooths it similar to a gaussian blur, or results in blocks with nearest
+        // neighbour interpolation.
+        let (factor, interpolation) = match self.mode {
+            BlurMode::Pixelate => (self.pixelate_block_size(), InterpType::Nearest),
+            _ => (self.blur_factor() / 2.0, InterpType::Bilinear),
+        };
+        let factor = factor.max(1.0);
+        let obscured = area
+            .scale_simple(
+                ((width as f32 / factor) as i32).max(1),
+                ((height as f32 / factor) as i32).max(1),
+                InterpType::Tiles,
+            )
+            .and_then(|small| small.scale_simple(width, height, interpolation))
+            .ok_or(anyhow!("Could not scale the blurred area"))?;
+        obscured.copy_area(0, 0, width, height, &area, 0, 0);
+
+        if self.mode == BlurMode::Noise {
+            let (width, height) = (width as usize, height as usize);
+            let noise = Pixbuf::from_bytes(
+                &Bytes::from(noise_pixels(width, height).as_bytes()),
+                Colorspace::Rgb,
+                true,
+                8,
+                width as i32,
+                height as i32,
+                width as i32 * 4,
+            );
+            noise.composite(
+                &area,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                0.0,
+                0.0,
+                1.0,
+                1.0,
+                InterpType::Nearest,
+                255,
             );
-            canvas.restore();
         }
         Ok(())
     }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            self.size = Some(math::rect_map_point(self.top_left + size, from, to) - top_left);
+            self.top_left = top_left;
+        }
+        self.origin = math::rect_map_point(self.origin, from, to);
+    }
+
+    fn refresh_capture(&mut self) {
+        // the blurred image has to be recreated from the new area
+        self.cached_image.replace(None);
+        self.cached_noise.replace(None);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        let previous = std::mem::replace(&mut self.style, style);
+        // the blur factor follows the size, other changes keep the blurred image
+        if (previous.size, previous.annotation_size_factor)
+            != (style.size, style.annotation_size_factor)
+        {
+            self.cached_image.replace(None);
+        }
+    }
+
+    fn redaction(&self) -> Option<((Vec2D, Vec2D), style::Color)> {
+        // a filled area hides its pixels just like a redaction, without any trace of them
+        if self.mode != BlurMode::Fill || self.editing {
+            return None;
+        }
+        self.bounds()
+            .map(|rect| (pixel_rect(rect), self.style.color.with_alpha(255)))
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Blur(self.clone()))
+    }
 }
 
 #[derive(Default)]
 pub struct BlurTool {
     blur: Option<Blur>,
     style: Style,
+    /// The mode selected in the toolbar, overriding the configured primary mode.
+    mode: Option<BlurMode>,
     input_enabled: bool,
     sender: Option<Sender<SketchBoardInput>>,
 }
 
+impl BlurTool {
+    pub fn set_mode(&mut self, mode: BlurMode) {
+        self.mode = Some(mode);
+    }
+}
+
 impl Tool for BlurTool {
     fn input_enabled(&self) -> bool {
         self.input_enabled
@@ -195,15 +567,28 @@ impl Tool for BlurTool {
                     return ToolUpdateResult::Unmodified;
                 }
 
+                // like the highlighter, the secondary mode is used when CTRL is pressed at the start
+                let config = APP_CONFIG.read();
+                let primary = self.mode.unwrap_or(config.primary_blur_mode());
+                let mode = if !event.modifier.intersects(ModifierType::CONTROL_MASK) {
+                    primary
+                } else if primary == config.secondary_blur_mode() {
+                    config.primary_blur_mode()
+                } else {
+                    config.secondary_blur_mode()
+                };
+
                 // start new
                 self.blur = Some(Blur {
                     origin: event.pos,
                     top_left: event.pos,
                     size: None,
                     style: self.style,
+                    mode,
                     centered: false,
                     editing: true,
                     cached_image: RefCell::new(None),
+                    cached_noise: RefCell::new(None),
                 });
 
                 ToolUpdateResult::Redraw
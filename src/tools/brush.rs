@@ -1,16 +1,27 @@
-use std::time::Instant;
+use std::{f32::consts::PI, time::Instant};
 
-use femtovg::{FontId, LineCap, LineJoin, Paint, Path};
+use femtovg::{FontId, LineJoin, Paint, Path};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
-    math::Vec2D,
-    sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
-    style::Style,
+    math::{self, Vec2D},
+    sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput, StylusAxes},
+    style::{StrokePattern, Style},
+    svg::{Element, SvgDocument},
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
-use relm4::Sender;
+use super::{
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools, shape_recognition,
+};
+use relm4::{Sender, gtk::gdk::ModifierType};
+
+/// How much a fully tilted stylus widens the stroke, on top of the pressure.
+const TILT_WIDENING: f32 = 0.5;
+/// The thinnest a stroke gets relative to its nominal width.
+const MIN_WIDTH_FACTOR: f32 = 0.1;
+/// Corners of the polygons approximating the round parts of variable width strokes.
+const ROUND_SEGMENTS: usize = 16;
 
 #[derive(Default)]
 pub struct BrushTool {
@@ -20,19 +31,106 @@ pub struct BrushTool {
     sender: Option<Sender<SketchBoardInput>>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct BrushDrawable {
     // The start point of the brush stroke this is relative to canvas
     // after this the points are relative to the start point
     start_point: Option<Vec2D>,
     points: Vec<Vec2D>,
+    /// The width of the stroke at each point relative to the nominal width, from the stylus
+    /// pressure. Empty for strokes with constant width.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    widths: Vec<f32>,
+    #[serde(skip)]
     smoother: Smoother,
     style: Style,
 }
 
 impl BrushDrawable {
-    fn add_point(&mut self, point: Vec2D) {
+    /// A finished stroke through the given points in image coordinates.
+    fn from_points(points: Vec<Vec2D>, widths: Vec<f32>, style: Style) -> Self {
+        let start_point = points.first().copied();
+        Self {
+            points: points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| match start_point {
+                    Some(start_point) if i > 0 => *p - start_point,
+                    _ => *p,
+                })
+                .collect(),
+            start_point,
+            widths,
+            smoother: Smoother::default(),
+            style,
+        }
+    }
+
+    fn add_point(&mut self, point: Vec2D, width: Option<f32>) {
         self.points.push(self.smoother.update(point));
+        if !self.widths.is_empty() {
+            // keep the last width for events without pressure
+            let width = width.or(self.widths.last().copied()).unwrap_or(1.0);
+            self.widths.push(width);
+        }
+    }
+
+    fn line_width(&self) -> f32 {
+        self.style
+            .size
+            .to_line_width(self.style.annotation_size_factor)
+    }
+
+    /// Whether the stroke is drawn with varying width, dashed strokes keep a constant width.
+    fn has_variable_width(&self) -> bool {
+        self.widths.len() == self.points.len() && self.style.stroke_pattern == StrokePattern::Solid
+    }
+
+    /// Polygons that together cover a variable width stroke: a circle around every point and
+    /// a quadrilateral joining each pair of neighbouring circles. All run in the same
+    /// direction, so that they fill as their union.
+    fn outline(&self) -> Vec<Vec<Vec2D>> {
+        let line_width = self.line_width();
+        let points = self.absolute_points();
+        let radii: Vec<f32> = self.widths.iter().map(|w| w * line_width / 2.0).collect();
+
+        let circles = points.iter().zip(&radii).map(|(center, radius)| {
+            (0..ROUND_SEGMENTS)
+                .map(|i| {
+                    let angle = -2.0 * PI * i as f32 / ROUND_SEGMENTS as f32;
+                    *center + Vec2D::new(angle.cos(), angle.sin()) * *radius
+                })
+                .collect()
+        });
+        let joints = points
+            .windows(2)
+            .zip(radii.windows(2))
+            .filter_map(|(p, r)| {
+                let direction = p[1] - p[0];
+                let length = direction.norm();
+                if length < f32::EPSILON {
+                    return None;
+                }
+                let normal = Vec2D::new(-direction.y, direction.x) * (1.0 / length);
+                Some(vec![
+                    p[0] + normal * r[0],
+                    p[1] + normal * r[1],
+                    p[1] - normal * r[1],
+                    p[0] - normal * r[0],
+                ])
+            });
+        circles.chain(joints).collect()
+    }
+
+    /// The points of the stroke in image coordinates.
+    fn absolute_points(&self) -> Vec<Vec2D> {
+        let Some(start_point) = self.start_point else {
+            return Vec::new();
+        };
+        std::iter::once(start_point)
+            .chain(self.points.iter().skip(1).map(|p| start_point + *p))
+            .collect()
     }
 }
 
@@ -51,6 +149,24 @@ impl Drawable for BrushDrawable {
             return Ok(());
         };
 
+        if self.has_variable_width() {
+            let mut path = Path::new();
+            for polygon in self.outline() {
+                for (i, p) in polygon.iter().enumerate() {
+                    if i == 0 {
+                        path.move_to(p.x, p.y);
+                    } else {
+                        path.line_to(p.x, p.y);
+                    }
+                }
+                path.close();
+            }
+            canvas.save();
+            canvas.fill_path(&path, &Paint::color(self.style.color.into()));
+            canvas.restore();
+            return Ok(());
+        }
+
         canvas.save();
         let mut path = Path::new();
 
@@ -61,13 +177,87 @@ impl Drawable for BrushDrawable {
 
         canvas.stroke_path(
             &path,
-            &Paint::from(self.style)
-                .with_line_cap(LineCap::Round)
-                .with_line_join(LineJoin::Round),
+            &Style {
+                round_caps: true,
+                ..self.style
+            }
+            .dashed_paint()
+            .with_line_join(LineJoin::Round),
         );
         canvas.restore();
         Ok(())
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> anyhow::Result<()> {
+        if self.has_variable_width() {
+            svg.push(Element::path(&self.outline(), true).fill(self.style.color));
+        } else if !self.points.is_empty() {
+            let style = Style {
+                round_caps: true,
+                ..self.style
+            };
+            svg.push(
+                Element::path(&[self.absolute_points()], false)
+                    .attr("fill", "none")
+                    .stroke(&style)
+                    .dashes(&style)
+                    .attr("stroke-linejoin", "round"),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        math::rect_from_points(self.absolute_points())
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let widest = self.widths.iter().copied().fold(1.0, f32::max);
+        let margin = tolerance + self.line_width() * widest / 2.0;
+        math::distance_to_polyline(pos, &self.absolute_points()) <= margin
+    }
+
+    fn erase(&self, start: Vec2D, end: Vec2D, radius: f32) -> Option<Vec<Box<dyn Drawable>>> {
+        let widest = self.widths.iter().copied().fold(1.0, f32::max);
+        let margin = radius + self.line_width() * widest / 2.0;
+        let pieces =
+            math::polyline_erase(&self.absolute_points(), &self.widths, start, end, margin)?;
+        Some(
+            pieces
+                .into_iter()
+                .map(|(points, widths)| Self::from_points(points, widths, self.style).clone_box())
+                .collect(),
+        )
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        let Some(start_point) = self.start_point else {
+            return;
+        };
+        let new_start_point = math::rect_map_point(start_point, from, to);
+
+        // the first point is the absolute click position, all others are relative to the start
+        for (i, p) in self.points.iter_mut().enumerate() {
+            *p = if i == 0 {
+                math::rect_map_point(*p, from, to)
+            } else {
+                math::rect_map_point(start_point + *p, from, to) - new_start_point
+            };
+        }
+        self.start_point = Some(new_start_point);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Brush(self.clone()))
+    }
 }
 
 impl Tool for BrushTool {
@@ -104,10 +294,21 @@ impl Tool for BrushTool {
                 let Some(brush) = &mut self.drawable else {
                     return ToolUpdateResult::Unmodified;
                 };
-                brush.add_point(event.pos);
-
-                // commit
-                let result = brush.clone_box();
+                brush.add_point(
+                    event.pos,
+                    width_factor(event.stylus, pressure_sensitivity()),
+                );
+
+                // commit, as the clean shape the stroke matches when snapping; ALT held at the
+                // end of the stroke inverts the configured choice
+                let snap = APP_CONFIG.read().brush_snap_to_shape()
+                    != event.modifier.contains(ModifierType::ALT_MASK);
+                let shape = if snap {
+                    shape_recognition::recognize(&brush.absolute_points(), brush.style)
+                } else {
+                    None
+                };
+                let result = shape.unwrap_or_else(|| brush.clone_box());
                 self.drawable = None;
 
                 ToolUpdateResult::Commit(result)
@@ -120,7 +321,10 @@ impl Tool for BrushTool {
                 let Some(brush) = &mut self.drawable else {
                     return ToolUpdateResult::Unmodified;
                 };
-                brush.add_point(event.pos);
+                brush.add_point(
+                    event.pos,
+                    width_factor(event.stylus, pressure_sensitivity()),
+                );
                 ToolUpdateResult::Redraw
             }
             MouseEventType::Click => {
@@ -131,6 +335,9 @@ impl Tool for BrushTool {
                     start_point: None,
                     smoother: Smoother::new(APP_CONFIG.read().brush_smooth_history_size()),
                     points: vec![event.pos],
+                    widths: width_factor(event.stylus, pressure_sensitivity())
+                        .into_iter()
+                        .collect(),
                     style: self.style,
                 });
                 ToolUpdateResult::Unmodified
@@ -160,7 +367,21 @@ impl Tool for BrushTool {
     }
 }
 
-#[derive(Debug, Clone)]
+fn pressure_sensitivity() -> f32 {
+    APP_CONFIG.read().brush_pressure_sensitivity()
+}
+
+/// The width of the stroke relative to its nominal width for the stylus state, or None for
+/// devices without pressure and with pressure sensitivity disabled.
+fn width_factor(stylus: StylusAxes, sensitivity: f32) -> Option<f32> {
+    let pressure = stylus.pressure.filter(|_| sensitivity > 0.0)?;
+    let tilt = stylus.tilt.map_or(0.0, |tilt| tilt.norm().min(1.0));
+    let factor = (1.0 + sensitivity * (2.0 * pressure.clamp(0.0, 1.0) - 1.0))
+        * (1.0 + sensitivity * TILT_WIDENING * tilt);
+    Some(factor.max(MIN_WIDTH_FACTOR))
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Smoother {
     history: Vec<Vec2D>, // last N raw inputs
     smoothed_point: Option<Vec2D>,
@@ -242,3 +463,55 @@ impl Smoother {
         min_alpha + (max_alpha - min_alpha) * norm
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MIN_WIDTH_FACTOR, width_factor};
+    use crate::{math::Vec2D, sketch_board::StylusAxes};
+
+    fn stylus(pressure: f32, tilt: Option<Vec2D>) -> StylusAxes {
+        StylusAxes {
+            pressure: Some(pressure),
+            tilt,
+        }
+    }
+
+    fn assert_factor(actual: Option<f32>, expected: f32) {
+        let actual = actual.expect("width factor");
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn constant_width_without_pressure_or_sensitivity() {
+        assert_eq!(width_factor(StylusAxes::default(), 1.0), None);
+        assert_eq!(width_factor(stylus(0.8, None), 0.0), None);
+    }
+
+    #[test]
+    fn pressure_scales_around_half_pressure() {
+        assert_factor(width_factor(stylus(0.5, None), 1.0), 1.0);
+        assert_factor(width_factor(stylus(1.0, None), 1.0), 2.0);
+        assert_factor(width_factor(stylus(1.0, None), 0.5), 1.5);
+        assert_factor(width_factor(stylus(0.0, None), 0.5), 0.5);
+    }
+
+    #[test]
+    fn width_never_drops_below_minimum() {
+        assert_factor(width_factor(stylus(0.0, None), 1.0), MIN_WIDTH_FACTOR);
+        assert_factor(width_factor(stylus(-1.0, None), 2.0), MIN_WIDTH_FACTOR);
+    }
+
+    #[test]
+    fn tilt_widens_up_to_full_tilt() {
+        let half_tilt = Some(Vec2D::new(0.0, 0.5));
+        let full_tilt = Some(Vec2D::new(1.0, 0.0));
+        let beyond = Some(Vec2D::new(3.0, 4.0));
+
+        assert_factor(width_factor(stylus(0.5, half_tilt), 1.0), 1.25);
+        assert_factor(width_factor(stylus(0.5, full_tilt), 1.0), 1.5);
+        assert_factor(width_factor(stylus(0.5, beyond), 1.0), 1.5);
+    }
+}
@@ -1,12 +1,14 @@
-use std::f32::consts::PI;
-
-use super::{Drawable, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, SavedDrawable, Tool, ToolUpdateResult, Tools,
+    handles::{self, Handle},
+};
 use crate::{
     math::{self, Vec2D},
     sketch_board::{
         KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput,
         SketchBoardOutput,
     },
+    svg::SvgDocument,
 };
 use anyhow::Result;
 use femtovg::{Color, Paint, Path};
@@ -29,9 +31,6 @@ pub struct CropTool {
 }
 
 impl Crop {
-    const HANDLE_RADIUS: f32 = 5.0;
-    const HANDLE_BORDER: f32 = 2.0;
-
     fn new(pos: Vec2D) -> Self {
         Self {
             pos,
@@ -40,70 +39,9 @@ impl Crop {
         }
     }
 
-    fn draw_single_handle(
-        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        center: Vec2D,
-        scale: f32,
-    ) {
-        let mut path = Path::new();
-        path.arc(
-            center.x,
-            center.y,
-            Crop::HANDLE_RADIUS / scale,
-            0.0,
-            2.0 * PI,
-            femtovg::Solidity::Solid,
-        );
-
-        let border_paint =
-            Paint::color(Color::rgbf(0.9, 0.9, 0.9)).with_line_width(Crop::HANDLE_BORDER / scale);
-        let fill_paint = Paint::color(Color::rgbaf(0.0, 0.0, 0.0, 0.4));
-
-        canvas.fill_path(&path, &fill_paint);
-        canvas.stroke_path(&path, &border_paint);
-    }
-
     pub fn get_rectangle(&self) -> (Vec2D, Vec2D) {
         math::rect_ensure_positive_size(self.pos, self.size)
     }
-
-    fn get_handle_pos(crop_pos: Vec2D, crop_size: Vec2D, handle: CropHandle) -> Vec2D {
-        match handle {
-            CropHandle::TopLeftCorner => crop_pos,
-            CropHandle::TopEdge => crop_pos + Vec2D::new(crop_size.x / 2.0, 0.0),
-            CropHandle::TopRightCorner => crop_pos + Vec2D::new(crop_size.x, 0.0),
-            CropHandle::RightEdge => crop_pos + Vec2D::new(crop_size.x, crop_size.y / 2.0),
-            CropHandle::BottomRightCorner => crop_pos + Vec2D::new(crop_size.x, crop_size.y),
-            CropHandle::BottomEdge => crop_pos + Vec2D::new(crop_size.x / 2.0, crop_size.y),
-            CropHandle::BottomLeftCorner => crop_pos + Vec2D::new(0.0, crop_size.y),
-            CropHandle::LeftEdge => crop_pos + Vec2D::new(0.0, crop_size.y / 2.0),
-        }
-    }
-    fn get_closest_handle(&self, mouse_pos: Vec2D) -> (CropHandle, f32) {
-        let mut min_distance_squared = f32::MAX;
-        let mut closest_handle = CropHandle::TopLeftCorner;
-        for h in CropHandle::all() {
-            let handle_pos = Self::get_handle_pos(self.pos, self.size, h);
-            let distance_squared = (handle_pos - mouse_pos).norm2();
-            if distance_squared < min_distance_squared {
-                min_distance_squared = distance_squared;
-                closest_handle = h;
-            }
-        }
-        (closest_handle, min_distance_squared)
-    }
-    fn test_handle_hit(&self, mouse_pos: Vec2D, margin2: f32) -> Option<CropHandle> {
-        const HANDLE_SIZE: f32 = Crop::HANDLE_RADIUS + Crop::HANDLE_BORDER;
-        const HANDLE_SIZE2: f32 = HANDLE_SIZE * HANDLE_SIZE;
-        let allowed_distance2 = HANDLE_SIZE2 + margin2;
-
-        let (handle, distance2) = self.get_closest_handle(mouse_pos);
-        if distance2 < allowed_distance2 {
-            Some(handle)
-        } else {
-            None
-        }
-    }
 }
 
 impl Drawable for Crop {
@@ -133,31 +71,32 @@ impl Drawable for Crop {
         canvas.stroke_path(&border_path, &border_paint);
 
         if self.active {
-            Self::draw_single_handle(canvas, self.pos, scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(size.x / 2.0, 0.0), scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(size.x, 0.0), scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(0.0, size.y / 2.0), scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(0.0, size.y), scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(size.x / 2.0, size.y), scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(size.x, size.y), scale);
-            Self::draw_single_handle(canvas, self.pos + Vec2D::new(size.x, size.y / 2.0), scale);
+            handles::draw_handles(canvas, self.pos, size, scale);
         }
 
         canvas.restore();
         Ok(())
     }
-}
 
-#[derive(Clone, Copy)]
-enum CropHandle {
-    TopLeftCorner,
-    TopEdge,
-    TopRightCorner,
-    RightEdge,
-    BottomRightCorner,
-    BottomEdge,
-    BottomLeftCorner,
-    LeftEdge,
+    fn draw_svg(&self, _svg: &mut SvgDocument) -> Result<()> {
+        // the crop is applied as the viewport of the document
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        Some(self.get_rectangle())
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        let pos = math::rect_map_point(self.pos, from, to);
+        self.size = math::rect_map_point(self.pos + self.size, from, to) - pos;
+        self.pos = pos;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        // the crop is applied to the background when saving, it is not an annotation
+        None
+    }
 }
 
 enum CropToolAction {
@@ -167,7 +106,7 @@ enum CropToolAction {
 }
 
 struct DragHandleState {
-    handle: CropHandle,
+    handle: Handle,
     top_left_start: Vec2D,
     bottom_right_start: Vec2D,
 }
@@ -183,20 +122,14 @@ impl CropTool {
             None => None,
         }
     }
-}
 
-impl CropHandle {
-    fn all() -> [CropHandle; 8] {
-        [
-            CropHandle::TopLeftCorner,
-            CropHandle::TopEdge,
-            CropHandle::TopRightCorner,
-            CropHandle::RightEdge,
-            CropHandle::BottomRightCorner,
-            CropHandle::BottomEdge,
-            CropHandle::BottomLeftCorner,
-            CropHandle::LeftEdge,
-        ]
+    /// Restore the crop rectangle (pos, size) of a project file, not being edited.
+    pub fn restore(&mut self, (pos, size): (Vec2D, Vec2D)) {
+        self.crop = Some(Crop {
+            pos,
+            size,
+            active: false,
+        });
     }
 }
 
@@ -232,38 +165,9 @@ impl CropTool {
         state: &DragHandleState,
         direction: Vec2D,
     ) {
-        let mut tl = state.top_left_start;
-        let mut br = state.bottom_right_start;
-
-        // apply transformation
-        match state.handle {
-            CropHandle::TopLeftCorner => {
-                tl += direction;
-            }
-            CropHandle::TopEdge => {
-                tl += Vec2D::new(0.0, direction.y);
-            }
-            CropHandle::TopRightCorner => {
-                tl += Vec2D::new(0.0, direction.y);
-                br += Vec2D::new(direction.x, 0.0);
-            }
-            CropHandle::RightEdge => {
-                br += Vec2D::new(direction.x, 0.0);
-            }
-            CropHandle::BottomRightCorner => {
-                br += direction;
-            }
-            CropHandle::BottomEdge => {
-                br += Vec2D::new(0.0, direction.y);
-            }
-            CropHandle::BottomLeftCorner => {
-                tl += Vec2D::new(direction.x, 0.0);
-                br += Vec2D::new(0.0, direction.y);
-            }
-            CropHandle::LeftEdge => {
-                tl += Vec2D::new(direction.x, 0.0);
-            }
-        }
+        let (tl, br) = state
+            .handle
+            .drag(state.top_left_start, state.bottom_right_start, direction);
 
         // convert back and save
         crop.pos = tl;
@@ -295,7 +199,8 @@ impl CropTool {
                 if !c.active {
                     activate = true;
                 }
-                if let Some(handle) = c.test_handle_hit(pos, CropTool::HANDLE_MARGIN_IN_2) {
+                if let Some(handle) = Handle::hit(c.pos, c.size, pos, CropTool::HANDLE_MARGIN_IN_2)
+                {
                     // Crop exists and we are near a handle, drag it
                     self.action = Some(CropToolAction::DragHandle(DragHandleState {
                         handle,
@@ -307,7 +212,7 @@ impl CropTool {
                     self.action = Some(CropToolAction::Move(MoveState { start: c.pos }));
                 } else if self.test_inside_crop(pos, CropTool::HANDLE_MARGIN_OUT) {
                     // Crop exists and we are near the edge, drag from the closest handle
-                    let (handle, _) = c.get_closest_handle(pos);
+                    let (handle, _) = Handle::closest(c.pos, c.size, pos);
                     self.action = Some(CropToolAction::DragHandle(DragHandleState {
                         handle,
                         top_left_start: c.pos,
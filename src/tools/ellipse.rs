@@ -1,28 +1,48 @@
 use anyhow::Result;
 use femtovg::{FontId, Path};
 use relm4::{Sender, gtk::gdk::Key};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::Vec2D,
+    math::{self, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
     style::Style,
+    svg::{Element, SvgDocument},
 };
 
 use super::{
-    Drawable, DrawableClone, Tool, ToolUpdateResult, Tools,
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools,
     drag_box::{DragBox, draw_center_marker},
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Ellipse {
+    #[serde(skip)]
     origin: Vec2D,
     middle: Vec2D,
     radii: Option<Vec2D>,
     style: Style,
+    #[serde(skip)]
     centered: bool,
+    #[serde(skip)]
     finishing: bool,
 }
 
+impl Ellipse {
+    /// An ellipse inscribed in the rectangle at `top_left` with `size`.
+    pub fn new(top_left: Vec2D, size: Vec2D, style: Style) -> Self {
+        Self {
+            origin: top_left,
+            middle: top_left + size * 0.5,
+            radii: Some(size * 0.5),
+            style,
+            centered: false,
+            finishing: true,
+        }
+    }
+}
+
 impl Drawable for Ellipse {
     fn draw(
         &self,
@@ -46,12 +66,81 @@ impl Drawable for Ellipse {
         if self.style.fill {
             canvas.fill_path(&path, &self.style.into());
         } else {
-            canvas.stroke_path(&path, &self.style.into());
+            canvas.stroke_path(&path, &self.style.dashed_paint());
         }
         canvas.restore();
 
         Ok(())
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        if let Some(radii) = self.radii {
+            svg.push(
+                Element::new("ellipse")
+                    .attr("cx", self.middle.x)
+                    .attr("cy", self.middle.y)
+                    .attr("rx", radii.x.abs())
+                    .attr("ry", radii.y.abs())
+                    .paint(&self.style)
+                    .dashes(&self.style),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        self.radii.map(|radii| {
+            let radii = Vec2D::new(radii.x.abs(), radii.y.abs());
+            (self.middle - radii, radii * 2.0)
+        })
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let Some(radii) = self.radii else {
+            return false;
+        };
+        let radii = Vec2D::new(radii.x.abs(), radii.y.abs());
+        if radii.x < f32::EPSILON || radii.y < f32::EPSILON {
+            return math::distance_to_segment(pos, self.middle - radii, self.middle + radii)
+                <= tolerance;
+        }
+
+        // distance from the center in units of the radii, 1.0 is on the outline
+        let offset = pos - self.middle;
+        let distance = Vec2D::new(offset.x / radii.x, offset.y / radii.y).norm();
+        let min_radius = radii.x.min(radii.y);
+        if self.style.fill {
+            (distance - 1.0) * min_radius <= tolerance
+        } else {
+            let margin = tolerance
+                + self
+                    .style
+                    .size
+                    .to_line_width(self.style.annotation_size_factor)
+                    / 2.0;
+            (distance - 1.0).abs() * min_radius <= margin
+        }
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        self.middle = math::rect_map_point(self.middle, from, to);
+        self.origin = math::rect_map_point(self.origin, from, to);
+        self.radii = self
+            .radii
+            .map(|radii| math::rect_map_vector(radii, from, to));
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Ellipse(*self))
+    }
 }
 
 impl Ellipse {
@@ -0,0 +1,224 @@
+use anyhow::Result;
+use femtovg::{FontId, Paint, Path};
+use relm4::{Sender, gtk::gdk::ModifierType};
+
+use crate::{
+    math::Vec2D,
+    sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    style::Style,
+    svg::SvgDocument,
+};
+
+use super::{Drawable, SavedDrawable, SharedDrawables, Tool, ToolUpdateResult, Tools};
+
+/// The outline of the eraser at the pointer.
+#[derive(Clone, Debug)]
+pub struct EraserCursor {
+    pos: Vec2D,
+    radius: f32,
+}
+
+impl Drawable for EraserCursor {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        // keep the outline equally thin at every zoom level
+        let unit = 1.0 / canvas.transform().average_scale().max(f32::EPSILON);
+
+        canvas.save();
+        let mut path = Path::new();
+        path.circle(self.pos.x, self.pos.y, self.radius);
+        canvas.stroke_path(
+            &path,
+            &Paint::color(femtovg::Color::black()).with_line_width(3.0 * unit),
+        );
+        canvas.stroke_path(
+            &path,
+            &Paint::color(femtovg::Color::white()).with_line_width(unit),
+        );
+        canvas.restore();
+        Ok(())
+    }
+
+    fn draw_svg(&self, _svg: &mut SvgDocument) -> Result<()> {
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        None
+    }
+
+    fn transform(&mut self, _from: (Vec2D, Vec2D), _to: (Vec2D, Vec2D)) {}
+
+    fn is_preview(&self) -> bool {
+        true
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        None
+    }
+}
+
+/// Erases the parts of brush strokes and freehand highlights it passes over, or whole strokes
+/// when CTRL is held at the start.
+pub struct EraserTool {
+    drawables: SharedDrawables,
+    cursor: Option<EraserCursor>,
+    /// Where the current drag started, its updates are relative to it.
+    drag_start: Option<Vec2D>,
+    last_pos: Vec2D,
+    whole_strokes: bool,
+    /// The drawables before the current drag erased anything, restored on undo.
+    originals: Option<Vec<Box<dyn Drawable>>>,
+    style: Style,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl EraserTool {
+    pub fn new(drawables: SharedDrawables) -> Self {
+        Self {
+            drawables,
+            cursor: None,
+            drag_start: None,
+            last_pos: Vec2D::zero(),
+            whole_strokes: false,
+            originals: None,
+            style: Style::default(),
+            input_enabled: false,
+            sender: None,
+        }
+    }
+
+    fn radius(&self) -> f32 {
+        self.style
+            .size
+            .to_highlight_width(self.style.annotation_size_factor)
+            / 2.0
+    }
+
+    fn move_cursor(&mut self, pos: Vec2D) {
+        self.cursor = Some(EraserCursor {
+            pos,
+            radius: self.radius(),
+        });
+    }
+
+    /// Erase along the way from the last position to `pos`.
+    fn erase_to(&mut self, pos: Vec2D) -> ToolUpdateResult {
+        let start = std::mem::replace(&mut self.last_pos, pos);
+        self.move_cursor(pos);
+
+        let radius = self.radius();
+        let mut drawables = self.drawables.borrow_mut();
+        let erased: Vec<_> = drawables
+            .iter()
+            .map(|d| d.erase(start, pos, radius))
+            .collect();
+        if erased.iter().all(Option::is_none) {
+            return ToolUpdateResult::Redraw;
+        }
+
+        self.originals
+            .get_or_insert_with(|| drawables.iter().map(|d| d.clone_box()).collect());
+        let whole_strokes = self.whole_strokes;
+        *drawables = std::mem::take(&mut *drawables)
+            .into_iter()
+            .zip(erased)
+            .flat_map(|(drawable, pieces)| match pieces {
+                Some(_) if whole_strokes => Vec::new(),
+                Some(pieces) => pieces,
+                None => vec![drawable],
+            })
+            .collect();
+        ToolUpdateResult::Redraw
+    }
+
+    /// Finish the current drag, recording what it erased as one undoable step.
+    fn finish(&mut self) -> ToolUpdateResult {
+        self.drag_start = None;
+        match self.originals.take() {
+            Some(originals) => ToolUpdateResult::Replace(originals),
+            None => ToolUpdateResult::Redraw,
+        }
+    }
+}
+
+impl Tool for EraserTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn active(&self) -> bool {
+        self.drag_start.is_some()
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button == MouseButton::Middle {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        match event.type_ {
+            MouseEventType::PointerPos => {
+                self.move_cursor(event.pos);
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::BeginDrag if event.button == MouseButton::Primary => {
+                self.drag_start = Some(event.pos);
+                self.last_pos = event.pos;
+                self.whole_strokes = event.modifier.contains(ModifierType::CONTROL_MASK);
+                self.erase_to(event.pos)
+            }
+            MouseEventType::UpdateDrag => match self.drag_start {
+                Some(start) => self.erase_to(start + event.pos),
+                None => ToolUpdateResult::Unmodified,
+            },
+            MouseEventType::EndDrag => match self.drag_start {
+                Some(start) => {
+                    self.erase_to(start + event.pos);
+                    self.finish()
+                }
+                None => ToolUpdateResult::Unmodified,
+            },
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.cursor = None;
+        self.finish()
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        match &self.cursor {
+            Some(cursor) => {
+                self.move_cursor(cursor.pos);
+                ToolUpdateResult::Redraw
+            }
+            None => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.cursor {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Eraser
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
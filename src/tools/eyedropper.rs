@@ -0,0 +1,325 @@
+use anyhow::Result;
+use femtovg::{FontId, Paint, Path};
+use relm4::{
+    Sender,
+    gtk::{
+        gdk::{Key, ModifierType},
+        gdk_pixbuf::Pixbuf,
+        glib::Bytes,
+    },
+};
+
+use crate::{
+    configuration::APP_CONFIG,
+    femtovg_area,
+    math::Vec2D,
+    sketch_board::{
+        KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput,
+        SketchBoardOutput,
+    },
+    style::Color,
+    svg::SvgDocument,
+    ui::toolbars::ToolbarEvent,
+};
+
+use super::{Drawable, SavedDrawable, Tool, ToolUpdateResult, Tools};
+
+/// Number of pixels the loupe shows on each side of the one under the pointer.
+const LOUPE_RADIUS: i32 = 5;
+/// Screen pixels per enlarged image pixel.
+const LOUPE_CELL: f32 = 10.0;
+/// Distance between the pointer and the loupe on screen.
+const LOUPE_OFFSET: f32 = 24.0;
+/// Height of the strip below the pixels showing the picked color.
+const SWATCH_HEIGHT: f32 = 24.0;
+
+/// The preview next to the pointer: the enlarged pixels around it and the color a click picks.
+#[derive(Clone, Debug)]
+pub struct Loupe {
+    /// Top left corner of the pixel under the pointer.
+    pixel: Vec2D,
+    /// The pixels around it row by row, None outside of the image.
+    cells: Vec<Option<Color>>,
+    color: Color,
+    sample_size: u32,
+}
+
+impl Loupe {
+    /// Sample the image with the pixel `bytes` at `pos`, or None if it is outside of the image.
+    fn sample(image: &Pixbuf, bytes: &[u8], pos: Vec2D, sample_size: u32) -> Option<Self> {
+        let (x, y) = (pos.x.floor() as i32, pos.y.floor() as i32);
+        let stride = image.rowstride() as usize;
+        let channels = image.n_channels() as usize;
+        let pixel_at = |px: i32, py: i32| {
+            if px < 0 || py < 0 || px >= image.width() || py >= image.height() {
+                return None;
+            }
+            let start = py as usize * stride + px as usize * channels;
+            let p = &bytes[start..start + channels];
+            Some(Color::new(
+                p[0],
+                p[1],
+                p[2],
+                if channels == 4 { p[3] } else { 255 },
+            ))
+        };
+        pixel_at(x, y)?;
+
+        let cells = (-LOUPE_RADIUS..=LOUPE_RADIUS)
+            .flat_map(|dy| (-LOUPE_RADIUS..=LOUPE_RADIUS).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| pixel_at(x + dx, y + dy))
+            .collect();
+
+        // average the colors of the square, clipped to the image
+        let half = (sample_size / 2) as i32;
+        let samples: Vec<Color> = (-half..=half)
+            .flat_map(|dy| (-half..=half).map(move |dx| (dx, dy)))
+            .filter_map(|(dx, dy)| pixel_at(x + dx, y + dy))
+            .collect();
+        let average = |channel: fn(&Color) -> u8| {
+            let sum: u32 = samples.iter().map(|c| channel(c) as u32).sum();
+            (sum as f32 / samples.len() as f32).round() as u8
+        };
+        let color = Color::new(average(|c| c.r), average(|c| c.g), average(|c| c.b), 255);
+
+        Some(Self {
+            pixel: Vec2D::new(x as f32, y as f32),
+            cells,
+            color,
+            sample_size,
+        })
+    }
+
+    fn hex(&self) -> String {
+        format!(
+            "#{:02X}{:02X}{:02X}",
+            self.color.r, self.color.g, self.color.b
+        )
+    }
+}
+
+impl Drawable for Loupe {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        // keep the loupe the same size on screen at every zoom level
+        let unit = 1.0 / canvas.transform().average_scale().max(f32::EPSILON);
+        let cell = LOUPE_CELL * unit;
+        let columns = 2 * LOUPE_RADIUS + 1;
+        let grid = columns as f32 * cell;
+        let swatch = SWATCH_HEIGHT * unit;
+        let origin = self.pixel + Vec2D::new(0.5 + LOUPE_OFFSET * unit, 0.5 + LOUPE_OFFSET * unit);
+
+        canvas.save();
+
+        let mut frame = Path::new();
+        frame.rect(origin.x, origin.y, grid, grid + swatch);
+        canvas.fill_path(&frame, &Paint::color(femtovg::Color::rgb(64, 64, 64)));
+
+        // unfiltered and without anti-aliasing, so the enlarged pixels stay sharp
+        for (i, color) in self.cells.iter().enumerate() {
+            if let Some(color) = color {
+                let (column, row) = (i as i32 % columns, i as i32 / columns);
+                let mut path = Path::new();
+                path.rect(
+                    origin.x + column as f32 * cell,
+                    origin.y + row as f32 * cell,
+                    cell,
+                    cell,
+                );
+                canvas.fill_path(&path, &Paint::color((*color).into()).with_anti_alias(false));
+            }
+        }
+
+        // outline the averaged pixels
+        let half = (self.sample_size / 2) as i32;
+        let start = (LOUPE_RADIUS - half) as f32 * cell;
+        let size = self.sample_size as f32 * cell;
+        let mut path = Path::new();
+        path.rect(origin.x + start, origin.y + start, size, size);
+        canvas.stroke_path(
+            &path,
+            &Paint::color(femtovg::Color::black()).with_line_width(3.0 * unit),
+        );
+        canvas.stroke_path(
+            &path,
+            &Paint::color(femtovg::Color::white()).with_line_width(unit),
+        );
+
+        let mut path = Path::new();
+        path.rect(origin.x, origin.y + grid, grid, swatch);
+        canvas.fill_path(&path, &Paint::color(self.color.into()));
+
+        let mut text_paint = Paint::color(self.color.contrast().into());
+        if femtovg_area::font_stack().is_empty() {
+            text_paint.set_font(&[font]);
+        } else {
+            text_paint.set_font(femtovg_area::font_stack());
+        }
+        text_paint.set_font_size(swatch * 0.55);
+        text_paint.set_text_align(femtovg::Align::Center);
+        text_paint.set_text_baseline(femtovg::Baseline::Middle);
+        canvas.fill_text(
+            origin.x + grid / 2.0,
+            origin.y + grid + swatch / 2.0,
+            self.hex(),
+            &text_paint,
+        )?;
+
+        canvas.stroke_path(
+            &frame,
+            &Paint::color(femtovg::Color::black()).with_line_width(unit),
+        );
+
+        canvas.restore();
+        Ok(())
+    }
+
+    fn draw_svg(&self, _svg: &mut SvgDocument) -> Result<()> {
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        None
+    }
+
+    fn transform(&mut self, _from: (Vec2D, Vec2D), _to: (Vec2D, Vec2D)) {}
+
+    fn is_preview(&self) -> bool {
+        true
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        None
+    }
+}
+
+/// Picks the annotation color from the original image.
+pub struct EyedropperTool {
+    background: Pixbuf,
+    /// The pixels of the background, read once as reading them copies the whole image.
+    pixels: Bytes,
+    loupe: Option<Loupe>,
+    /// Where the current drag started, its updates are relative to it.
+    drag_start: Option<Vec2D>,
+    sample_size: u32,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl EyedropperTool {
+    pub fn new(background: Pixbuf) -> Self {
+        Self {
+            pixels: background.read_pixel_bytes(),
+            background,
+            loupe: None,
+            drag_start: None,
+            sample_size: APP_CONFIG.read().eyedropper_sample_size(),
+            input_enabled: false,
+            sender: None,
+        }
+    }
+
+    fn update_loupe(&mut self, pos: Vec2D) -> ToolUpdateResult {
+        self.loupe = Loupe::sample(&self.background, &self.pixels, pos, self.sample_size);
+        ToolUpdateResult::Redraw
+    }
+
+    /// Make the color under `pos` the annotation color, in the sketch board and the toolbar.
+    fn pick(&mut self, pos: Vec2D) -> ToolUpdateResult {
+        self.update_loupe(pos);
+        if let (Some(loupe), Some(sender)) = (&self.loupe, &self.sender) {
+            sender
+                .send(SketchBoardInput::ToolbarEvent(ToolbarEvent::ColorSelected(
+                    loupe.color,
+                )))
+                .ok();
+            sender
+                .send(SketchBoardInput::Output(SketchBoardOutput::SetColor(
+                    loupe.color,
+                )))
+                .ok();
+        }
+        ToolUpdateResult::Redraw
+    }
+
+    fn set_sample_size(&mut self, sample_size: u32) -> ToolUpdateResult {
+        self.sample_size = sample_size;
+        match &self.loupe {
+            Some(loupe) => self.update_loupe(loupe.pixel),
+            None => ToolUpdateResult::Redraw,
+        }
+    }
+}
+
+impl Tool for EyedropperTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button == MouseButton::Middle {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        match event.type_ {
+            MouseEventType::PointerPos => self.update_loupe(event.pos),
+            MouseEventType::Click if event.button == MouseButton::Primary => self.pick(event.pos),
+            MouseEventType::BeginDrag if event.button == MouseButton::Primary => {
+                self.drag_start = Some(event.pos);
+                ToolUpdateResult::Unmodified
+            }
+            MouseEventType::UpdateDrag => match self.drag_start {
+                Some(start) => self.update_loupe(start + event.pos),
+                None => ToolUpdateResult::Unmodified,
+            },
+            MouseEventType::EndDrag => match self.drag_start.take() {
+                // a click without moving has already picked the color
+                Some(start) if event.pos != Vec2D::zero() => self.pick(start + event.pos),
+                _ => ToolUpdateResult::Unmodified,
+            },
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.modifier.contains(ModifierType::CONTROL_MASK) {
+            return ToolUpdateResult::Unmodified;
+        }
+        match event.key {
+            Key::plus | Key::equal | Key::KP_Add => {
+                self.set_sample_size((self.sample_size + 2).min(5))
+            }
+            Key::minus | Key::KP_Subtract => self.set_sample_size(self.sample_size.max(3) - 2),
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.loupe = None;
+        self.drag_start = None;
+        ToolUpdateResult::Redraw
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.loupe {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Eyedropper
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
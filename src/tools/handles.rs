@@ -0,0 +1,149 @@
+use std::f32::consts::PI;
+
+use femtovg::{Color, Paint, Path};
+
+use crate::math::Vec2D;
+
+pub const HANDLE_RADIUS: f32 = 5.0;
+pub const HANDLE_BORDER: f32 = 2.0;
+
+/// The eight drag handles placed on the corners and edges of a rectangle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handle {
+    TopLeftCorner,
+    TopEdge,
+    TopRightCorner,
+    RightEdge,
+    BottomRightCorner,
+    BottomEdge,
+    BottomLeftCorner,
+    LeftEdge,
+}
+
+impl Handle {
+    pub fn all() -> [Handle; 8] {
+        [
+            Handle::TopLeftCorner,
+            Handle::TopEdge,
+            Handle::TopRightCorner,
+            Handle::RightEdge,
+            Handle::BottomRightCorner,
+            Handle::BottomEdge,
+            Handle::BottomLeftCorner,
+            Handle::LeftEdge,
+        ]
+    }
+
+    pub fn position(self, pos: Vec2D, size: Vec2D) -> Vec2D {
+        match self {
+            Handle::TopLeftCorner => pos,
+            Handle::TopEdge => pos + Vec2D::new(size.x / 2.0, 0.0),
+            Handle::TopRightCorner => pos + Vec2D::new(size.x, 0.0),
+            Handle::RightEdge => pos + Vec2D::new(size.x, size.y / 2.0),
+            Handle::BottomRightCorner => pos + Vec2D::new(size.x, size.y),
+            Handle::BottomEdge => pos + Vec2D::new(size.x / 2.0, size.y),
+            Handle::BottomLeftCorner => pos + Vec2D::new(0.0, size.y),
+            Handle::LeftEdge => pos + Vec2D::new(0.0, size.y / 2.0),
+        }
+    }
+
+    /// Find the handle of the rectangle closest to `mouse_pos` together with its squared distance.
+    pub fn closest(pos: Vec2D, size: Vec2D, mouse_pos: Vec2D) -> (Handle, f32) {
+        let mut min_distance_squared = f32::MAX;
+        let mut closest_handle = Handle::TopLeftCorner;
+        for h in Handle::all() {
+            let distance_squared = (h.position(pos, size) - mouse_pos).norm2();
+            if distance_squared < min_distance_squared {
+                min_distance_squared = distance_squared;
+                closest_handle = h;
+            }
+        }
+        (closest_handle, min_distance_squared)
+    }
+
+    /// Find the handle under `mouse_pos`, allowing an additional squared margin.
+    pub fn hit(pos: Vec2D, size: Vec2D, mouse_pos: Vec2D, margin2: f32) -> Option<Handle> {
+        const HANDLE_SIZE: f32 = HANDLE_RADIUS + HANDLE_BORDER;
+        const HANDLE_SIZE2: f32 = HANDLE_SIZE * HANDLE_SIZE;
+        let allowed_distance2 = HANDLE_SIZE2 + margin2;
+
+        let (handle, distance2) = Handle::closest(pos, size, mouse_pos);
+        if distance2 < allowed_distance2 {
+            Some(handle)
+        } else {
+            None
+        }
+    }
+
+    /// Move the corners and edges attached to this handle by `direction` and
+    /// return the resulting top left and bottom right corners.
+    pub fn drag(self, top_left: Vec2D, bottom_right: Vec2D, direction: Vec2D) -> (Vec2D, Vec2D) {
+        let mut tl = top_left;
+        let mut br = bottom_right;
+
+        match self {
+            Handle::TopLeftCorner => {
+                tl += direction;
+            }
+            Handle::TopEdge => {
+                tl += Vec2D::new(0.0, direction.y);
+            }
+            Handle::TopRightCorner => {
+                tl += Vec2D::new(0.0, direction.y);
+                br += Vec2D::new(direction.x, 0.0);
+            }
+            Handle::RightEdge => {
+                br += Vec2D::new(direction.x, 0.0);
+            }
+            Handle::BottomRightCorner => {
+                br += direction;
+            }
+            Handle::BottomEdge => {
+                br += Vec2D::new(0.0, direction.y);
+            }
+            Handle::BottomLeftCorner => {
+                tl += Vec2D::new(direction.x, 0.0);
+                br += Vec2D::new(0.0, direction.y);
+            }
+            Handle::LeftEdge => {
+                tl += Vec2D::new(direction.x, 0.0);
+            }
+        }
+
+        (tl, br)
+    }
+}
+
+pub fn draw_handle(
+    canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    center: Vec2D,
+    scale: f32,
+) {
+    let mut path = Path::new();
+    path.arc(
+        center.x,
+        center.y,
+        HANDLE_RADIUS / scale,
+        0.0,
+        2.0 * PI,
+        femtovg::Solidity::Solid,
+    );
+
+    let border_paint =
+        Paint::color(Color::rgbf(0.9, 0.9, 0.9)).with_line_width(HANDLE_BORDER / scale);
+    let fill_paint = Paint::color(Color::rgbaf(0.0, 0.0, 0.0, 0.4));
+
+    canvas.fill_path(&path, &fill_paint);
+    canvas.stroke_path(&path, &border_paint);
+}
+
+pub fn draw_handles(
+    canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    pos: Vec2D,
+    size: Vec2D,
+    scale: f32,
+) {
+    for h in Handle::all() {
+        draw_handle(canvas, h.position(pos, size), scale);
+    }
+}
@@ -7,20 +7,21 @@ use relm4::{
     Sender,
     gtk::gdk::{Key, ModifierType},
 };
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
     math::{self, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
     style::Style,
+    svg::{Element, SvgDocument},
     tools::DrawableClone,
 };
 
 use satty_cli::command_line;
 
 use super::{
-    Drawable, Tool, ToolUpdateResult, Tools,
+    Drawable, SavedDrawable, Tool, ToolUpdateResult, Tools,
     drag_box::{DragBox, draw_center_marker},
 };
 
@@ -42,29 +43,61 @@ impl From<command_line::Highlighters> for Highlighters {
     }
 }
 
-#[derive(Clone, Debug)]
-struct BlockHighlight {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BlockHighlight {
+    #[serde(skip)]
     origin: Vec2D,
     top_left: Vec2D,
     size: Option<Vec2D>,
+    #[serde(skip)]
     centered: bool,
+    #[serde(skip)]
     finishing: bool,
 }
 
-#[derive(Clone, Debug)]
-struct FreehandHighlight {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FreehandHighlight {
     points: Vec<Vec2D>,
+    #[serde(skip)]
     shift_pressed: bool,
 }
 
-#[derive(Clone, Debug)]
-struct Highlighter<T> {
+impl FreehandHighlight {
+    /// A finished highlight through the given points in image coordinates.
+    fn from_points(points: Vec<Vec2D>) -> Self {
+        let first = points.first().copied().unwrap_or_default();
+        Self {
+            points: points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| if i == 0 { *p } else { *p - first })
+                .collect(),
+            shift_pressed: false,
+        }
+    }
+
+    /// The points of the highlight in image coordinates.
+    fn absolute_points(&self) -> Vec<Vec2D> {
+        let Some(&first) = self.points.first() else {
+            return Vec::new();
+        };
+        std::iter::once(first)
+            .chain(self.points.iter().skip(1).map(|p| first + *p))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Highlighter<T> {
     data: T,
     style: Style,
 }
 
 trait Highlight {
     fn highlight(&self, canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>) -> Result<()>;
+    fn highlight_svg(&self, svg: &mut SvgDocument);
 }
 
 impl Highlight for Highlighter<FreehandHighlight> {
@@ -101,6 +134,26 @@ impl Highlight for Highlighter<FreehandHighlight> {
         canvas.restore();
         Ok(())
     }
+
+    fn highlight_svg(&self, svg: &mut SvgDocument) {
+        svg.push(
+            Element::path(&[self.data.absolute_points()], false)
+                .attr("fill", "none")
+                .stroke_color(
+                    self.style
+                        .color
+                        .with_alpha((255.0 * HIGHLIGHT_OPACITY) as u8),
+                )
+                .attr(
+                    "stroke-width",
+                    self.style
+                        .size
+                        .to_highlight_width(self.style.annotation_size_factor),
+                )
+                .attr("stroke-linejoin", "round")
+                .attr("stroke-linecap", "square"),
+        );
+    }
 }
 
 impl Highlight for Highlighter<BlockHighlight> {
@@ -135,6 +188,18 @@ impl Highlight for Highlighter<BlockHighlight> {
         canvas.fill_path(&shadow_path, &shadow_paint);
         Ok(())
     }
+
+    fn highlight_svg(&self, svg: &mut SvgDocument) {
+        if let Some(size) = self.data.size {
+            svg.push(
+                Element::rounded_rect(self.data.top_left, size).fill(
+                    self.style
+                        .color
+                        .with_alpha((255.0 * HIGHLIGHT_OPACITY) as u8),
+                ),
+            );
+        }
+    }
 }
 
 impl BlockHighlight {
@@ -146,8 +211,9 @@ impl BlockHighlight {
     }
 }
 
-#[derive(Clone, Debug)]
-enum HighlightKind {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum HighlightKind {
     Block(Highlighter<BlockHighlight>),
     Freehand(Highlighter<FreehandHighlight>),
 }
@@ -160,6 +226,21 @@ pub struct HighlightTool {
     sender: Option<Sender<SketchBoardInput>>,
 }
 
+impl HighlightKind {
+    pub fn block(top_left: Vec2D, size: Vec2D, style: Style) -> Self {
+        Self::Block(Highlighter {
+            data: BlockHighlight {
+                origin: top_left,
+                top_left,
+                size: Some(size),
+                centered: false,
+                finishing: true,
+            },
+            style,
+        })
+    }
+}
+
 impl Drawable for HighlightKind {
     fn draw(
         &self,
@@ -172,6 +253,116 @@ impl Drawable for HighlightKind {
             HighlightKind::Freehand(highlighter) => highlighter.highlight(canvas),
         }
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        match self {
+            HighlightKind::Block(highlighter) => highlighter.highlight_svg(svg),
+            HighlightKind::Freehand(highlighter) => highlighter.highlight_svg(svg),
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        match self {
+            HighlightKind::Block(highlighter) => highlighter
+                .data
+                .size
+                .map(|size| math::rect_ensure_positive_size(highlighter.data.top_left, size)),
+            HighlightKind::Freehand(highlighter) => {
+                math::rect_from_points(highlighter.data.absolute_points())
+            }
+        }
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        match self {
+            HighlightKind::Block(_) => self
+                .bounds()
+                .is_some_and(|rect| math::rect_contains(rect, pos, tolerance)),
+            HighlightKind::Freehand(highlighter) => {
+                let margin = tolerance
+                    + highlighter
+                        .style
+                        .size
+                        .to_highlight_width(highlighter.style.annotation_size_factor)
+                        / 2.0;
+                math::distance_to_polyline(pos, &highlighter.data.absolute_points()) <= margin
+            }
+        }
+    }
+
+    fn erase(&self, start: Vec2D, end: Vec2D, radius: f32) -> Option<Vec<Box<dyn Drawable>>> {
+        // block highlights are rectangles rather than strokes and are left alone
+        let HighlightKind::Freehand(highlighter) = self else {
+            return None;
+        };
+        let margin = radius
+            + highlighter
+                .style
+                .size
+                .to_highlight_width(highlighter.style.annotation_size_factor)
+                / 2.0;
+        let pieces =
+            math::polyline_erase(&highlighter.data.absolute_points(), &[], start, end, margin)?;
+        Some(
+            pieces
+                .into_iter()
+                .map(|(points, _)| {
+                    HighlightKind::Freehand(Highlighter {
+                        data: FreehandHighlight::from_points(points),
+                        style: highlighter.style,
+                    })
+                    .clone_box()
+                })
+                .collect(),
+        )
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        match self {
+            HighlightKind::Block(highlighter) => {
+                let data = &mut highlighter.data;
+                if let Some(size) = data.size {
+                    let top_left = math::rect_map_point(data.top_left, from, to);
+                    data.size =
+                        Some(math::rect_map_point(data.top_left + size, from, to) - top_left);
+                    data.top_left = top_left;
+                }
+                data.origin = math::rect_map_point(data.origin, from, to);
+            }
+            HighlightKind::Freehand(highlighter) => {
+                let points = &mut highlighter.data.points;
+                let Some(&first) = points.first() else {
+                    return;
+                };
+                let new_first = math::rect_map_point(first, from, to);
+
+                // the first point is absolute, all others are relative to it
+                for p in points.iter_mut().skip(1) {
+                    *p = math::rect_map_point(first + *p, from, to) - new_first;
+                }
+                points[0] = new_first;
+            }
+        }
+    }
+
+    fn style(&self) -> Option<Style> {
+        match self {
+            HighlightKind::Block(highlighter) => Some(highlighter.style),
+            HighlightKind::Freehand(highlighter) => Some(highlighter.style),
+        }
+    }
+
+    fn set_style(&mut self, style: Style) {
+        match self {
+            HighlightKind::Block(highlighter) => highlighter.style = style,
+            HighlightKind::Freehand(highlighter) => highlighter.style = style,
+        }
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Highlight(self.clone()))
+    }
 }
 
 impl Tool for HighlightTool {
@@ -4,30 +4,230 @@ use relm4::{
     Sender,
     gtk::gdk::{Key, ModifierType},
 };
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::Vec2D,
+    math::{self, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
     style::Style,
+    svg::{Element, SvgDocument},
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools};
+
+/// The distance from the first vertex within which a click closes a polyline.
+const CLOSE_DISTANCE: f32 = 10.0;
 
 #[derive(Default)]
 pub struct LineTool {
     line: Option<Line>,
+    polyline: Option<Polyline>,
+    /// Set by a double-click finishing a polyline, so that its drag events are ignored.
+    finished: bool,
     style: Style,
     input_enabled: bool,
     sender: Option<Sender<SketchBoardInput>>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Line {
     start: Vec2D,
     direction: Option<Vec2D>,
     style: Style,
 }
 
+impl Line {
+    pub fn new(start: Vec2D, end: Vec2D, style: Style) -> Self {
+        Self {
+            start,
+            direction: Some(end - start),
+            style,
+        }
+    }
+}
+
+/// Connected line segments, optionally closed into a polygon which is filled with the style.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Polyline {
+    points: Vec<Vec2D>,
+    closed: bool,
+    style: Style,
+    /// The end of the segment following the pointer while adding vertices.
+    #[serde(skip)]
+    preview: Option<Vec2D>,
+}
+
+impl Polyline {
+    pub fn new(points: Vec<Vec2D>, closed: bool, style: Style) -> Self {
+        Self {
+            points,
+            closed,
+            style,
+            preview: None,
+        }
+    }
+
+    /// The position of the next vertex, snapped to 15° steps from the last one if requested.
+    fn next_vertex(&self, pos: Vec2D, snap: bool) -> Vec2D {
+        match self.points.last() {
+            Some(&last) if snap => last + (pos - last).snapped_vector_15deg(),
+            _ => pos,
+        }
+    }
+
+    fn outline(&self) -> Vec<Vec2D> {
+        let mut points = self.points.clone();
+        points.extend(self.preview);
+        if self.closed
+            && let Some(&first) = self.points.first()
+        {
+            points.push(first);
+        }
+        points
+    }
+}
+
+impl Drawable for Polyline {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let points = self.outline();
+        let Some((first, rest)) = points.split_first() else {
+            return Ok(()); // exit early if no points
+        };
+
+        canvas.save();
+
+        let mut path = Path::new();
+        path.move_to(first.x, first.y);
+        for p in rest {
+            path.line_to(p.x, p.y);
+        }
+
+        if self.closed {
+            path.close();
+            if self.style.fill {
+                canvas.fill_path(&path, &self.style.into());
+            } else {
+                canvas.stroke_path(&path, &self.style.dashed_paint());
+            }
+        } else {
+            canvas.stroke_path(&path, &self.style.dashed_paint());
+        }
+
+        canvas.restore();
+
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        if self.points.len() > 1 {
+            let element = Element::path(std::slice::from_ref(&self.points), self.closed);
+            svg.push(if self.closed {
+                element.paint(&self.style).dashes(&self.style)
+            } else {
+                element
+                    .attr("fill", "none")
+                    .stroke(&self.style)
+                    .dashes(&self.style)
+            });
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        math::rect_from_points(self.outline())
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        if self.closed && self.style.fill && math::polygon_contains(&self.points, pos) {
+            return true;
+        }
+        let margin = tolerance
+            + self
+                .style
+                .size
+                .to_line_width(self.style.annotation_size_factor)
+                / 2.0;
+        math::distance_to_polyline(pos, &self.outline()) <= margin
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        for p in &mut self.points {
+            *p = math::rect_map_point(*p, from, to);
+        }
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Polyline(self.clone()))
+    }
+}
+
+impl LineTool {
+    /// Commit the polyline being drawn, it needs at least one segment to be kept.
+    fn finish_polyline(&mut self, closed: bool) -> ToolUpdateResult {
+        let Some(mut polyline) = self.polyline.take() else {
+            return ToolUpdateResult::Unmodified;
+        };
+        polyline.preview = None;
+        polyline
+            .points
+            .dedup_by(|a, b| a.distance_to(b) < f32::EPSILON);
+        // a polygon needs a third corner to enclose anything
+        polyline.closed = closed && polyline.points.len() > 2;
+        if polyline.points.len() > 1 {
+            ToolUpdateResult::Commit(polyline.clone_box())
+        } else {
+            ToolUpdateResult::Redraw
+        }
+    }
+
+    fn handle_polyline_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        let Some(polyline) = &mut self.polyline else {
+            return ToolUpdateResult::Unmodified;
+        };
+        let snap = event.modifier.intersects(ModifierType::SHIFT_MASK);
+        match event.type_ {
+            MouseEventType::PointerPos => {
+                polyline.preview = Some(polyline.next_vertex(event.pos, snap));
+                ToolUpdateResult::Redraw
+            }
+            _ if event.button != MouseButton::Primary => ToolUpdateResult::Unmodified,
+            MouseEventType::Click if event.n_pressed == 2 => {
+                // the first click of the double-click already added the last vertex
+                self.finished = true;
+                self.finish_polyline(false)
+            }
+            MouseEventType::BeginDrag => {
+                if polyline
+                    .points
+                    .first()
+                    .is_some_and(|first| first.distance_to(&event.pos) <= CLOSE_DISTANCE)
+                {
+                    return self.finish_polyline(true);
+                }
+                let vertex = polyline.next_vertex(event.pos, snap);
+                polyline.points.push(vertex);
+                ToolUpdateResult::Redraw
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+}
+
 impl Drawable for Line {
     fn draw(
         &self,
@@ -46,12 +246,66 @@ impl Drawable for Line {
         path.move_to(self.start.x, self.start.y);
         path.line_to(self.start.x + direction.x, self.start.y + direction.y);
 
-        canvas.stroke_path(&path, &self.style.into());
+        canvas.stroke_path(&path, &self.style.dashed_paint());
 
         canvas.restore();
 
         Ok(())
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        if let Some(direction) = self.direction {
+            let end = self.start + direction;
+            svg.push(
+                Element::new("line")
+                    .attr("x1", self.start.x)
+                    .attr("y1", self.start.y)
+                    .attr("x2", end.x)
+                    .attr("y2", end.y)
+                    .stroke(&self.style)
+                    .dashes(&self.style),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        self.direction
+            .and_then(|d| math::rect_from_points([self.start, self.start + d]))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let Some(direction) = self.direction else {
+            return false;
+        };
+        let margin = tolerance
+            + self
+                .style
+                .size
+                .to_line_width(self.style.annotation_size_factor)
+                / 2.0;
+        math::distance_to_segment(pos, self.start, self.start + direction) <= margin
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        let start = math::rect_map_point(self.start, from, to);
+        self.direction = self
+            .direction
+            .map(|d| math::rect_map_point(self.start + d, from, to) - start);
+        self.start = start;
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Line(*self))
+    }
 }
 
 impl Tool for LineTool {
@@ -64,10 +318,32 @@ impl Tool for LineTool {
     }
 
     fn active(&self) -> bool {
-        self.line.is_some()
+        self.line.is_some() || self.polyline.is_some()
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.line = None;
+        self.finish_polyline(false)
     }
 
     fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if self.polyline.is_some() {
+            return self.handle_polyline_event(event);
+        }
+        if self.finished {
+            // the second press of the double-click which finished a polyline
+            match event.type_ {
+                MouseEventType::BeginDrag | MouseEventType::UpdateDrag => {
+                    return ToolUpdateResult::Unmodified;
+                }
+                MouseEventType::EndDrag => {
+                    self.finished = false;
+                    return ToolUpdateResult::Unmodified;
+                }
+                _ => {}
+            }
+        }
+
         match event.type_ {
             MouseEventType::BeginDrag => {
                 if event.button == MouseButton::Middle {
@@ -90,6 +366,8 @@ impl Tool for LineTool {
 
                 if let Some(a) = &mut self.line {
                     if event.pos == Vec2D::zero() {
+                        // a click without dragging starts a polyline
+                        self.polyline = Some(Polyline::new(vec![a.start], false, self.style));
                         self.line = None;
 
                         ToolUpdateResult::Redraw
@@ -129,9 +407,14 @@ impl Tool for LineTool {
     }
 
     fn handle_key_event(&mut self, event: crate::sketch_board::KeyEventMsg) -> ToolUpdateResult {
-        if event.key == Key::Escape && self.line.is_some() {
+        if event.key == Key::Escape && (self.line.is_some() || self.polyline.is_some()) {
             self.line = None;
+            self.polyline = None;
             ToolUpdateResult::Redraw
+        } else if (event.key == Key::Return || event.key == Key::KP_Enter)
+            && self.polyline.is_some()
+        {
+            self.finish_polyline(false)
         } else {
             ToolUpdateResult::Unmodified
         }
@@ -139,10 +422,18 @@ impl Tool for LineTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        if let Some(polyline) = &mut self.polyline {
+            polyline.style = style;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn get_drawable(&self) -> Option<&dyn Drawable> {
+        if let Some(polyline) = &self.polyline {
+            return Some(polyline);
+        }
         match &self.line {
             Some(d) => Some(d),
             None => None,
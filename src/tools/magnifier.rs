@@ -0,0 +1,550 @@
+use std::cell::RefCell;
+
+use anyhow::{Result, anyhow};
+use femtovg::{
+    FontId, ImageFlags, ImageId, Paint, Path,
+    imgref::{Img, ImgVec},
+    rgb::RGBA8,
+};
+use relm4::{
+    Sender,
+    gtk::{
+        gdk::Key,
+        gdk_pixbuf::{InterpType, Pixbuf},
+    },
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::APP_CONFIG,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    style::Style,
+    svg::{Element, SvgDocument},
+};
+
+use super::{
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools,
+    drag_box::{DragBox, draw_center_marker},
+};
+
+const MIN_ZOOM: f32 = 2.0;
+const MAX_ZOOM: f32 = 8.0;
+/// Distance between the source region and the initially placed inset.
+const INSET_GAP: f32 = 20.0;
+
+/// An enlarged inset of a region of the image, sampled from the original image at native
+/// resolution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Magnifier {
+    #[serde(skip)]
+    origin: Vec2D,
+    top_left: Vec2D,
+    size: Option<Vec2D>,
+    /// Top left corner of the inset.
+    inset: Vec2D,
+    zoom: f32,
+    connector: bool,
+    border: bool,
+    style: Style,
+    #[serde(skip)]
+    centered: bool,
+    #[serde(skip)]
+    editing: bool,
+    #[serde(skip)]
+    background: Option<Pixbuf>,
+    #[serde(skip)]
+    cached_image: RefCell<Option<ImageId>>,
+}
+
+impl Magnifier {
+    pub fn new(top_left: Vec2D, size: Vec2D, inset: Vec2D, zoom: f32, style: Style) -> Self {
+        let config = APP_CONFIG.read();
+        Self {
+            origin: top_left,
+            top_left,
+            size: Some(size),
+            inset,
+            zoom: zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+            connector: config.magnifier_connector(),
+            border: config.magnifier_border(),
+            style,
+            centered: false,
+            editing: false,
+            background: None,
+            cached_image: RefCell::new(None),
+        }
+    }
+
+    /// Attach the image the inset is sampled from.
+    pub fn with_background(mut self, background: Pixbuf) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    fn calculate_shape(&mut self, event: &MouseEventMsg) {
+        let drag_box = DragBox::from_origin_delta(self.origin, event.pos, event.modifier);
+        self.centered = drag_box.centered;
+        self.top_left = drag_box.top_left;
+        self.size = Some(drag_box.size);
+    }
+
+    fn source(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+    }
+
+    fn inset_rect(&self) -> Option<(Vec2D, Vec2D)> {
+        self.source()
+            .map(|(_, size)| (self.inset, size * self.zoom))
+    }
+
+    fn set_zoom(&mut self, zoom: f32) {
+        // keep the center of the inset in place
+        if let Some((pos, size)) = self.inset_rect() {
+            let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+            let center = pos + size * 0.5;
+            self.inset = center - size * (0.5 * zoom / self.zoom);
+            self.zoom = zoom;
+        }
+    }
+
+    /// The part of the source region inside the image, with the area of the inset it is shown in.
+    fn visible_source(&self, image_size: Vec2D) -> Option<((Vec2D, Vec2D), (Vec2D, Vec2D))> {
+        let source = self.source()?;
+        let inset = self.inset_rect()?;
+        let (pos, size) = math::rect_round(math::rect_ensure_in_bounds(
+            source,
+            (Vec2D::zero(), image_size),
+        ));
+        if size.x < 1.0 || size.y < 1.0 {
+            return None;
+        }
+        let inset_pos = math::rect_map_point(pos, source, inset);
+        let inset_size = math::rect_map_vector(size, source, inset);
+        Some(((pos, size), (inset_pos, inset_size)))
+    }
+
+    /// The line connecting the borders of the source region and the inset, or None if they
+    /// overlap.
+    fn connector_line(&self) -> Option<(Vec2D, Vec2D)> {
+        let source = self.source()?;
+        let inset = self.inset_rect()?;
+        if math::rect_intersects(source, inset) {
+            return None;
+        }
+        let source_center = source.0 + source.1 * 0.5;
+        let inset_center = inset.0 + inset.1 * 0.5;
+        Some((
+            edge_point(source, inset_center),
+            edge_point(inset, source_center),
+        ))
+    }
+
+    /// Upload the sampled pixels unfiltered, so the enlarged pixels stay sharp.
+    fn upload_source(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        background: &Pixbuf,
+        (pos, size): (Vec2D, Vec2D),
+    ) -> Result<ImageId> {
+        let image = pixels(background, pos, size);
+        Ok(canvas.create_image(image.as_ref(), ImageFlags::NEAREST)?)
+    }
+}
+
+/// The point where the line from the center of the rectangle (pos, size) towards `target`
+/// leaves it.
+fn edge_point((pos, size): (Vec2D, Vec2D), target: Vec2D) -> Vec2D {
+    let center = pos + size * 0.5;
+    let direction = target - center;
+    let scale_x = if direction.x.abs() > f32::EPSILON {
+        size.x * 0.5 / direction.x.abs()
+    } else {
+        f32::INFINITY
+    };
+    let scale_y = if direction.y.abs() > f32::EPSILON {
+        size.y * 0.5 / direction.y.abs()
+    } else {
+        f32::INFINITY
+    };
+    let scale = scale_x.min(scale_y);
+    if scale.is_finite() {
+        center + direction * scale
+    } else {
+        center
+    }
+}
+
+/// The RGBA pixels of the area (pos, size) of the image, which has to lie inside it.
+pub(super) fn pixels(image: &Pixbuf, pos: Vec2D, size: Vec2D) -> ImgVec<RGBA8> {
+    let (width, height) = (size.x as usize, size.y as usize);
+    let stride = image.rowstride() as usize;
+    let channels = image.n_channels() as usize;
+    // reading the bytes copies them, so only the rows of the area are read
+    let bytes = image
+        .new_subpixbuf(pos.x as i32, pos.y as i32, width as i32, height as i32)
+        .read_pixel_bytes();
+
+    let mut buf = Vec::with_capacity(width * height);
+    for row in 0..height {
+        let start = row * stride;
+        buf.extend(
+            bytes[start..start + width * channels]
+                .chunks_exact(channels)
+                .map(|p| RGBA8::new(p[0], p[1], p[2], if channels == 4 { p[3] } else { 255 })),
+        );
+    }
+    Img::new(buf, width, height)
+}
+
+impl Drawable for Magnifier {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some(source) = self.source() else {
+            return Ok(()); // early exit if none
+        };
+        let paint: Paint = self.style.into();
+
+        canvas.save();
+        if self.editing && self.centered {
+            draw_center_marker(canvas, self.origin);
+        }
+
+        let mut path = Path::new();
+        path.rect(source.0.x, source.0.y, source.1.x, source.1.y);
+        canvas.stroke_path(&path, &paint);
+
+        if !self.editing {
+            if self.connector
+                && let Some((start, end)) = self.connector_line()
+            {
+                let mut path = Path::new();
+                path.move_to(start.x, start.y);
+                path.line_to(end.x, end.y);
+                canvas.stroke_path(&path, &paint);
+            }
+
+            if let Some(background) = &self.background {
+                let image_size = Vec2D::new(background.width() as f32, background.height() as f32);
+                if let Some((area, (pos, size))) = self.visible_source(image_size) {
+                    if self.cached_image.borrow().is_none() {
+                        let image = self.upload_source(canvas, background, area)?;
+                        self.cached_image.borrow_mut().replace(image);
+                    }
+                    // this unwrap is safe because we placed it above
+                    let image = self.cached_image.borrow().unwrap();
+                    let mut path = Path::new();
+                    path.rect(pos.x, pos.y, size.x, size.y);
+                    canvas.fill_path(
+                        &path,
+                        &Paint::image(image, pos.x, pos.y, size.x, size.y, 0f32, 1f32),
+                    );
+                }
+            }
+
+            if self.border
+                && let Some((pos, size)) = self.inset_rect()
+            {
+                let mut path = Path::new();
+                path.rect(pos.x, pos.y, size.x, size.y);
+                canvas.stroke_path(&path, &paint);
+            }
+        }
+        canvas.restore();
+
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        let (Some(source), Some(inset)) = (self.source(), self.inset_rect()) else {
+            return Ok(());
+        };
+        let rect = |(pos, size): (Vec2D, Vec2D)| {
+            Element::new("rect")
+                .attr("x", pos.x)
+                .attr("y", pos.y)
+                .attr("width", size.x)
+                .attr("height", size.y)
+                .attr("fill", "none")
+                .stroke(&self.style)
+        };
+
+        svg.push(rect(source));
+        if self.connector
+            && let Some((start, end)) = self.connector_line()
+        {
+            svg.push(
+                Element::path(&[vec![start, end]], false)
+                    .attr("fill", "none")
+                    .stroke(&self.style),
+            );
+        }
+        // the SVG background holds the same pixels as the original image
+        if let Some(((pos, size), area)) = svg.background_area(source.0, source.1) {
+            let inset_pos = math::rect_map_point(pos, source, inset);
+            let inset_size = math::rect_map_vector(size, source, inset);
+            // enlarged beforehand, viewers would smooth the pixels otherwise
+            let enlarged = area
+                .scale_simple(
+                    (inset_size.x.round() as i32).max(1),
+                    (inset_size.y.round() as i32).max(1),
+                    InterpType::Nearest,
+                )
+                .ok_or(anyhow!("Could not scale the magnified area"))?;
+            svg.image(&enlarged, inset_pos, inset_size)?;
+        }
+        if self.border {
+            svg.push(rect(inset));
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        let (source, inset) = (self.source()?, self.inset_rect()?);
+        math::rect_from_points([source.0, source.0 + source.1, inset.0, inset.0 + inset.1])
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        self.source()
+            .is_some_and(|rect| math::rect_contains(rect, pos, tolerance))
+            || self
+                .inset_rect()
+                .is_some_and(|rect| math::rect_contains(rect, pos, tolerance))
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            self.size = Some(math::rect_map_point(self.top_left + size, from, to) - top_left);
+            self.top_left = top_left;
+        }
+        self.origin = math::rect_map_point(self.origin, from, to);
+        self.inset = math::rect_map_point(self.inset, from, to);
+    }
+
+    fn refresh_capture(&mut self) {
+        // the inset has to be sampled from the new region
+        self.cached_image.replace(None);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn magnification(&self) -> Option<((Vec2D, Vec2D), (Vec2D, Vec2D))> {
+        self.source().zip(self.inset_rect())
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Magnifier(self.clone()))
+    }
+}
+
+pub struct MagnifierTool {
+    magnifier: Option<Magnifier>,
+    background: Pixbuf,
+    /// The source region is chosen and the inset waits to be placed.
+    placing: bool,
+    /// The inset is dragged, starting at the given center.
+    moving: Option<Vec2D>,
+    style: Style,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl MagnifierTool {
+    pub fn new(background: Pixbuf) -> Self {
+        Self {
+            magnifier: None,
+            background,
+            placing: false,
+            moving: None,
+            style: Style::default(),
+            input_enabled: false,
+            sender: None,
+        }
+    }
+
+    /// Put the inset next to the source region, on the side where it fits into the image.
+    fn initial_inset(&self, (pos, size): (Vec2D, Vec2D), zoom: f32) -> Vec2D {
+        let image_size = Vec2D::new(
+            self.background.width() as f32,
+            self.background.height() as f32,
+        );
+        let inset_size = size * zoom;
+
+        let right = pos.x + size.x + INSET_GAP;
+        let left = pos.x - INSET_GAP - inset_size.x;
+        let x = if right + inset_size.x > image_size.x && left >= 0.0 {
+            left
+        } else {
+            right
+        };
+        let y = (pos.y + (size.y - inset_size.y) * 0.5)
+            .min(image_size.y - inset_size.y)
+            .max(0.0);
+        Vec2D::new(x, y)
+    }
+
+    fn set_inset_center(&mut self, center: Vec2D) {
+        if let Some(magnifier) = &mut self.magnifier
+            && let Some((_, size)) = magnifier.inset_rect()
+        {
+            magnifier.inset = center - size * 0.5;
+        }
+    }
+
+    fn commit(&mut self) -> ToolUpdateResult {
+        self.placing = false;
+        self.moving = None;
+        match self.magnifier.take() {
+            Some(magnifier) => ToolUpdateResult::Commit(magnifier.clone_box()),
+            None => ToolUpdateResult::Unmodified,
+        }
+    }
+}
+
+impl Tool for MagnifierTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn active(&self) -> bool {
+        self.magnifier.is_some()
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        if self.placing {
+            self.commit()
+        } else {
+            self.magnifier = None;
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button != MouseButton::Primary {
+            return ToolUpdateResult::Unmodified;
+        }
+        match event.type_ {
+            MouseEventType::Click if self.placing => {
+                self.set_inset_center(event.pos);
+                self.moving = Some(event.pos);
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::BeginDrag if !self.placing => {
+                // start new
+                let zoom = APP_CONFIG.read().magnifier_zoom();
+                let mut magnifier =
+                    Magnifier::new(event.pos, Vec2D::zero(), event.pos, zoom, self.style)
+                        .with_background(self.background.clone());
+                magnifier.size = None;
+                magnifier.editing = true;
+                self.magnifier = Some(magnifier);
+
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::UpdateDrag => {
+                if let Some(center) = self.moving {
+                    self.set_inset_center(center + event.pos);
+                    ToolUpdateResult::Redraw
+                } else if let Some(magnifier) = &mut self.magnifier
+                    && !self.placing
+                {
+                    if event.pos == Vec2D::zero() {
+                        return ToolUpdateResult::Unmodified;
+                    }
+                    magnifier.calculate_shape(&event);
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            MouseEventType::EndDrag if !self.placing => {
+                let Some(magnifier) = &mut self.magnifier else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    self.magnifier = None;
+                    return ToolUpdateResult::Redraw;
+                }
+                magnifier.calculate_shape(&event);
+                magnifier.editing = false;
+                if let Some(source) = magnifier.source() {
+                    let zoom = magnifier.zoom;
+                    let inset = self.initial_inset(source, zoom);
+                    if let Some(magnifier) = &mut self.magnifier {
+                        magnifier.inset = inset;
+                    }
+                }
+                self.placing = true;
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::Release if self.moving.is_some() => self.commit(),
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        let Some(magnifier) = &mut self.magnifier else {
+            return ToolUpdateResult::Unmodified;
+        };
+        match event.key {
+            Key::Escape => {
+                self.magnifier = None;
+                self.placing = false;
+                self.moving = None;
+                ToolUpdateResult::Redraw
+            }
+            Key::Return | Key::KP_Enter if self.placing => self.commit(),
+            Key::plus | Key::equal | Key::KP_Add if self.placing => {
+                magnifier.set_zoom(magnifier.zoom + 1.0);
+                ToolUpdateResult::Redraw
+            }
+            Key::minus | Key::KP_Subtract if self.placing => {
+                magnifier.set_zoom(magnifier.zoom - 1.0);
+                ToolUpdateResult::Redraw
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        if let Some(magnifier) = &mut self.magnifier {
+            magnifier.style = style;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.magnifier {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Magnifier
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
@@ -4,12 +4,17 @@ use std::rc::Rc;
 
 use femtovg::{Color, Paint, Path};
 use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::sketch_board::{KeyEventMsg, MouseButton, MouseEventType, SketchBoardInput};
-use crate::style::Style;
-use crate::{math::Vec2D, sketch_board::MouseEventMsg};
-
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use crate::style::{self, Style};
+use crate::svg::{Element, SvgDocument};
+use crate::{
+    math::{self, Vec2D},
+    sketch_board::MouseEventMsg,
+};
+
+use super::{Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools};
 use relm4::Sender;
 
 pub struct MarkerTool {
@@ -21,21 +26,54 @@ pub struct MarkerTool {
     sender: Option<Sender<SketchBoardInput>>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Marker {
     pos: Vec2D,
     number: u16,
     extra_ring: bool,
     style: Style,
+    #[serde(skip)]
     tool_next_number: Rc<RefCell<u16>>,
+    #[serde(skip)]
+    outer_radius: RefCell<f32>,
 }
 
 impl Marker {
+    pub fn new(pos: Vec2D, number: u16, style: Style) -> Self {
+        Self {
+            pos,
+            number,
+            extra_ring: false,
+            style,
+            tool_next_number: Rc::new(RefCell::new(number + 1)),
+            outer_radius: RefCell::new(0.0),
+        }
+    }
+
+    pub fn number(&self) -> u16 {
+        self.number
+    }
+
+    /// Link the marker to the numbering of the marker tool, which is lost when saved.
+    pub fn with_tool_counter(mut self, next_number: Rc<RefCell<u16>>) -> Self {
+        self.tool_next_number = next_number;
+        self
+    }
+
     fn get_line_width(&self) -> f32 {
         self.style
             .size
             .to_line_width(self.style.annotation_size_factor)
     }
+
+    /// Whether black text is more readable than white on the marker color.
+    fn has_dark_text(&self) -> bool {
+        let color: Color = self.style.color.into();
+        // https://en.wikipedia.org/wiki/Luma_(video)
+        let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+        luminance > 0.5
+    }
 }
 
 impl Drawable for Marker {
@@ -48,9 +86,7 @@ impl Drawable for Marker {
         let text = format!("{}", self.number);
 
         let marker_color: Color = self.style.color.into();
-        // https://en.wikipedia.org/wiki/Luma_(video)
-        let luminance = 0.2126 * marker_color.r + 0.7152 * marker_color.g + 0.0722 * marker_color.b;
-        let text_color = if luminance > 0.5 {
+        let text_color = if self.has_dark_text() {
             Color::black()
         } else {
             Color::white()
@@ -106,6 +142,12 @@ impl Drawable for Marker {
             canvas.stroke_path(&outer_ring_path, &circle_paint);
         }
 
+        *self.outer_radius.borrow_mut() = if self.extra_ring {
+            circle_radius + line_width * 2.5
+        } else {
+            circle_radius + line_width * 0.5
+        };
+
         canvas.fill_text(pos.x, pos.y, &text, &paint)?;
         canvas.restore();
         Ok(())
@@ -118,9 +160,99 @@ impl Drawable for Marker {
     fn handle_redo(&mut self) {
         *self.tool_next_number.borrow_mut() = self.number + 1;
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> anyhow::Result<()> {
+        let line_width = self.get_line_width();
+        let text_size = self
+            .style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32;
+        let outer_radius = *self.outer_radius.borrow();
+        // the radius is measured when drawn, estimate it for markers that never were
+        let circle_radius = if outer_radius > 0.0 {
+            outer_radius - line_width * if self.extra_ring { 2.5 } else { 0.5 }
+        } else {
+            text_size * 0.6 + line_width * 1.5
+        };
+
+        let circle = |radius: f32| {
+            Element::new("circle")
+                .attr("cx", self.pos.x)
+                .attr("cy", self.pos.y)
+                .attr("r", radius)
+        };
+        svg.push(
+            circle(circle_radius)
+                .fill(self.style.color)
+                .stroke_color(self.style.color)
+                .attr("stroke-width", line_width),
+        );
+        if self.extra_ring {
+            svg.push(
+                circle(circle_radius + line_width * 2.0)
+                    .attr("fill", "none")
+                    .stroke_color(self.style.color)
+                    .attr("stroke-width", line_width),
+            );
+        }
+
+        let text_color = if self.has_dark_text() {
+            style::Color::new(0, 0, 0, 255)
+        } else {
+            style::Color::new(255, 255, 255, 255)
+        };
+        svg.push(
+            Element::new("text")
+                .attr("x", self.pos.x)
+                .attr("y", self.pos.y)
+                .attr("text-anchor", "middle")
+                .attr("dominant-baseline", "central")
+                .font(text_size)
+                .fill(text_color)
+                .text(&self.number.to_string()),
+        );
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        let radius = Vec2D::new(*self.outer_radius.borrow(), *self.outer_radius.borrow());
+        Some((self.pos - radius, radius * 2.0))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        pos.distance_to(&self.pos) <= *self.outer_radius.borrow() + tolerance
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        // the marker size follows the style, so only its center is moved
+        self.pos = math::rect_map_point(self.pos, from, to);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Marker(self.clone()))
+    }
 }
 
 impl MarkerTool {
+    pub fn new(next_number: Rc<RefCell<u16>>) -> Self {
+        Self {
+            marker: None,
+            origin: Vec2D::zero(),
+            style: Default::default(),
+            next_number,
+            input_enabled: true,
+            sender: None,
+        }
+    }
+
     fn handle_alt_key_event(&mut self, event: KeyEventMsg, pressed: bool) -> ToolUpdateResult {
         if let Some(marker) = &mut self.marker
             && (event.key == Key::Alt_L || event.key == Key::Alt_R)
@@ -174,6 +306,7 @@ impl Tool for MarkerTool {
                     style: self.style,
                     tool_next_number: self.next_number.clone(),
                     extra_ring: event.modifier.contains(ModifierType::ALT_MASK),
+                    outer_radius: RefCell::new(0.0),
                 });
                 ToolUpdateResult::Redraw
             }
@@ -214,13 +347,6 @@ impl Tool for MarkerTool {
 
 impl Default for MarkerTool {
     fn default() -> Self {
-        Self {
-            marker: None,
-            origin: Vec2D::zero(),
-            style: Default::default(),
-            next_number: Rc::new(RefCell::new(1)),
-            input_enabled: true,
-            sender: None,
-        }
+        Self::new(Rc::new(RefCell::new(1)))
     }
 }
@@ -0,0 +1,381 @@
+use anyhow::Result;
+use femtovg::{FontId, Paint, Path};
+use relm4::{
+    Sender,
+    gtk::gdk::{Key, ModifierType},
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::APP_CONFIG,
+    femtovg_area,
+    math::{self, Vec2D},
+    sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    style::Style,
+    svg::{Element, SvgDocument},
+};
+
+use super::{Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MeasureMode {
+    /// A dimension line labelled with its length and angle.
+    #[default]
+    Distance,
+    /// A rectangle labelled with its width and height.
+    Rectangle,
+}
+
+/// A measurement of the image in pixels, labelled with the result.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Measure {
+    start: Vec2D,
+    end: Option<Vec2D>,
+    mode: MeasureMode,
+    style: Style,
+}
+
+impl Measure {
+    pub fn new(start: Vec2D, end: Vec2D, mode: MeasureMode, style: Style) -> Self {
+        Self {
+            start,
+            end: Some(end),
+            mode,
+            style,
+        }
+    }
+
+    fn line_width(&self) -> f32 {
+        self.style
+            .size
+            .to_line_width(self.style.annotation_size_factor)
+            / 2.0
+    }
+
+    fn label_size(&self) -> f32 {
+        self.style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32
+            * 0.5
+    }
+
+    /// The label text, showing logical pixels too if the image is scaled.
+    fn label(&self) -> Option<String> {
+        let end = self.end?;
+        let scale = APP_CONFIG
+            .read()
+            .input_scale()
+            .filter(|scale| *scale > 0.0 && (scale - 1.0).abs() > f32::EPSILON);
+
+        let offset = end - self.start;
+        Some(match self.mode {
+            MeasureMode::Distance => {
+                let length = offset.norm();
+                // counterclockwise from the positive x-axis, as the image y-axis points down
+                let angle = (-offset.y).atan2(offset.x).to_degrees();
+                match scale {
+                    Some(scale) => format!(
+                        "{length:.0} px · {:.0} logical · {angle:.1}°",
+                        length / scale
+                    ),
+                    None => format!("{length:.0} px · {angle:.1}°"),
+                }
+            }
+            MeasureMode::Rectangle => {
+                let (width, height) = (offset.x.abs(), offset.y.abs());
+                match scale {
+                    Some(scale) => format!(
+                        "{width:.0} × {height:.0} px · {:.0} × {:.0} logical",
+                        width / scale,
+                        height / scale
+                    ),
+                    None => format!("{width:.0} × {height:.0} px"),
+                }
+            }
+        })
+    }
+
+    /// Where the label is centered: in the middle of the line or the rectangle.
+    fn label_center(&self) -> Option<Vec2D> {
+        self.end.map(|end| (self.start + end) * 0.5)
+    }
+
+    /// The lines to stroke: the dimension line with a tick at both ends, or the rectangle.
+    fn polylines(&self) -> Option<Vec<Vec<Vec2D>>> {
+        let end = self.end?;
+        Some(match self.mode {
+            MeasureMode::Distance => {
+                let offset = end - self.start;
+                let length = offset.norm();
+                let mut polylines = vec![vec![self.start, end]];
+                if length > f32::EPSILON {
+                    let tick = Vec2D::new(-offset.y, offset.x) * (self.label_size() * 0.4 / length);
+                    polylines.push(vec![self.start - tick, self.start + tick]);
+                    polylines.push(vec![end - tick, end + tick]);
+                }
+                polylines
+            }
+            MeasureMode::Rectangle => vec![vec![
+                self.start,
+                Vec2D::new(end.x, self.start.y),
+                end,
+                Vec2D::new(self.start.x, end.y),
+                self.start,
+            ]],
+        })
+    }
+}
+
+impl Drawable for Measure {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let (Some(polylines), Some(label), Some(center)) =
+            (self.polylines(), self.label(), self.label_center())
+        else {
+            return Ok(()); // exit if no end
+        };
+
+        canvas.save();
+
+        let mut path = Path::new();
+        for points in &polylines {
+            path.move_to(points[0].x, points[0].y);
+            for p in &points[1..] {
+                path.line_to(p.x, p.y);
+            }
+        }
+        canvas.stroke_path(
+            &path,
+            &Paint::from(self.style).with_line_width(self.line_width()),
+        );
+
+        let label_size = self.label_size();
+        let mut text_paint = Paint::color(self.style.color.contrast().into());
+        if femtovg_area::font_stack().is_empty() {
+            text_paint.set_font(&[font]);
+        } else {
+            text_paint.set_font(femtovg_area::font_stack());
+        }
+        text_paint.set_font_size(label_size);
+        text_paint.set_text_align(femtovg::Align::Center);
+        text_paint.set_text_baseline(femtovg::Baseline::Middle);
+
+        let metrics = canvas.measure_text(center.x, center.y, &label, &text_paint)?;
+        let padding = label_size * 0.3;
+        let box_size = Vec2D::new(metrics.width() + padding * 2.0, label_size + padding * 2.0);
+        let mut box_path = Path::new();
+        box_path.rounded_rect(
+            center.x - box_size.x / 2.0,
+            center.y - box_size.y / 2.0,
+            box_size.x,
+            box_size.y,
+            padding,
+        );
+        canvas.fill_path(&box_path, &Paint::color(self.style.color.into()));
+        canvas.fill_text(center.x, center.y, &label, &text_paint)?;
+
+        canvas.restore();
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        let (Some(polylines), Some(label), Some(center)) =
+            (self.polylines(), self.label(), self.label_center())
+        else {
+            return Ok(());
+        };
+
+        svg.push(
+            Element::path(&polylines, false)
+                .attr("fill", "none")
+                .stroke_color(self.style.color)
+                .attr("stroke-width", self.line_width())
+                .attr(
+                    "stroke-linecap",
+                    if self.style.round_caps {
+                        "round"
+                    } else {
+                        "butt"
+                    },
+                ),
+        );
+
+        // the text can't be measured here, estimate the width of the box around it
+        let label_size = self.label_size();
+        let padding = label_size * 0.3;
+        let box_size = Vec2D::new(
+            label.chars().count() as f32 * label_size * 0.55 + padding * 2.0,
+            label_size + padding * 2.0,
+        );
+        svg.push(
+            Element::new("rect")
+                .attr("x", center.x - box_size.x / 2.0)
+                .attr("y", center.y - box_size.y / 2.0)
+                .attr("width", box_size.x)
+                .attr("height", box_size.y)
+                .attr("rx", padding)
+                .fill(self.style.color),
+        );
+        svg.push(
+            Element::new("text")
+                .attr("x", center.x)
+                .attr("y", center.y)
+                .attr("text-anchor", "middle")
+                .attr("dominant-baseline", "central")
+                .font(label_size)
+                .fill(self.style.color.contrast())
+                .text(&label),
+        );
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        self.end
+            .and_then(|end| math::rect_from_points([self.start, end]))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let (Some(polylines), Some(center)) = (self.polylines(), self.label_center()) else {
+            return false;
+        };
+        let margin = tolerance + self.line_width() / 2.0;
+        polylines
+            .iter()
+            .any(|points| math::distance_to_polyline(pos, points) <= margin)
+            || pos.distance_to(&center) <= tolerance + self.label_size()
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        self.start = math::rect_map_point(self.start, from, to);
+        self.end = self.end.map(|end| math::rect_map_point(end, from, to));
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Measure(*self))
+    }
+}
+
+#[derive(Default)]
+pub struct MeasureTool {
+    measure: Option<Measure>,
+    style: Style,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl MeasureTool {
+    /// The end of the measurement dragged by `delta`, snapped to 15° steps for distances if
+    /// requested.
+    fn end(measure: &Measure, delta: Vec2D, modifier: ModifierType) -> Vec2D {
+        if measure.mode == MeasureMode::Distance && modifier.intersects(ModifierType::SHIFT_MASK) {
+            measure.start + delta.snapped_vector_15deg()
+        } else {
+            measure.start + delta
+        }
+    }
+}
+
+impl Tool for MeasureTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn active(&self) -> bool {
+        self.measure.is_some()
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button == MouseButton::Middle {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        match event.type_ {
+            MouseEventType::BeginDrag => {
+                let mode = if event.modifier.contains(ModifierType::CONTROL_MASK) {
+                    MeasureMode::Rectangle
+                } else {
+                    MeasureMode::Distance
+                };
+                self.measure = Some(Measure {
+                    start: event.pos,
+                    end: None,
+                    mode,
+                    style: self.style,
+                });
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::UpdateDrag => {
+                if let Some(measure) = &mut self.measure {
+                    measure.end = Some(Self::end(measure, event.pos, event.modifier));
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            MouseEventType::EndDrag => {
+                let Some(mut measure) = self.measure.take() else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    ToolUpdateResult::Redraw
+                } else {
+                    measure.end = Some(Self::end(&measure, event.pos, event.modifier));
+                    ToolUpdateResult::Commit(measure.clone_box())
+                }
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: crate::sketch_board::KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape && self.measure.is_some() {
+            self.measure = None;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.measure = None;
+        ToolUpdateResult::Unmodified
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        ToolUpdateResult::Unmodified
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.measure {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Measure
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
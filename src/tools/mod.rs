@@ -5,6 +5,7 @@ use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc};
 use anyhow::Result;
 use femtovg::{Canvas, FontId, renderer::OpenGl};
 use relm4::gtk::gdk_pixbuf::{
+    Pixbuf,
     glib::{Variant, VariantTy},
     prelude::{StaticVariantType, ToVariant},
 };
@@ -14,12 +15,13 @@ use relm4::{
     Sender,
     gtk::{self, IMMulticontext},
 };
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::Vec2D,
+    math::{self, Vec2D},
     sketch_board::{InputEvent, KeyEventMsg, MouseEventMsg, SketchBoardInput, TextEventMsg},
-    style::Style,
+    style::{Color, Style},
+    svg::SvgDocument,
 };
 
 use satty_cli::command_line;
@@ -30,11 +32,21 @@ mod brush;
 mod crop;
 mod drag_box;
 mod ellipse;
+mod eraser;
+mod eyedropper;
+mod handles;
 mod highlight;
 mod line;
+mod magnifier;
 mod marker;
+mod measure;
+mod picture;
 mod pointer;
 mod rectangle;
+mod redact;
+mod shape_recognition;
+mod spotlight;
+mod stamp;
 mod text;
 
 pub enum ToolEvent {
@@ -130,6 +142,8 @@ pub trait Tool {
 #[derive(Clone, Debug)]
 pub struct GroupableTool {
     pub tool: Tools,
+    /// The blur mode the entry selects, for the variants of the blur tool.
+    pub blur_mode: Option<BlurMode>,
     pub icon_name: String,
     pub tooltip: Option<String>, // should be fine as long as there is no runtime shortcut editing
 }
@@ -158,29 +172,158 @@ where
 pub trait Drawable: DrawableClone + Debug {
     fn draw(&self, canvas: &mut Canvas<OpenGl>, font: FontId, bounds: (Vec2D, Vec2D))
     -> Result<()>;
+
+    /// Add the drawable as vector elements to an SVG export.
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()>;
+
     fn handle_undo(&mut self) {}
     fn handle_redo(&mut self) {}
+
+    /// The bounding rectangle (pos, size) in image coordinates, or None if there is
+    /// nothing to show yet.
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)>;
+
+    /// Whether `pos` (in image coordinates) is on the drawable, allowing `tolerance` pixels of slack.
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        self.bounds()
+            .is_some_and(|rect| math::rect_contains(rect, pos, tolerance))
+    }
+
+    /// Move and scale the drawable so that the rectangle `from` ends up at `to`.
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D));
+
+    /// Drop the images the drawable captured for its previous place, so they are captured
+    /// again on the next draw. A transformed drawable keeps showing its old capture until then,
+    /// capturing anew on every step of a drag would upload a new image each time.
+    fn refresh_capture(&mut self) {}
+
+    /// The style the drawable is drawn with, or None if it can't be restyled.
+    fn style(&self) -> Option<Style> {
+        None
+    }
+
+    fn set_style(&mut self, _style: Style) {}
+
+    /// A point the drawable is reshaped at, offered as a handle by the pointer tool when the
+    /// drawable is selected on its own, or None if it has none.
+    fn control_handle(&self) -> Option<Vec2D> {
+        None
+    }
+
+    /// Reshape the drawable so that its control handle is at `pos`.
+    fn set_control_handle(&mut self, _pos: Vec2D) {}
+
+    /// The pixel aligned rectangle (pos, size) and opaque color the drawable covers the image
+    /// with, or None if it doesn't redact anything. Redactions are painted over everything else.
+    fn redaction(&self) -> Option<((Vec2D, Vec2D), Color)> {
+        None
+    }
+
+    /// Obscure the pixels the drawable hides in the background embedded into SVG exports, for
+    /// drawables that hide them other than by a `redaction`. The pixels of the document's
+    /// background areas are changed in place.
+    fn obscure_background(&self, _svg: &SvgDocument) -> Result<()> {
+        Ok(())
+    }
+
+    /// The rectangle (pos, size) shown enlarged and the inset it is shown in, or None if the
+    /// drawable doesn't magnify anything.
+    fn magnification(&self) -> Option<((Vec2D, Vec2D), (Vec2D, Vec2D))> {
+        None
+    }
+
+    /// Draw the part of the drawable within `source` once more, enlarged into the `inset` of a
+    /// magnifier, for drawables obscuring pixels the magnifier would show in the original.
+    /// Called after the whole stack is drawn.
+    fn draw_magnified(
+        &self,
+        _canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _bounds: (Vec2D, Vec2D),
+        _source: (Vec2D, Vec2D),
+        _inset: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether the drawable affects the image itself and is drawn right above it, below all
+    /// other drawables.
+    fn is_backdrop(&self) -> bool {
+        false
+    }
+
+    /// The pieces left after erasing everything within `radius` of the segment from `start` to
+    /// `end`, or None if the drawable is not touched or can't be erased partially.
+    fn erase(&self, _start: Vec2D, _end: Vec2D, _radius: f32) -> Option<Vec<Box<dyn Drawable>>> {
+        None
+    }
+
+    /// Whether the drawable only guides the user on screen and is left out of rendered images.
+    fn is_preview(&self) -> bool {
+        false
+    }
+
+    /// The drawable as stored in project files, or None if it is not persisted.
+    fn to_saved(&self) -> Option<SavedDrawable>;
+}
+
+/// A committed drawable as stored in project files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SavedDrawable {
+    Arrow(arrow::Arrow),
+    Blur(blur::Blur),
+    Brush(brush::BrushDrawable),
+    Ellipse(ellipse::Ellipse),
+    Highlight(highlight::HighlightKind),
+    Line(line::Line),
+    Magnifier(magnifier::Magnifier),
+    Marker(marker::Marker),
+    Measure(measure::Measure),
+    Picture(picture::Picture),
+    Polyline(line::Polyline),
+    Rectangle(rectangle::Rectangle),
+    Redact(redact::Redact),
+    Spotlight(spotlight::Spotlight),
+    Stamp(stamp::Stamp),
+    Text(text::SavedText),
 }
 
+/// The committed drawables, shared between the renderer and the tools editing them.
+pub type SharedDrawables = Rc<RefCell<Vec<Box<dyn Drawable>>>>;
+
 #[derive(Debug)]
 pub enum ToolUpdateResult {
     Commit(Box<dyn Drawable>),
+    /// Committed drawables were modified in place, holds their index and previous state.
+    Edit(Vec<(usize, Box<dyn Drawable>)>),
+    /// The committed drawables were replaced as a whole, holds the previous ones.
+    Replace(Vec<Box<dyn Drawable>>),
     Redraw,
     Unmodified,
     StopPropagation,
     RedrawAndStopPropagation,
 }
 
-pub use arrow::ArrowTool;
-pub use blur::BlurTool;
+pub use arrow::{Arrow, ArrowTool};
+pub use blur::{Blur, BlurMode, BlurTool};
 pub use crop::CropTool;
-pub use ellipse::EllipseTool;
-pub use highlight::{HighlightTool, Highlighters};
-pub use line::LineTool;
-pub use rectangle::RectangleTool;
-pub use text::TextTool;
-
-use self::{brush::BrushTool, marker::MarkerTool, pointer::PointerTool};
+pub use ellipse::{Ellipse, EllipseTool};
+pub use eraser::EraserTool;
+pub use eyedropper::EyedropperTool;
+pub use highlight::{HighlightKind, HighlightTool, Highlighters};
+pub use line::{Line, LineTool, Polyline};
+pub use magnifier::{Magnifier, MagnifierTool};
+pub use marker::Marker;
+pub use measure::{Measure, MeasureMode, MeasureTool};
+pub use picture::Picture;
+pub use pointer::PointerTool;
+pub use rectangle::{Rectangle, RectangleTool};
+pub use redact::{Redact, RedactTool, apply_redactions, collect_redactions, draw_redaction};
+pub use spotlight::{Spot, SpotShape, Spotlight, SpotlightTool};
+pub use stamp::{Stamp, StampSymbol, StampTool};
+pub use text::{SavedText, TextTool};
+
+use self::{brush::BrushTool, marker::MarkerTool};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -196,6 +339,14 @@ pub enum Tools {
     Blur = 8,
     Highlight = 9,
     Brush = 10,
+    Redact = 11,
+    Magnifier = 12,
+    Spotlight = 13,
+    Callout = 14,
+    Measure = 15,
+    Eyedropper = 16,
+    Stamp = 17,
+    Eraser = 18,
 }
 
 impl fmt::Display for Tools {
@@ -212,6 +363,14 @@ impl fmt::Display for Tools {
             Tools::Marker => "Marker",
             Tools::Blur => "Blur",
             Tools::Highlight => "Highlight",
+            Tools::Redact => "Redact",
+            Tools::Magnifier => "Magnifier",
+            Tools::Spotlight => "Spotlight",
+            Tools::Callout => "Callout",
+            Tools::Measure => "Measure",
+            Tools::Eyedropper => "Eyedropper",
+            Tools::Stamp => "Stamp",
+            Tools::Eraser => "Eraser",
         };
         write!(f, "{}", name)
     }
@@ -237,6 +396,14 @@ impl FromStr for Tools {
             "blur" => Ok(Self::Blur),
             "highlight" => Ok(Self::Highlight),
             "brush" => Ok(Self::Brush),
+            "redact" => Ok(Self::Redact),
+            "magnifier" => Ok(Self::Magnifier),
+            "spotlight" => Ok(Self::Spotlight),
+            "callout" => Ok(Self::Callout),
+            "measure" => Ok(Self::Measure),
+            "eyedropper" => Ok(Self::Eyedropper),
+            "stamp" => Ok(Self::Stamp),
+            "eraser" => Ok(Self::Eraser),
             _ => Err(ParseCommandError),
         }
     }
@@ -246,16 +413,20 @@ pub struct ToolsManager {
     tools: HashMap<Tools, Rc<RefCell<dyn Tool>>>,
     crop_tool: Rc<RefCell<CropTool>>,
     text_tool: Rc<RefCell<TextTool>>,
+    blur_tool: Rc<RefCell<BlurTool>>,
+    stamp_tool: Rc<RefCell<StampTool>>,
+    pointer_tool: Rc<RefCell<PointerTool>>,
+    drawables: SharedDrawables,
+    marker_next_number: Rc<RefCell<u16>>,
+    /// The image magnifiers sample their insets from.
+    background: Pixbuf,
 }
 
 impl ToolsManager {
-    pub fn new() -> Self {
+    pub fn new(background: &Pixbuf) -> Self {
         let mut tools: HashMap<Tools, Rc<RefCell<dyn Tool>>> = HashMap::new();
+        let drawables: SharedDrawables = Rc::new(RefCell::new(Vec::new()));
         //tools.insert(Tools::Crop, Rc::new(RefCell::new(CropTool::default())));
-        tools.insert(
-            Tools::Pointer,
-            Rc::new(RefCell::new(PointerTool::default())),
-        );
         tools.insert(Tools::Line, Rc::new(RefCell::new(LineTool::default())));
         tools.insert(Tools::Arrow, Rc::new(RefCell::new(ArrowTool::default())));
         tools.insert(
@@ -267,20 +438,59 @@ impl ToolsManager {
             Rc::new(RefCell::new(EllipseTool::default())),
         );
         tools.insert(Tools::Text, Rc::new(RefCell::new(TextTool::default())));
-        tools.insert(Tools::Blur, Rc::new(RefCell::new(BlurTool::default())));
+        tools.insert(Tools::Callout, Rc::new(RefCell::new(TextTool::callout())));
+        let blur_tool = Rc::new(RefCell::new(BlurTool::default()));
+        tools.insert(Tools::Blur, blur_tool.clone());
         tools.insert(
             Tools::Highlight,
             Rc::new(RefCell::new(HighlightTool::default())),
         );
-        tools.insert(Tools::Marker, Rc::new(RefCell::new(MarkerTool::default())));
+        let marker_next_number = Rc::new(RefCell::new(1));
+        tools.insert(
+            Tools::Marker,
+            Rc::new(RefCell::new(MarkerTool::new(marker_next_number.clone()))),
+        );
         tools.insert(Tools::Brush, Rc::new(RefCell::new(BrushTool::default())));
+        let stamp_tool = Rc::new(RefCell::new(StampTool::default()));
+        tools.insert(Tools::Stamp, stamp_tool.clone());
+        tools.insert(
+            Tools::Measure,
+            Rc::new(RefCell::new(MeasureTool::default())),
+        );
+        tools.insert(Tools::Redact, Rc::new(RefCell::new(RedactTool::default())));
+        tools.insert(
+            Tools::Eyedropper,
+            Rc::new(RefCell::new(EyedropperTool::new(background.clone()))),
+        );
+        tools.insert(
+            Tools::Magnifier,
+            Rc::new(RefCell::new(MagnifierTool::new(background.clone()))),
+        );
+        tools.insert(
+            Tools::Eraser,
+            Rc::new(RefCell::new(EraserTool::new(drawables.clone()))),
+        );
+        tools.insert(
+            Tools::Spotlight,
+            Rc::new(RefCell::new(SpotlightTool::new(
+                drawables.clone(),
+                background.clone(),
+            ))),
+        );
 
         let crop_tool = Rc::new(RefCell::new(CropTool::default()));
         let text_tool = Rc::new(RefCell::new(TextTool::default()));
+        let pointer_tool = Rc::new(RefCell::new(PointerTool::new(drawables.clone())));
         Self {
             tools,
             crop_tool,
             text_tool,
+            blur_tool,
+            stamp_tool,
+            pointer_tool,
+            drawables,
+            marker_next_number,
+            background: background.clone(),
         }
     }
 
@@ -288,6 +498,7 @@ impl ToolsManager {
         match tool {
             Tools::Crop => self.crop_tool.clone(),
             Tools::Text => self.text_tool.clone(),
+            Tools::Pointer => self.pointer_tool.clone(),
             _ => self
                 .tools
                 .get(tool)
@@ -305,6 +516,72 @@ impl ToolsManager {
     pub fn get_text_tool(&self) -> Rc<RefCell<TextTool>> {
         self.text_tool.clone()
     }
+
+    pub fn get_blur_tool(&self) -> Rc<RefCell<BlurTool>> {
+        self.blur_tool.clone()
+    }
+
+    pub fn get_stamp_tool(&self) -> Rc<RefCell<StampTool>> {
+        self.stamp_tool.clone()
+    }
+
+    pub fn get_pointer_tool(&self) -> Rc<RefCell<PointerTool>> {
+        self.pointer_tool.clone()
+    }
+
+    pub fn get_drawables(&self) -> SharedDrawables {
+        self.drawables.clone()
+    }
+
+    /// The crop rectangle (pos, size) to save in project files.
+    pub fn saved_crop(&self) -> Option<(Vec2D, Vec2D)> {
+        self.crop_tool
+            .borrow()
+            .get_crop()
+            .map(|c| c.get_rectangle())
+    }
+
+    /// The committed drawables in their persisted form.
+    pub fn saved_drawables(&self) -> Vec<SavedDrawable> {
+        self.drawables
+            .borrow()
+            .iter()
+            .filter_map(|d| d.to_saved())
+            .collect()
+    }
+
+    /// Add drawables loaded from a project file. They are not part of the undo history.
+    pub fn restore(&self, saved: Vec<SavedDrawable>) {
+        let mut drawables = self.drawables.borrow_mut();
+        for saved in saved {
+            let drawable: Box<dyn Drawable> = match saved {
+                SavedDrawable::Arrow(d) => Box::new(d),
+                SavedDrawable::Blur(d) => Box::new(d),
+                SavedDrawable::Brush(d) => Box::new(d),
+                SavedDrawable::Ellipse(d) => Box::new(d),
+                SavedDrawable::Highlight(d) => Box::new(d),
+                SavedDrawable::Line(d) => Box::new(d),
+                SavedDrawable::Magnifier(d) => Box::new(d.with_background(self.background.clone())),
+                SavedDrawable::Marker(d) => {
+                    // continue numbering after the highest restored marker
+                    let d = d.with_tool_counter(self.marker_next_number.clone());
+                    let next = d.number() + 1;
+                    let mut next_number = self.marker_next_number.borrow_mut();
+                    *next_number = (*next_number).max(next);
+                    Box::new(d)
+                }
+                SavedDrawable::Measure(d) => Box::new(d),
+                SavedDrawable::Picture(d) => Box::new(d),
+                SavedDrawable::Polyline(d) => Box::new(d),
+                SavedDrawable::Rectangle(d) => Box::new(d),
+                SavedDrawable::Redact(d) => Box::new(d),
+                SavedDrawable::Spotlight(d) => Box::new(d.with_background(self.background.clone())),
+                SavedDrawable::Stamp(d) => Box::new(d),
+                SavedDrawable::Text(d) => Box::new(text::Text::from(d)),
+            };
+            drawables.push(drawable);
+        }
+    }
 }
 
 impl StaticVariantType for Tools {
@@ -333,6 +610,14 @@ impl FromVariant for Tools {
             8 => Some(Tools::Blur),
             9 => Some(Tools::Highlight),
             10 => Some(Tools::Brush),
+            11 => Some(Tools::Redact),
+            12 => Some(Tools::Magnifier),
+            13 => Some(Tools::Spotlight),
+            14 => Some(Tools::Callout),
+            15 => Some(Tools::Measure),
+            16 => Some(Tools::Eyedropper),
+            17 => Some(Tools::Stamp),
+            18 => Some(Tools::Eraser),
             _ => None,
         })
     }
@@ -352,6 +637,14 @@ impl From<command_line::Tools> for Tools {
             command_line::Tools::Blur => Self::Blur,
             command_line::Tools::Highlight => Self::Highlight,
             command_line::Tools::Brush => Self::Brush,
+            command_line::Tools::Redact => Self::Redact,
+            command_line::Tools::Magnifier => Self::Magnifier,
+            command_line::Tools::Spotlight => Self::Spotlight,
+            command_line::Tools::Callout => Self::Callout,
+            command_line::Tools::Measure => Self::Measure,
+            command_line::Tools::Eyedropper => Self::Eyedropper,
+            command_line::Tools::Stamp => Self::Stamp,
+            command_line::Tools::Eraser => Self::Eraser,
         }
     }
 }
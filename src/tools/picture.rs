@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+
+use anyhow::Result;
+use femtovg::{FontId, ImageFlags, ImageId, Paint, Path};
+use relm4::gtk::gdk_pixbuf::Pixbuf;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    math::{self, Vec2D},
+    svg::SvgDocument,
+};
+
+use super::{Drawable, SavedDrawable, magnifier::pixels};
+
+/// An image inserted on top of the screenshot, like a logo or a second screenshot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Picture {
+    top_left: Vec2D,
+    size: Vec2D,
+    #[serde(with = "crate::project::embedded_png")]
+    image: Pixbuf,
+    #[serde(skip)]
+    cached_image: RefCell<Option<ImageId>>,
+}
+
+impl Picture {
+    pub fn new(top_left: Vec2D, size: Vec2D, image: Pixbuf) -> Self {
+        Self {
+            top_left,
+            size,
+            image,
+            cached_image: RefCell::new(None),
+        }
+    }
+
+    /// Place the image centered at `center` at its native size, scaled down to fit into
+    /// `max_size`.
+    pub fn centered(image: Pixbuf, center: Vec2D, max_size: Vec2D) -> Self {
+        let native = Vec2D::new(image.width() as f32, image.height() as f32);
+        let scale = (max_size.x / native.x).min(max_size.y / native.y).min(1.0);
+        let size = native * scale;
+        Self::new(center - size * 0.5, size, image)
+    }
+}
+
+impl Drawable for Picture {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some((pos, size)) = self.bounds() else {
+            return Ok(());
+        };
+
+        if self.cached_image.borrow().is_none() {
+            let native = Vec2D::new(self.image.width() as f32, self.image.height() as f32);
+            let pixels = pixels(&self.image, Vec2D::zero(), native);
+            let image = canvas.create_image(pixels.as_ref(), ImageFlags::empty())?;
+            self.cached_image.borrow_mut().replace(image);
+        }
+        // this unwrap is safe because we placed it above
+        let image = self.cached_image.borrow().unwrap();
+
+        canvas.save();
+        let mut path = Path::new();
+        path.rect(pos.x, pos.y, size.x, size.y);
+        canvas.fill_path(
+            &path,
+            &Paint::image(image, pos.x, pos.y, size.x, size.y, 0.0, 1.0),
+        );
+        canvas.restore();
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        if let Some((pos, size)) = self.bounds() {
+            svg.push(
+                SvgDocument::image_element(&self.image, pos, size)?
+                    .attr("preserveAspectRatio", "none"),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        Some(math::rect_ensure_positive_size(self.top_left, self.size))
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        let top_left = math::rect_map_point(self.top_left, from, to);
+        self.size = math::rect_map_point(self.top_left + self.size, from, to) - top_left;
+        self.top_left = top_left;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Picture(self.clone()))
+    }
+}
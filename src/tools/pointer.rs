@@ -1,22 +1,424 @@
-use super::{Tool, Tools};
-use crate::sketch_board::SketchBoardInput;
-use relm4::Sender;
+use anyhow::Result;
+use femtovg::{Color, Paint, Path};
+use relm4::{
+    Sender,
+    gtk::gdk::{Key, ModifierType},
+};
+
+use super::{
+    Drawable, SharedDrawables, Tool, ToolUpdateResult, Tools,
+    handles::{self, Handle},
+};
+use crate::{
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    style::Style,
+};
 
-#[derive(Default)]
 pub struct PointerTool {
+    drawables: SharedDrawables,
+    selection: Vec<usize>,
+    action: Option<PointerToolAction>,
+    style: Style,
     input_enabled: bool,
     sender: Option<Sender<SketchBoardInput>>,
 }
 
+enum PointerToolAction {
+    Move(DragState),
+    Resize(Handle, DragState),
+    /// Dragging the control handle of the only selected drawable, from where it was.
+    Reshape(Vec2D, DragState),
+    Select(SelectState),
+}
+
+struct DragState {
+    originals: Vec<(usize, Box<dyn Drawable>)>,
+    bounds: (Vec2D, Vec2D),
+}
+
+struct SelectState {
+    origin: Vec2D,
+    size: Vec2D,
+    // the selection before the rubber band started, kept when extending
+    initial: Vec<usize>,
+}
+
+impl PointerTool {
+    const HIT_TOLERANCE: f32 = 5.0;
+    const HANDLE_MARGIN_IN_2: f32 = 10.0 * 10.0;
+
+    pub fn new(drawables: SharedDrawables) -> Self {
+        Self {
+            drawables,
+            selection: Vec::new(),
+            action: None,
+            style: Style::default(),
+            input_enabled: false,
+            sender: None,
+        }
+    }
+
+    /// Indices of the selected drawables in ascending order.
+    pub fn selection(&self) -> &[usize] {
+        &self.selection
+    }
+
+    /// Cancel any ongoing drag and take the current selection.
+    pub fn take_selection(&mut self) -> Vec<usize> {
+        self.cancel_action();
+        std::mem::take(&mut self.selection)
+    }
+
+    pub fn clear_selection(&mut self) -> bool {
+        !self.take_selection().is_empty()
+    }
+
+    pub fn set_selection(&mut self, mut selection: Vec<usize>) {
+        selection.sort_unstable();
+        selection.dedup();
+        self.selection = selection;
+    }
+
+    /// The rectangle enclosing all selected drawables.
+    fn selection_bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        let drawables = self.drawables.borrow();
+        math::rect_from_points(
+            self.selection
+                .iter()
+                .filter_map(|index| drawables.get(*index)?.bounds())
+                .flat_map(|(pos, size)| [pos, pos + size]),
+        )
+    }
+
+    /// The control handle of the drawable if it is the only one selected.
+    fn control_handle(&self) -> Option<Vec2D> {
+        match self.selection.as_slice() {
+            [index] => self.drawables.borrow().get(*index)?.control_handle(),
+            _ => None,
+        }
+    }
+
+    /// Find the topmost drawable at `pos` in rendering order: backdrops are drawn below all
+    /// other drawables, so they are only hit where nothing else is.
+    fn find_drawable_at(&self, pos: Vec2D) -> Option<usize> {
+        let drawables = self.drawables.borrow();
+        let hit = |backdrop: bool| {
+            drawables
+                .iter()
+                .rposition(|d| d.is_backdrop() == backdrop && d.hit_test(pos, Self::HIT_TOLERANCE))
+        };
+        hit(false).or_else(|| hit(true))
+    }
+
+    /// Find all drawables overlapping the given rectangle.
+    fn find_drawables_in(&self, rect: (Vec2D, Vec2D)) -> Vec<usize> {
+        self.drawables
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.bounds().is_some_and(|b| math::rect_intersects(b, rect)))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn start_drag_state(&self) -> Option<DragState> {
+        let drawables = self.drawables.borrow();
+        let originals: Vec<_> = self
+            .selection
+            .iter()
+            .filter_map(|index| Some((*index, drawables.get(*index)?.clone_box())))
+            .collect();
+        if originals.is_empty() {
+            return None;
+        }
+
+        Some(DragState {
+            originals,
+            bounds: self.selection_bounds()?,
+        })
+    }
+
+    fn begin_drag(&mut self, pos: Vec2D, modifier: ModifierType) -> ToolUpdateResult {
+        let extend = modifier.contains(ModifierType::SHIFT_MASK);
+
+        // the handles of the current selection take precedence over anything below them
+        if !extend
+            && let Some(handle) = self.control_handle()
+            && handle.distance_to(&pos).powi(2) <= Self::HANDLE_MARGIN_IN_2
+        {
+            self.action = self
+                .start_drag_state()
+                .map(|state| PointerToolAction::Reshape(handle, state));
+            return ToolUpdateResult::Redraw;
+        }
+        if !extend
+            && let Some((bounds_pos, bounds_size)) = self.selection_bounds()
+            && let Some(handle) =
+                Handle::hit(bounds_pos, bounds_size, pos, Self::HANDLE_MARGIN_IN_2)
+        {
+            self.action = self
+                .start_drag_state()
+                .map(|state| PointerToolAction::Resize(handle, state));
+            return ToolUpdateResult::Redraw;
+        }
+
+        match self.find_drawable_at(pos) {
+            Some(index) if extend && self.selection.contains(&index) => {
+                // shift-click on a selected drawable removes it from the selection
+                self.selection.retain(|i| *i != index);
+                self.action = None;
+                return ToolUpdateResult::Redraw;
+            }
+            Some(index) if extend => {
+                let mut selection = self.selection.clone();
+                selection.push(index);
+                self.set_selection(selection);
+            }
+            Some(index) => {
+                if !self.selection.contains(&index) {
+                    self.selection = vec![index];
+                }
+            }
+            None => {
+                if !extend {
+                    self.selection.clear();
+                }
+                self.action = Some(PointerToolAction::Select(SelectState {
+                    origin: pos,
+                    size: Vec2D::zero(),
+                    initial: self.selection.clone(),
+                }));
+                return ToolUpdateResult::Redraw;
+            }
+        }
+
+        self.action = self.start_drag_state().map(PointerToolAction::Move);
+        ToolUpdateResult::Redraw
+    }
+
+    fn update_drag(&mut self, direction: Vec2D) -> bool {
+        match &mut self.action {
+            Some(PointerToolAction::Select(state)) => {
+                state.size = direction;
+                let rect = (state.origin, state.size);
+                let mut selection = state.initial.clone();
+                selection.extend(self.find_drawables_in(rect));
+                self.set_selection(selection);
+                true
+            }
+            Some(PointerToolAction::Move(state)) => {
+                let target = (state.bounds.0 + direction, state.bounds.1);
+                Self::apply_transform(&self.drawables, state, target);
+                true
+            }
+            Some(PointerToolAction::Resize(handle, state)) => {
+                let (tl, br) =
+                    handle.drag(state.bounds.0, state.bounds.0 + state.bounds.1, direction);
+                let target = math::rect_ensure_positive_size(tl, br - tl);
+                Self::apply_transform(&self.drawables, state, target);
+                true
+            }
+            Some(PointerToolAction::Reshape(handle, state)) => {
+                let mut drawables = self.drawables.borrow_mut();
+                for (index, original) in &state.originals {
+                    let mut drawable = original.clone_box();
+                    drawable.set_control_handle(*handle + direction);
+                    if let Some(d) = drawables.get_mut(*index) {
+                        *d = drawable;
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace the dragged drawables by their originals mapped onto `target`.
+    fn apply_transform(drawables: &SharedDrawables, state: &DragState, target: (Vec2D, Vec2D)) {
+        let mut drawables = drawables.borrow_mut();
+        for (index, original) in &state.originals {
+            let mut drawable = original.clone_box();
+            drawable.transform(state.bounds, target);
+            if let Some(d) = drawables.get_mut(*index) {
+                *d = drawable;
+            }
+        }
+    }
+
+    fn end_drag(&mut self, direction: Vec2D) -> ToolUpdateResult {
+        if !self.update_drag(direction) {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        match self.action.take() {
+            Some(PointerToolAction::Move(state))
+            | Some(PointerToolAction::Resize(_, state))
+            | Some(PointerToolAction::Reshape(_, state))
+                if !direction.is_zero() =>
+            {
+                // the drag showed the captures of the originals, capture once at the new place
+                let mut drawables = self.drawables.borrow_mut();
+                for (index, _) in &state.originals {
+                    if let Some(d) = drawables.get_mut(*index) {
+                        d.refresh_capture();
+                    }
+                }
+                ToolUpdateResult::Edit(state.originals)
+            }
+            _ => ToolUpdateResult::Redraw,
+        }
+    }
+
+    /// Apply the style properties changed from `previous` to `style` to all selected drawables.
+    fn restyle_selection(&mut self, previous: &Style, style: &Style) -> ToolUpdateResult {
+        let mut drawables = self.drawables.borrow_mut();
+        let mut edit = Vec::new();
+        for index in &self.selection {
+            if let Some(d) = drawables.get_mut(*index)
+                && let Some(current) = d.style()
+            {
+                let mut restyled = d.clone_box();
+                restyled.set_style(current.with_changes(previous, style));
+                edit.push((*index, std::mem::replace(d, restyled)));
+            }
+        }
+
+        if edit.is_empty() {
+            ToolUpdateResult::Unmodified
+        } else {
+            ToolUpdateResult::Edit(edit)
+        }
+    }
+
+    /// Abort the current action, restoring dragged drawables to their state before the drag.
+    fn cancel_action(&mut self) -> bool {
+        match self.action.take() {
+            Some(PointerToolAction::Move(state))
+            | Some(PointerToolAction::Resize(_, state))
+            | Some(PointerToolAction::Reshape(_, state)) => {
+                let mut drawables = self.drawables.borrow_mut();
+                for (index, original) in state.originals {
+                    if let Some(d) = drawables.get_mut(index) {
+                        *d = original;
+                    }
+                }
+                true
+            }
+            Some(PointerToolAction::Select(state)) => {
+                self.selection = state.initial;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn draw_selection(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    ) -> Result<()> {
+        let scale = canvas.transform().average_scale();
+        let border_paint = Paint::color(Color::rgbf(0.1, 0.1, 0.1)).with_line_width(1.0 / scale);
+
+        canvas.save();
+        if let Some(PointerToolAction::Select(state)) = &self.action {
+            let (pos, size) = math::rect_ensure_positive_size(state.origin, state.size);
+            let mut band_path = Path::new();
+            band_path.rect(pos.x, pos.y, size.x, size.y);
+
+            canvas.fill_path(&band_path, &Paint::color(Color::rgbaf(0.2, 0.4, 0.9, 0.2)));
+            canvas.stroke_path(&band_path, &border_paint);
+        }
+
+        if let Some((pos, size)) = self.selection_bounds() {
+            if self.selection.len() > 1 {
+                // outline every drawable of a multi-selection
+                let drawables = self.drawables.borrow();
+                for (pos, size) in self
+                    .selection
+                    .iter()
+                    .filter_map(|index| drawables.get(*index)?.bounds())
+                {
+                    let mut path = Path::new();
+                    path.rect(pos.x, pos.y, size.x, size.y);
+                    canvas.stroke_path(&path, &border_paint);
+                }
+            }
+
+            let mut border_path = Path::new();
+            border_path.rect(pos.x, pos.y, size.x, size.y);
+            canvas.stroke_path(&border_path, &border_paint);
+            handles::draw_handles(canvas, pos, size, scale);
+            if let Some(handle) = self.control_handle() {
+                handles::draw_handle(canvas, handle, scale);
+            }
+        }
+        canvas.restore();
+        Ok(())
+    }
+}
+
 impl Tool for PointerTool {
     fn get_tool_type(&self) -> super::Tools {
         Tools::Pointer
     }
 
-    fn get_drawable(&self) -> Option<&dyn super::Drawable> {
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        // the selection is drawn separately by the renderer using `draw_selection(&self)`,
+        // so it doesn't end up in the exported image
         None
     }
 
+    fn active(&self) -> bool {
+        self.action.is_some()
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button != MouseButton::Primary {
+            return ToolUpdateResult::Unmodified;
+        }
+        match event.type_ {
+            MouseEventType::BeginDrag => self.begin_drag(event.pos, event.modifier),
+            MouseEventType::UpdateDrag => {
+                if self.update_drag(event.pos) {
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            MouseEventType::EndDrag => self.end_drag(event.pos),
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape && (self.cancel_action() || self.clear_selection()) {
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        let previous = std::mem::replace(&mut self.style, style);
+        if self.action.is_some() {
+            return ToolUpdateResult::Unmodified;
+        }
+        self.restyle_selection(&previous, &style)
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        if self.clear_selection() {
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_dismissed(&mut self) -> ToolUpdateResult {
+        self.handle_deactivated()
+    }
+
     fn input_enabled(&self) -> bool {
         self.input_enabled
     }
@@ -1,29 +1,48 @@
 use anyhow::Result;
 use femtovg::{FontId, Path};
 use relm4::{Sender, gtk::gdk::Key};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
-    math::Vec2D,
+    math::{self, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
     style::Style,
+    svg::{Element, SvgDocument},
 };
 
 use super::{
-    Drawable, DrawableClone, Tool, ToolUpdateResult, Tools,
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools,
     drag_box::{DragBox, draw_center_marker},
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct Rectangle {
+    #[serde(skip)]
     origin: Vec2D,
     top_left: Vec2D,
     size: Option<Vec2D>,
     style: Style,
+    #[serde(skip)]
     centered: bool,
+    #[serde(skip)]
     finishing: bool,
 }
 
+impl Rectangle {
+    pub fn new(top_left: Vec2D, size: Vec2D, style: Style) -> Self {
+        Self {
+            origin: top_left,
+            top_left,
+            size: Some(size),
+            style,
+            centered: false,
+            finishing: true,
+        }
+    }
+}
+
 impl Drawable for Rectangle {
     fn draw(
         &self,
@@ -53,12 +72,67 @@ impl Drawable for Rectangle {
         if self.style.fill {
             canvas.fill_path(&path, &self.style.into());
         } else {
-            canvas.stroke_path(&path, &self.style.into());
+            canvas.stroke_path(&path, &self.style.dashed_paint());
         }
         canvas.restore();
 
         Ok(())
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        if let Some(size) = self.size {
+            svg.push(
+                Element::rounded_rect(self.top_left, size)
+                    .paint(&self.style)
+                    .dashes(&self.style),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let Some(rect) = self.bounds() else {
+            return false;
+        };
+        if self.style.fill {
+            return math::rect_contains(rect, pos, tolerance);
+        }
+
+        // only the outline is visible, so the inside is no hit
+        let margin = tolerance
+            + self
+                .style
+                .size
+                .to_line_width(self.style.annotation_size_factor)
+                / 2.0;
+        math::rect_contains(rect, pos, margin) && !math::rect_contains(rect, pos, -margin)
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            self.size = Some(math::rect_map_point(self.top_left + size, from, to) - top_left);
+            self.top_left = top_left;
+        }
+        self.origin = math::rect_map_point(self.origin, from, to);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Rectangle(*self))
+    }
 }
 
 impl Rectangle {
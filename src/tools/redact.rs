@@ -0,0 +1,462 @@
+use anyhow::Result;
+use femtovg::{FontId, Paint, Path, imgref::ImgVec, rgb::RGBA8};
+use relm4::{Sender, gtk::gdk::Key};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    math::{self, Vec2D},
+    sketch_board::{MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    style::{Color, Style},
+    svg::{Element, SvgDocument},
+};
+
+use super::{
+    Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools,
+    drag_box::{DragBox, draw_center_marker},
+};
+
+/// An opaque box hiding the pixels below it, unlike a blur it can't be reversed.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Redact {
+    #[serde(skip)]
+    origin: Vec2D,
+    top_left: Vec2D,
+    size: Option<Vec2D>,
+    style: Style,
+    #[serde(skip)]
+    centered: bool,
+    #[serde(skip)]
+    finishing: bool,
+}
+
+/// The rectangle (pos, size) grown to whole pixels, so that no pixel is only partially covered.
+pub fn pixel_rect(rect: (Vec2D, Vec2D)) -> (Vec2D, Vec2D) {
+    let (pos, size) = math::rect_ensure_positive_size(rect.0, rect.1);
+    let top_left = Vec2D::new(pos.x.floor(), pos.y.floor());
+    let bottom_right = Vec2D::new((pos.x + size.x).ceil(), (pos.y + size.y).ceil());
+    (top_left, bottom_right - top_left)
+}
+
+/// The redactions of the drawables, including the parts of them that magnifiers show
+/// enlarged elsewhere.
+pub fn collect_redactions<'a>(
+    drawables: impl IntoIterator<Item = &'a dyn Drawable>,
+) -> Vec<((Vec2D, Vec2D), Color)> {
+    let drawables: Vec<_> = drawables.into_iter().collect();
+    let redactions: Vec<_> = drawables.iter().filter_map(|d| d.redaction()).collect();
+    let magnified: Vec<_> = drawables
+        .iter()
+        .filter_map(|d| d.magnification())
+        .flat_map(|(source, inset)| {
+            redactions.iter().filter_map(move |(rect, color)| {
+                let (pos, size) = math::rect_intersection(*rect, source)?;
+                let mapped = (
+                    math::rect_map_point(pos, source, inset),
+                    math::rect_map_vector(size, source, inset),
+                );
+                Some((pixel_rect(mapped), *color))
+            })
+        })
+        .collect();
+
+    redactions.into_iter().chain(magnified).collect()
+}
+
+/// Overwrite the redacted pixels of `image`, which starts at `offset` in image coordinates.
+/// Unlike the GPU rendering, this leaves nothing of the original pixels no matter what is
+/// blended on top.
+pub fn apply_redactions(
+    image: &mut ImgVec<RGBA8>,
+    offset: Vec2D,
+    redactions: &[((Vec2D, Vec2D), Color)],
+) {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    for (rect, color) in redactions {
+        let (pos, size) = pixel_rect(*rect);
+        let x0 = (pos.x - offset.x).floor().clamp(0.0, width) as usize;
+        let y0 = (pos.y - offset.y).floor().clamp(0.0, height) as usize;
+        let x1 = (pos.x + size.x - offset.x).ceil().clamp(0.0, width) as usize;
+        let y1 = (pos.y + size.y - offset.y).ceil().clamp(0.0, height) as usize;
+        let pixel = RGBA8::new(color.r, color.g, color.b, 255);
+        for row in image.rows_mut().take(y1).skip(y0) {
+            row[x0..x1.max(x0)].fill(pixel);
+        }
+    }
+}
+
+/// Fill the redacted rectangle without rounded corners and anti-aliasing, which would blend
+/// the original pixels into the edges.
+pub fn draw_redaction(
+    canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    rect: (Vec2D, Vec2D),
+    color: Color,
+) {
+    let (pos, size) = pixel_rect(rect);
+    let mut path = Path::new();
+    path.rect(pos.x, pos.y, size.x, size.y);
+    canvas.fill_path(
+        &path,
+        &Paint::color(color.with_alpha(255).into()).with_anti_alias(false),
+    );
+}
+
+impl Redact {
+    pub fn new(top_left: Vec2D, size: Vec2D, style: Style) -> Self {
+        Self {
+            origin: top_left,
+            top_left,
+            size: Some(size),
+            style,
+            centered: false,
+            finishing: true,
+        }
+    }
+
+    fn calculate_shape(&mut self, event: &MouseEventMsg) {
+        let drag_box = DragBox::from_origin_delta(self.origin, event.pos, event.modifier);
+        self.centered = drag_box.centered;
+        self.top_left = drag_box.top_left;
+        self.size = Some(drag_box.size);
+    }
+}
+
+impl Drawable for Redact {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some(rect) = self.bounds() else {
+            return Ok(()); // early exit if none
+        };
+
+        canvas.save();
+        draw_redaction(canvas, rect, self.style.color);
+        if !self.finishing && self.centered {
+            draw_center_marker(canvas, self.origin);
+        }
+        canvas.restore();
+
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        // the embedded background is already redacted, this keeps the box editable
+        if let Some(rect) = self.bounds() {
+            let (pos, size) = pixel_rect(rect);
+            svg.push(
+                Element::new("rect")
+                    .attr("x", pos.x)
+                    .attr("y", pos.y)
+                    .attr("width", size.x)
+                    .attr("height", size.y)
+                    .fill(self.style.color.with_alpha(255)),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            self.size = Some(math::rect_map_point(self.top_left + size, from, to) - top_left);
+            self.top_left = top_left;
+        }
+        self.origin = math::rect_map_point(self.origin, from, to);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn redaction(&self) -> Option<((Vec2D, Vec2D), Color)> {
+        self.bounds()
+            .map(|rect| (pixel_rect(rect), self.style.color.with_alpha(255)))
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Redact(*self))
+    }
+}
+
+#[derive(Default)]
+pub struct RedactTool {
+    redact: Option<Redact>,
+    style: Style,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl Tool for RedactTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn active(&self) -> bool {
+        self.redact.is_some()
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        match event.type_ {
+            MouseEventType::BeginDrag => {
+                if event.button == MouseButton::Middle {
+                    return ToolUpdateResult::Unmodified;
+                }
+                // start new
+                self.redact = Some(Redact {
+                    origin: event.pos,
+                    top_left: event.pos,
+                    size: None,
+                    style: self.style,
+                    centered: false,
+                    finishing: false,
+                });
+
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::EndDrag => {
+                if event.button == MouseButton::Middle {
+                    return ToolUpdateResult::Unmodified;
+                }
+
+                if let Some(redact) = &mut self.redact {
+                    redact.finishing = true;
+                    if event.pos == Vec2D::zero() {
+                        self.redact = None;
+
+                        ToolUpdateResult::Redraw
+                    } else {
+                        redact.calculate_shape(&event);
+                        let result = redact.clone_box();
+                        self.redact = None;
+                        ToolUpdateResult::Commit(result)
+                    }
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            MouseEventType::UpdateDrag => {
+                if event.button == MouseButton::Middle {
+                    return ToolUpdateResult::Unmodified;
+                }
+
+                if let Some(redact) = &mut self.redact {
+                    if event.pos == Vec2D::zero() {
+                        return ToolUpdateResult::Unmodified;
+                    }
+                    redact.calculate_shape(&event);
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: crate::sketch_board::KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape && self.redact.is_some() {
+            self.redact = None;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        ToolUpdateResult::Unmodified
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.redact {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Redact
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Redact, apply_redactions, collect_redactions, pixel_rect};
+    use crate::{
+        math::Vec2D,
+        style::{Color, Style},
+        tools::{Drawable, Magnifier},
+    };
+    use femtovg::{
+        imgref::{Img, ImgVec},
+        rgb::RGBA8,
+    };
+
+    const ORIGINAL: RGBA8 = RGBA8::new(10, 20, 30, 255);
+
+    fn image(width: usize, height: usize) -> ImgVec<RGBA8> {
+        Img::new(vec![ORIGINAL; width * height], width, height)
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> (Vec2D, Vec2D) {
+        (Vec2D::new(x, y), Vec2D::new(width, height))
+    }
+
+    /// The coordinates of the pixels with the given value.
+    fn pixels_of(image: &ImgVec<RGBA8>, value: RGBA8) -> Vec<(usize, usize)> {
+        image
+            .rows()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(move |(_, p)| **p == value)
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect()
+    }
+
+    fn block(xs: std::ops::Range<usize>, ys: std::ops::Range<usize>) -> Vec<(usize, usize)> {
+        ys.flat_map(|y| xs.clone().map(move |x| (x, y))).collect()
+    }
+
+    #[test]
+    fn pixel_rect_grows_to_whole_pixels() {
+        for (pos, size) in [
+            rect(0.0, 0.0, 2.0, 3.0),
+            rect(0.2, 1.7, 2.1, 0.1),
+            rect(-3.5, -0.5, 1.2, 4.9),
+            rect(5.5, 4.25, -2.75, -3.5),
+            rect(1.5, 1.5, 0.0, 0.0),
+        ] {
+            let (grown_pos, grown_size) = pixel_rect((pos, size));
+            let (min, max) = (
+                Vec2D::new(pos.x.min(pos.x + size.x), pos.y.min(pos.y + size.y)),
+                Vec2D::new(pos.x.max(pos.x + size.x), pos.y.max(pos.y + size.y)),
+            );
+            for value in [grown_pos.x, grown_pos.y, grown_size.x, grown_size.y] {
+                assert_eq!(value, value.round(), "{value} of {pos:?} {size:?}");
+            }
+            assert!(grown_pos.x <= min.x && grown_pos.y <= min.y);
+            assert!(grown_pos.x + grown_size.x >= max.x && grown_pos.y + grown_size.y >= max.y);
+        }
+        assert_eq!(
+            pixel_rect(rect(0.2, 1.7, 2.1, 0.1)),
+            rect(0.0, 1.0, 3.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn fills_semi_transparent_colors_opaquely() {
+        let mut image = image(4, 4);
+        apply_redactions(
+            &mut image,
+            Vec2D::zero(),
+            &[(rect(1.0, 1.0, 2.0, 2.0), Color::new(200, 0, 0, 100))],
+        );
+
+        assert_eq!(
+            pixels_of(&image, RGBA8::new(200, 0, 0, 255)),
+            block(1..3, 1..3)
+        );
+        assert_eq!(pixels_of(&image, ORIGINAL).len(), 16 - 4);
+    }
+
+    #[test]
+    fn covers_partial_pixels_of_fractional_and_negative_rects() {
+        let mut image = image(6, 6);
+        let red = Color::new(255, 0, 0, 255);
+        apply_redactions(
+            &mut image,
+            Vec2D::zero(),
+            &[
+                (rect(0.5, 0.2, 1.1, 0.9), red),
+                (rect(5.5, 5.0, -1.2, -1.5), red),
+            ],
+        );
+
+        let mut expected = block(0..2, 0..2);
+        expected.extend(block(4..6, 3..5));
+        assert_eq!(pixels_of(&image, RGBA8::new(255, 0, 0, 255)), expected);
+    }
+
+    #[test]
+    fn applies_the_crop_offset() {
+        let mut image = image(4, 4);
+        apply_redactions(
+            &mut image,
+            Vec2D::new(2.0, 1.0),
+            &[(rect(3.0, 2.0, 1.0, 2.0), Color::new(0, 0, 255, 255))],
+        );
+
+        assert_eq!(
+            pixels_of(&image, RGBA8::new(0, 0, 255, 255)),
+            block(1..2, 1..3)
+        );
+    }
+
+    #[test]
+    fn clips_rects_reaching_outside_the_image() {
+        let mut image = image(4, 4);
+        let green = Color::new(0, 255, 0, 255);
+        apply_redactions(
+            &mut image,
+            Vec2D::zero(),
+            &[
+                (rect(-5.0, -5.0, 7.0, 6.5), green),
+                (rect(3.5, 3.5, 10.0, 10.0), green),
+                (rect(10.0, -3.0, 2.0, 2.0), green),
+            ],
+        );
+
+        let mut expected = block(0..2, 0..2);
+        expected.push((3, 3));
+        assert_eq!(pixels_of(&image, RGBA8::new(0, 255, 0, 255)), expected);
+    }
+
+    #[test]
+    fn redacts_magnified_copies() {
+        let style = Style {
+            color: Color::new(0, 0, 0, 128),
+            ..Style::default()
+        };
+        let redact = Redact::new(Vec2D::new(2.0, 2.0), Vec2D::new(2.0, 2.0), style);
+        // the inset shows the source enlarged to (8, 0) - (16, 8)
+        let magnifier = Magnifier::new(
+            Vec2D::zero(),
+            Vec2D::new(4.0, 4.0),
+            Vec2D::new(8.0, 0.0),
+            2.0,
+            style,
+        );
+        let drawables: [&dyn Drawable; 2] = [&redact, &magnifier];
+
+        let mut image = image(16, 8);
+        apply_redactions(&mut image, Vec2D::zero(), &collect_redactions(drawables));
+
+        let mut expected = block(2..4, 2..4);
+        expected.extend(block(12..16, 4..8));
+        expected.sort_by_key(|(x, y)| (*y, *x));
+        assert_eq!(pixels_of(&image, RGBA8::new(0, 0, 0, 255)), expected);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{
+    math::{self, Vec2D},
+    style::{ArrowHead, Style},
+};
+
+use super::{Arrow, Drawable, DrawableClone, Ellipse, Line, Rectangle};
+
+/// Strokes shorter than this many image pixels are never replaced.
+const MIN_LENGTH: f32 = 20.0;
+/// How far the points of a line may stray from it, relative to its length.
+const LINE_TOLERANCE: f32 = 0.06;
+/// How much longer than the distance between its ends a line may be drawn, for wobbles.
+const LINE_MAX_DETOUR: f32 = 1.2;
+/// How far apart the ends of a closed stroke may be, relative to its length.
+const CLOSED_GAP: f32 = 0.15;
+/// The mean distance of a closed stroke to a rectangle or ellipse, relative to half of its
+/// smaller side.
+const SHAPE_TOLERANCE: f32 = 0.08;
+/// How far the barbs of an arrow reach back and aside from its tip, relative to the shaft.
+const HEAD_MAX_SIZE: f32 = 0.5;
+const HEAD_MIN_SPREAD: f32 = 0.05;
+
+/// The clean shape a brush stroke through `points` closely matches: a line, an arrow drawn
+/// with its head after the shaft, an axis aligned rectangle or an ellipse. None if it matches
+/// none of them.
+pub fn recognize(points: &[Vec2D], style: Style) -> Option<Box<dyn Drawable>> {
+    let length = path_length(points);
+    if points.len() < 3 || length < MIN_LENGTH {
+        return None;
+    }
+
+    let (first, last) = (points[0], points[points.len() - 1]);
+    if first.distance_to(&last) <= CLOSED_GAP * length {
+        closed_shape(points, style)
+    } else if let Some(arrow) = arrow(points, style) {
+        Some(arrow)
+    } else if is_line(points) {
+        Some(Line::new(first, last, style).clone_box())
+    } else {
+        None
+    }
+}
+
+fn path_length(points: &[Vec2D]) -> f32 {
+    points.windows(2).map(|w| w[0].distance_to(&w[1])).sum()
+}
+
+fn mean(values: impl ExactSizeIterator<Item = f32>) -> f32 {
+    let count = values.len().max(1) as f32;
+    values.sum::<f32>() / count
+}
+
+/// Whether the points run straight from the first to the last one.
+fn is_line(points: &[Vec2D]) -> bool {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let chord = first.distance_to(&last);
+    chord >= MIN_LENGTH
+        && path_length(points) <= chord * LINE_MAX_DETOUR
+        && points
+            .iter()
+            .all(|p| math::distance_to_segment(*p, first, last) <= LINE_TOLERANCE * chord)
+}
+
+/// A straight shaft up to the point farthest from the start, followed by a short head
+/// around that tip.
+fn arrow(points: &[Vec2D], style: Style) -> Option<Box<dyn Drawable>> {
+    let start = points[0];
+    let (tip_index, tip) = points
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| start.distance_to(a).total_cmp(&start.distance_to(b)))?;
+
+    let (shaft, head) = (&points[..=tip_index], &points[tip_index..]);
+    let shaft_length = start.distance_to(tip);
+    let head_length = path_length(head);
+    if shaft.len() < 2 || !is_line(shaft) || head_length < 0.1 * shaft_length {
+        return None;
+    }
+
+    // the barbs stay behind the tip and close to it, but spread away from the shaft
+    let direction = (*tip - start) * (1.0 / shaft_length);
+    let along = |p: &Vec2D| (p.x - tip.x) * direction.x + (p.y - tip.y) * direction.y;
+    let aside = |p: &Vec2D| ((p.x - tip.x) * direction.y - (p.y - tip.y) * direction.x).abs();
+    let in_reach = head.iter().all(|p| {
+        p.distance_to(tip) <= HEAD_MAX_SIZE * shaft_length
+            && along(p) <= LINE_TOLERANCE * shaft_length
+    });
+    let spread = head.iter().map(aside).fold(0.0, f32::max);
+    if !in_reach || spread < HEAD_MIN_SPREAD * shaft_length {
+        return None;
+    }
+
+    let style = Style {
+        arrow_end: match style.arrow_end {
+            ArrowHead::None => ArrowHead::Triangle,
+            head => head,
+        },
+        ..style
+    };
+    Some(Arrow::new(start, *tip, style).clone_box())
+}
+
+/// The rectangle or ellipse spanning the bounds of the points, whichever they follow closer.
+fn closed_shape(points: &[Vec2D], style: Style) -> Option<Box<dyn Drawable>> {
+    let (pos, size) = math::rect_from_points(points.iter().copied())?;
+    let half = size.x.min(size.y) / 2.0;
+    if 2.0 * half < MIN_LENGTH {
+        return None;
+    }
+
+    // all points lie within their bounds, so the outline is at the nearest side
+    let rectangle_error = mean(points.iter().map(|p| {
+        (p.x - pos.x)
+            .min(pos.x + size.x - p.x)
+            .min(p.y - pos.y)
+            .min(pos.y + size.y - p.y)
+    })) / half;
+
+    let center = pos + size * 0.5;
+    let radii = size * 0.5;
+    let ellipse_error = mean(points.iter().map(|p| {
+        let offset = *p - center;
+        (((offset.x / radii.x).powi(2) + (offset.y / radii.y).powi(2)).sqrt() - 1.0).abs()
+    }));
+
+    if rectangle_error.min(ellipse_error) > SHAPE_TOLERANCE {
+        None
+    } else if rectangle_error < ellipse_error {
+        Some(Rectangle::new(pos, size, style).clone_box())
+    } else {
+        Some(Ellipse::new(pos, size, style).clone_box())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CLOSED_GAP, MIN_LENGTH, recognize};
+    use crate::{math::Vec2D, style::Style, tools::SavedDrawable};
+
+    fn recognized(points: &[Vec2D]) -> Option<SavedDrawable> {
+        recognize(points, Style::default()).map(|d| d.to_saved().expect("saveable shape"))
+    }
+
+    /// Points every `step` pixels along the corners, `end` included.
+    fn trace(corners: &[(f32, f32)], step: f32) -> Vec<Vec2D> {
+        let mut points = vec![Vec2D::new(corners[0].0, corners[0].1)];
+        for w in corners.windows(2) {
+            let (from, to) = (Vec2D::new(w[0].0, w[0].1), Vec2D::new(w[1].0, w[1].1));
+            let count = (from.distance_to(&to) / step).ceil().max(1.0) as usize;
+            points.extend((1..=count).map(|i| from + (to - from) * (i as f32 / count as f32)));
+        }
+        points
+    }
+
+    #[test]
+    fn recognizes_lines() {
+        let points: Vec<_> = (0..=10)
+            .map(|i| Vec2D::new(i as f32 * 10.0, if i % 2 == 0 { 0.0 } else { 1.0 }))
+            .collect();
+        assert!(matches!(recognized(&points), Some(SavedDrawable::Line(_))));
+    }
+
+    #[test]
+    fn recognizes_arrows() {
+        let mut points = trace(&[(0.0, 0.0), (100.0, 0.0)], 10.0);
+        points.extend([Vec2D::new(92.0, 6.0), Vec2D::new(92.0, -6.0)]);
+        assert!(matches!(recognized(&points), Some(SavedDrawable::Arrow(_))));
+    }
+
+    #[test]
+    fn recognizes_rectangles() {
+        let points = trace(
+            &[
+                (0.0, 0.0),
+                (100.0, 0.0),
+                (100.0, 60.0),
+                (0.0, 60.0),
+                (0.0, 0.0),
+            ],
+            10.0,
+        );
+        assert!(matches!(
+            recognized(&points),
+            Some(SavedDrawable::Rectangle(_))
+        ));
+    }
+
+    #[test]
+    fn recognizes_ellipses() {
+        let points: Vec<_> = (0..=36)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::TAU / 36.0;
+                Vec2D::new(50.0 + 50.0 * angle.cos(), 30.0 + 30.0 * angle.sin())
+            })
+            .collect();
+        assert!(matches!(
+            recognized(&points),
+            Some(SavedDrawable::Ellipse(_))
+        ));
+    }
+
+    #[test]
+    fn ignores_short_strokes() {
+        let short = trace(&[(0.0, 0.0), (MIN_LENGTH * 0.8, 0.0)], 2.0);
+        assert!(recognized(&short).is_none());
+
+        let long = trace(&[(0.0, 0.0), (MIN_LENGTH * 1.2, 0.0)], 2.0);
+        assert!(matches!(recognized(&long), Some(SavedDrawable::Line(_))));
+
+        assert!(recognized(&[Vec2D::zero(), Vec2D::new(100.0, 0.0)]).is_none());
+    }
+
+    #[test]
+    fn ignores_wobbly_strokes() {
+        let points: Vec<_> = (0..=10)
+            .map(|i| Vec2D::new(i as f32 * 10.0, if i % 2 == 0 { 0.0 } else { 20.0 }))
+            .collect();
+        assert!(recognized(&points).is_none());
+    }
+
+    #[test]
+    fn closes_outlines_with_small_gaps() {
+        // an outline stopping `gap` short of its start is 320 - gap pixels long
+        let gap = 320.0 * CLOSED_GAP / (1.0 + CLOSED_GAP);
+        let almost_closed = trace(
+            &[
+                (0.0, 0.0),
+                (100.0, 0.0),
+                (100.0, 60.0),
+                (0.0, 60.0),
+                (0.0, 0.9 * gap),
+            ],
+            10.0,
+        );
+        assert!(matches!(
+            recognized(&almost_closed),
+            Some(SavedDrawable::Rectangle(_))
+        ));
+
+        let open = trace(
+            &[
+                (0.0, 0.0),
+                (100.0, 0.0),
+                (100.0, 60.0),
+                (0.0, 60.0),
+                (0.0, 1.2 * gap),
+            ],
+            10.0,
+        );
+        assert!(recognized(&open).is_none());
+    }
+}
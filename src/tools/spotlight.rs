@@ -0,0 +1,416 @@
+use std::cell::RefCell;
+
+use anyhow::{Result, anyhow};
+use femtovg::{Color, FontId, ImageFlags, ImageId, Paint, Path};
+use relm4::{
+    Sender,
+    gtk::{
+        gdk::{Key, ModifierType},
+        gdk_pixbuf::Pixbuf,
+    },
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::APP_CONFIG,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    svg::{Element, SvgDocument},
+};
+
+use super::{
+    Drawable, DrawableClone, SavedDrawable, SharedDrawables, Tool, ToolUpdateResult, Tools,
+    drag_box::DragBox, magnifier::pixels,
+};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpotShape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+/// A region kept clear of the dimming.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Spot {
+    shape: SpotShape,
+    top_left: Vec2D,
+    size: Vec2D,
+}
+
+impl Spot {
+    pub fn new(shape: SpotShape, top_left: Vec2D, size: Vec2D) -> Self {
+        let (top_left, size) = math::rect_ensure_positive_size(top_left, size);
+        Self {
+            shape,
+            top_left,
+            size,
+        }
+    }
+
+    fn rect(&self) -> (Vec2D, Vec2D) {
+        (self.top_left, self.size)
+    }
+
+    fn path(&self) -> Path {
+        let mut path = Path::new();
+        match self.shape {
+            SpotShape::Rectangle => path.rounded_rect(
+                self.top_left.x,
+                self.top_left.y,
+                self.size.x,
+                self.size.y,
+                APP_CONFIG.read().corner_roundness(),
+            ),
+            SpotShape::Ellipse => {
+                let middle = self.top_left + self.size * 0.5;
+                path.ellipse(middle.x, middle.y, self.size.x / 2.0, self.size.y / 2.0)
+            }
+        }
+        path
+    }
+
+    fn element(&self) -> Element {
+        match self.shape {
+            SpotShape::Rectangle => Element::rounded_rect(self.top_left, self.size),
+            SpotShape::Ellipse => {
+                let middle = self.top_left + self.size * 0.5;
+                Element::new("ellipse")
+                    .attr("cx", middle.x)
+                    .attr("cy", middle.y)
+                    .attr("rx", self.size.x / 2.0)
+                    .attr("ry", self.size.y / 2.0)
+            }
+        }
+    }
+}
+
+/// Dims and desaturates the image except for its spots. All spots form a single mask, so
+/// overlapping ones don't darken twice.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Spotlight {
+    spots: Vec<Spot>,
+    dim: f32,
+    desaturate: f32,
+    /// Only the outlines are shown while a spot is dragged.
+    #[serde(skip)]
+    editing: bool,
+    #[serde(skip)]
+    background: Option<Pixbuf>,
+    #[serde(skip)]
+    cached_image: RefCell<Option<ImageId>>,
+    #[serde(skip)]
+    cached_gray: RefCell<Option<ImageId>>,
+}
+
+impl Spotlight {
+    /// A spotlight dimming and desaturating as configured.
+    pub fn new(spots: Vec<Spot>) -> Self {
+        let config = APP_CONFIG.read();
+        Self {
+            spots,
+            dim: config.spotlight_dim(),
+            desaturate: config.spotlight_desaturate(),
+            editing: false,
+            background: None,
+            cached_image: RefCell::new(None),
+            cached_gray: RefCell::new(None),
+        }
+    }
+
+    /// Attach the image shown inside the spots.
+    pub fn with_background(mut self, background: Pixbuf) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Take over the spots of another spotlight.
+    pub fn merge(&mut self, other: Spotlight) {
+        self.spots.extend(other.spots);
+    }
+
+    fn gray(background: &Pixbuf) -> Result<Pixbuf> {
+        let gray = background
+            .copy()
+            .ok_or(anyhow!("Could not copy the background image"))?;
+        background.saturate_and_pixelate(&gray, 0.0, false);
+        Ok(gray)
+    }
+
+    fn upload(
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        image: &Pixbuf,
+    ) -> Result<ImageId> {
+        let size = Vec2D::new(image.width() as f32, image.height() as f32);
+        let pixels = pixels(image, Vec2D::zero(), size);
+        Ok(canvas.create_image(pixels.as_ref(), ImageFlags::empty())?)
+    }
+}
+
+impl Drawable for Spotlight {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        if self.editing {
+            let paint = Paint::color(Color::rgba(128, 128, 128, 255)).with_line_width(2.0);
+            for spot in &self.spots {
+                canvas.stroke_path(&spot.path(), &paint);
+            }
+            return Ok(());
+        }
+        // the spots show the image again, which isn't possible without it
+        let Some(background) = &self.background else {
+            return Ok(());
+        };
+        let (width, height) = (background.width() as f32, background.height() as f32);
+
+        if self.cached_image.borrow().is_none() {
+            let image = Self::upload(canvas, background)?;
+            self.cached_image.borrow_mut().replace(image);
+        }
+        if self.desaturate > 0.0 && self.cached_gray.borrow().is_none() {
+            let image = Self::upload(canvas, &Self::gray(background)?)?;
+            self.cached_gray.borrow_mut().replace(image);
+        }
+
+        canvas.save();
+        let mut path = Path::new();
+        path.rect(0.0, 0.0, width, height);
+        if let Some(gray) = *self.cached_gray.borrow() {
+            canvas.fill_path(
+                &path,
+                &Paint::image(gray, 0.0, 0.0, width, height, 0.0, self.desaturate),
+            );
+        }
+        if self.dim > 0.0 {
+            canvas.fill_path(&path, &Paint::color(Color::rgbaf(0.0, 0.0, 0.0, self.dim)));
+        }
+
+        // drawing the unchanged image into every spot leaves overlaps as clear as the rest
+        // this unwrap is safe because we placed it above
+        let image = self.cached_image.borrow().unwrap();
+        let paint = Paint::image(image, 0.0, 0.0, width, height, 0.0, 1.0);
+        for spot in &self.spots {
+            canvas.fill_path(&spot.path(), &paint);
+        }
+        canvas.restore();
+
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        let size = svg.background_size();
+        let id = svg.unique_id("spotlight");
+        let mut mask = Element::new("mask")
+            .attr("id", &id)
+            .attr("maskUnits", "userSpaceOnUse")
+            .attr("x", 0)
+            .attr("y", 0)
+            .attr("width", size.x)
+            .attr("height", size.y)
+            .child(
+                Element::new("rect")
+                    .attr("width", size.x)
+                    .attr("height", size.y)
+                    .attr("fill", "white"),
+            );
+        for spot in &self.spots {
+            mask = mask.child(spot.element().attr("fill", "black"));
+        }
+        svg.push(Element::new("defs").child(mask));
+
+        let mask_url = format!("url(#{id})");
+        if self.desaturate > 0.0
+            && let Some(((pos, size), area)) = svg.background_area(Vec2D::zero(), size)
+        {
+            svg.push(
+                SvgDocument::image_element(&Self::gray(&area)?, pos, size)?
+                    .attr("opacity", self.desaturate)
+                    .attr("mask", &mask_url),
+            );
+        }
+        if self.dim > 0.0 {
+            svg.push(
+                Element::new("rect")
+                    .attr("width", size.x)
+                    .attr("height", size.y)
+                    .attr("fill", "#000000")
+                    .attr("fill-opacity", self.dim)
+                    .attr("mask", &mask_url),
+            );
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        math::rect_from_points(
+            self.spots
+                .iter()
+                .flat_map(|spot| [spot.top_left, spot.top_left + spot.size]),
+        )
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        self.spots
+            .iter()
+            .any(|spot| math::rect_contains(spot.rect(), pos, tolerance))
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        for spot in &mut self.spots {
+            let top_left = math::rect_map_point(spot.top_left, from, to);
+            let bottom_right = math::rect_map_point(spot.top_left + spot.size, from, to);
+            *spot = Spot::new(spot.shape, top_left, bottom_right - top_left);
+        }
+    }
+
+    fn is_backdrop(&self) -> bool {
+        true
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Spotlight(self.clone()))
+    }
+}
+
+pub struct SpotlightTool {
+    spotlight: Option<Spotlight>,
+    origin: Vec2D,
+    drawables: SharedDrawables,
+    background: Pixbuf,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl SpotlightTool {
+    pub fn new(drawables: SharedDrawables, background: Pixbuf) -> Self {
+        Self {
+            spotlight: None,
+            origin: Vec2D::zero(),
+            drawables,
+            background,
+            input_enabled: false,
+            sender: None,
+        }
+    }
+
+    /// Add the spots to the topmost spotlight, or commit them as a new one if there is none.
+    fn merge_into_drawables(&mut self, spotlight: Spotlight) -> ToolUpdateResult {
+        let mut drawables = self.drawables.borrow_mut();
+        let existing =
+            drawables
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(index, d)| match d.to_saved() {
+                    Some(SavedDrawable::Spotlight(s)) => Some((index, s)),
+                    _ => None,
+                });
+
+        match existing {
+            Some((index, mut merged)) => {
+                merged.merge(spotlight);
+                let merged = merged.with_background(self.background.clone());
+                let previous = std::mem::replace(&mut drawables[index], Box::new(merged));
+                ToolUpdateResult::Edit(vec![(index, previous)])
+            }
+            None => ToolUpdateResult::Commit(spotlight.clone_box()),
+        }
+    }
+}
+
+impl Tool for SpotlightTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn active(&self) -> bool {
+        self.spotlight.is_some()
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button != MouseButton::Primary {
+            return ToolUpdateResult::Unmodified;
+        }
+        match event.type_ {
+            MouseEventType::BeginDrag => {
+                let shape = if event.modifier.contains(ModifierType::CONTROL_MASK) {
+                    SpotShape::Ellipse
+                } else {
+                    SpotShape::Rectangle
+                };
+                let mut spotlight =
+                    Spotlight::new(vec![Spot::new(shape, event.pos, Vec2D::zero())])
+                        .with_background(self.background.clone());
+                spotlight.editing = true;
+                self.origin = event.pos;
+                self.spotlight = Some(spotlight);
+
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::UpdateDrag | MouseEventType::EndDrag => {
+                let Some(spotlight) = &mut self.spotlight else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    if event.type_ == MouseEventType::EndDrag {
+                        self.spotlight = None;
+                        return ToolUpdateResult::Redraw;
+                    }
+                    return ToolUpdateResult::Unmodified;
+                }
+
+                let drag_box = DragBox::from_origin_delta(self.origin, event.pos, event.modifier);
+                if let Some(spot) = spotlight.spots.first_mut() {
+                    *spot = Spot::new(spot.shape, drag_box.top_left, drag_box.size);
+                }
+                if event.type_ == MouseEventType::UpdateDrag {
+                    return ToolUpdateResult::Redraw;
+                }
+
+                match self.spotlight.take() {
+                    Some(mut spotlight) => {
+                        spotlight.editing = false;
+                        self.merge_into_drawables(spotlight)
+                    }
+                    None => ToolUpdateResult::Unmodified,
+                }
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape && self.spotlight.is_some() {
+            self.spotlight = None;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.spotlight {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Spotlight
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
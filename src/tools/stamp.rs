@@ -0,0 +1,297 @@
+use std::{borrow::Cow, cell::RefCell, fmt};
+
+use anyhow::Result;
+use femtovg::{FontId, Paint};
+use relm4::{
+    Sender,
+    gtk::{
+        gdk::Key,
+        glib::{Variant, VariantTy, prelude::*, variant::FromVariant},
+    },
+};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    femtovg_area,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, SketchBoardInput},
+    style::Style,
+    svg::{Element, SvgDocument},
+};
+
+use super::{Drawable, DrawableClone, SavedDrawable, Tool, ToolUpdateResult, Tools};
+
+/// The symbols the stamp tool places, drawn from the fonts with the emoji font as last fallback.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StampSymbol {
+    #[default]
+    Check = 0,
+    Cross = 1,
+    Warning = 2,
+    Star = 3,
+    Heart = 4,
+    Question = 5,
+    Exclamation = 6,
+    Info = 7,
+    ThumbsUp = 8,
+    ThumbsDown = 9,
+}
+
+impl StampSymbol {
+    pub const ALL: [StampSymbol; 10] = [
+        Self::Check,
+        Self::Cross,
+        Self::Warning,
+        Self::Star,
+        Self::Heart,
+        Self::Question,
+        Self::Exclamation,
+        Self::Info,
+        Self::ThumbsUp,
+        Self::ThumbsDown,
+    ];
+
+    pub fn glyph(self) -> &'static str {
+        match self {
+            Self::Check => "✔",
+            Self::Cross => "✘",
+            Self::Warning => "⚠",
+            Self::Star => "★",
+            Self::Heart => "♥",
+            Self::Question => "❓",
+            Self::Exclamation => "❗",
+            Self::Info => "ℹ",
+            Self::ThumbsUp => "👍",
+            Self::ThumbsDown => "👎",
+        }
+    }
+}
+
+impl fmt::Display for StampSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Check => "Check",
+            Self::Cross => "Cross",
+            Self::Warning => "Warning",
+            Self::Star => "Star",
+            Self::Heart => "Heart",
+            Self::Question => "Question",
+            Self::Exclamation => "Exclamation",
+            Self::Info => "Info",
+            Self::ThumbsUp => "Thumbs Up",
+            Self::ThumbsDown => "Thumbs Down",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl StaticVariantType for StampSymbol {
+    fn static_variant_type() -> Cow<'static, VariantTy> {
+        Cow::Borrowed(VariantTy::UINT32)
+    }
+}
+
+impl ToVariant for StampSymbol {
+    fn to_variant(&self) -> Variant {
+        Variant::from(*self as u32)
+    }
+}
+
+impl FromVariant for StampSymbol {
+    fn from_variant(variant: &Variant) -> Option<Self> {
+        variant
+            .get::<u32>()
+            .and_then(|v| Self::ALL.into_iter().find(|symbol| *symbol as u32 == v))
+    }
+}
+
+/// A symbol centered at `pos`, sized like text.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Stamp {
+    pos: Vec2D,
+    symbol: StampSymbol,
+    style: Style,
+    /// Half the size of the glyph, measured when drawn.
+    #[serde(skip)]
+    half_size: RefCell<Option<Vec2D>>,
+}
+
+impl Stamp {
+    pub fn new(pos: Vec2D, symbol: StampSymbol, style: Style) -> Self {
+        Self {
+            pos,
+            symbol,
+            style,
+            half_size: RefCell::new(None),
+        }
+    }
+
+    fn size(&self) -> f32 {
+        self.style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32
+    }
+
+    fn half_size(&self) -> Vec2D {
+        // estimate the extent of stamps that never were drawn
+        self.half_size.borrow().unwrap_or_else(|| {
+            let size = self.size();
+            Vec2D::new(size * 0.5, size * 0.5)
+        })
+    }
+}
+
+impl Drawable for Stamp {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        font: FontId,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let mut paint = Paint::color(self.style.color.into());
+        if femtovg_area::font_stack().is_empty() {
+            paint.set_font(&[font]);
+        } else {
+            paint.set_font(femtovg_area::font_stack());
+        }
+        paint.set_font_size(self.size());
+        paint.set_text_align(femtovg::Align::Center);
+        paint.set_text_baseline(femtovg::Baseline::Middle);
+
+        let metrics = canvas.measure_text(self.pos.x, self.pos.y, self.symbol.glyph(), &paint)?;
+        *self.half_size.borrow_mut() = Some(Vec2D::new(metrics.width(), metrics.height()) * 0.5);
+
+        canvas.save();
+        canvas.fill_text(self.pos.x, self.pos.y, self.symbol.glyph(), &paint)?;
+        canvas.restore();
+        Ok(())
+    }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        svg.push(
+            Element::new("text")
+                .attr("x", self.pos.x)
+                .attr("y", self.pos.y)
+                .attr("text-anchor", "middle")
+                .attr("dominant-baseline", "central")
+                .font(self.size())
+                .fill(self.style.color)
+                .text(self.symbol.glyph()),
+        );
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        let half_size = self.half_size();
+        Some((self.pos - half_size, half_size * 2.0))
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        // the stamp size follows the style, so only its center is moved
+        self.pos = math::rect_map_point(self.pos, from, to);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Stamp(self.clone()))
+    }
+}
+
+#[derive(Default)]
+pub struct StampTool {
+    stamp: Option<Stamp>,
+    origin: Vec2D,
+    symbol: StampSymbol,
+    style: Style,
+    input_enabled: bool,
+    sender: Option<Sender<SketchBoardInput>>,
+}
+
+impl StampTool {
+    pub fn set_symbol(&mut self, symbol: StampSymbol) {
+        self.symbol = symbol;
+    }
+}
+
+impl Tool for StampTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn active(&self) -> bool {
+        self.stamp.is_some()
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button != MouseButton::Primary {
+            return ToolUpdateResult::Unmodified;
+        }
+        match event.type_ {
+            MouseEventType::Click => {
+                self.origin = event.pos;
+                self.stamp = Some(Stamp::new(event.pos, self.symbol, self.style));
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::UpdateDrag => {
+                if let Some(stamp) = &mut self.stamp {
+                    stamp.pos = self.origin + event.pos;
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+            MouseEventType::Release => match self.stamp.take() {
+                Some(stamp) => ToolUpdateResult::Commit(stamp.clone_box()),
+                None => ToolUpdateResult::Unmodified,
+            },
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape && self.stamp.is_some() {
+            self.stamp = None;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.stamp = None;
+        ToolUpdateResult::Unmodified
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        ToolUpdateResult::Unmodified
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.stamp {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Stamp
+    }
+
+    fn set_sender(&mut self, sender: Sender<SketchBoardInput>) {
+        self.sender = Some(sender);
+    }
+}
@@ -6,20 +6,22 @@ use relm4::gtk::{
     TextBuffer,
     gdk::{Key, ModifierType, Rectangle},
 };
-use std::{borrow::Cow, ops::Range};
+use std::{borrow::Cow, f32::consts::FRAC_PI_2, ops::Range};
 
 use relm4::gtk::prelude::*;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
     femtovg_area,
     ime::preedit::{Preedit, UnderlineKind},
-    math::Vec2D,
+    math::{self, Vec2D},
     sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, TextEventMsg},
     style::Style,
+    svg::{Element, SvgDocument},
 };
 
-use super::{Drawable, DrawableClone, InputContext, Tool, ToolUpdateResult, Tools};
+use super::{Drawable, DrawableClone, InputContext, SavedDrawable, Tool, ToolUpdateResult, Tools};
 use crate::sketch_board::SketchBoardInput;
 use relm4::Sender;
 use relm4::gtk::gdk::DisplayManager;
@@ -42,7 +44,8 @@ enum DecorationKind {
 }
 
 /// Decorative text effect, cycled through with the Alt key.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 enum TextEffect {
     #[default]
     None,
@@ -99,10 +102,133 @@ pub struct Text {
     rect: RefCell<Rectangle>,
     glyphs: RefCell<Vec<Vec<Rectangle>>>,
     line_ranges: RefCell<Vec<Range<usize>>>,
+    line_height: RefCell<f32>,
     cursor_visible: RefCell<bool>,
     draw_rect: RefCell<bool>,
     effect: TextEffect,
     font_ids: Vec<FontId>,
+    /// The point the tail of the callout box points at, plain text has no box.
+    callout: Option<Vec2D>,
+}
+
+/// The content of a committed text as stored in project files.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SavedText {
+    pos: Vec2D,
+    text: String,
+    style: Style,
+    #[serde(default)]
+    effect: TextEffect,
+    #[serde(default)]
+    callout: Option<Vec2D>,
+}
+
+impl SavedText {
+    pub fn new(pos: Vec2D, text: String, style: Style) -> Self {
+        Self {
+            pos,
+            text,
+            style,
+            effect: TextEffect::default(),
+            callout: None,
+        }
+    }
+
+    /// Put the text into a callout box with its tail pointing at the anchor.
+    pub fn with_callout(mut self, anchor: Vec2D) -> Self {
+        self.callout = Some(anchor);
+        self
+    }
+}
+
+impl From<SavedText> for Text {
+    fn from(saved: SavedText) -> Self {
+        let mut text = Text::new(saved.pos, saved.style, None);
+        text.text_buffer.set_text(&saved.text);
+        text.editing = false;
+        text.effect = saved.effect;
+        text.callout = saved.callout;
+        *text.draw_rect.borrow_mut() = false;
+        text
+    }
+}
+
+/// The number of line segments approximating each rounded corner of a callout box.
+const CALLOUT_CORNER_STEPS: usize = 6;
+
+/// The outline of a rounded box (pos, size) with a tail pointing at the anchor. The tail
+/// leaves the box on the edge facing the anchor, there is none if the anchor lies inside.
+fn callout_outline(
+    rect: (Vec2D, Vec2D),
+    radius: f32,
+    anchor: Vec2D,
+    tail_width: f32,
+) -> Vec<Vec2D> {
+    let (pos, size) = rect;
+    let radius = radius.min(size.x / 2.0).min(size.y / 2.0).max(0.0);
+    let (x0, y0) = (pos.x, pos.y);
+    let (x1, y1) = (pos.x + size.x, pos.y + size.y);
+
+    // the straight part of each edge clockwise from the top, and the center of the corner after it
+    let edges = [
+        (
+            Vec2D::new(x0 + radius, y0),
+            Vec2D::new(x1 - radius, y0),
+            Vec2D::new(x1 - radius, y0 + radius),
+        ),
+        (
+            Vec2D::new(x1, y0 + radius),
+            Vec2D::new(x1, y1 - radius),
+            Vec2D::new(x1 - radius, y1 - radius),
+        ),
+        (
+            Vec2D::new(x1 - radius, y1),
+            Vec2D::new(x0 + radius, y1),
+            Vec2D::new(x0 + radius, y1 - radius),
+        ),
+        (
+            Vec2D::new(x0, y1 - radius),
+            Vec2D::new(x0, y0 + radius),
+            Vec2D::new(x0 + radius, y0 + radius),
+        ),
+    ];
+
+    // compare the direction to the anchor relative to the box proportions
+    let offset = anchor - (pos + size * 0.5);
+    let tail_edge = if math::rect_contains(rect, anchor, 0.0) {
+        None
+    } else if offset.x.abs() * size.y > offset.y.abs() * size.x {
+        Some(if offset.x > 0.0 { 1 } else { 3 })
+    } else {
+        Some(if offset.y > 0.0 { 2 } else { 0 })
+    };
+
+    let mut points = Vec::with_capacity(4 * (CALLOUT_CORNER_STEPS + 2) + 3);
+    for (i, (start, end, corner)) in edges.into_iter().enumerate() {
+        points.push(start);
+
+        let length = start.distance_to(&end);
+        let half_width = (tail_width / 2.0).min(length / 2.0);
+        if tail_edge == Some(i) && half_width > 0.0 {
+            // center the base of the tail on the anchor as far as the edge allows
+            let direction = (end - start) * (1.0 / length);
+            let along = anchor - start;
+            let center = (along.x * direction.x + along.y * direction.y)
+                .clamp(half_width, length - half_width);
+            points.push(start + direction * (center - half_width));
+            points.push(anchor);
+            points.push(start + direction * (center + half_width));
+        }
+
+        points.push(end);
+        let start_angle = (i as f32 - 1.0) * FRAC_PI_2;
+        for step in 1..=CALLOUT_CORNER_STEPS {
+            let angle = start_angle + FRAC_PI_2 * step as f32 / CALLOUT_CORNER_STEPS as f32;
+            points.push(corner + Vec2D::new(angle.cos(), angle.sin()) * radius);
+        }
+    }
+    points
 }
 
 struct DisplayContent<'a> {
@@ -143,10 +269,12 @@ impl Text {
             rect: RefCell::new(Rectangle::new(0, 0, 0, 0)),
             glyphs: RefCell::new(Vec::new()),
             line_ranges: RefCell::new(Vec::new()),
+            line_height: RefCell::new(0.0),
             cursor_visible: RefCell::new(true),
             draw_rect: RefCell::new(true),
             effect: TextEffect::default(),
             font_ids: femtovg_area::font_stack().to_vec(),
+            callout: None,
         }
     }
 
@@ -221,6 +349,78 @@ impl Text {
             false,
         )
     }
+
+    /// The rectangle (pos, size) covered by the glyphs as of the last draw.
+    fn text_rect(&self) -> Option<(Vec2D, Vec2D)> {
+        let rect = self.rect.borrow();
+        if rect.width() <= 0 || rect.height() <= 0 {
+            return None;
+        }
+        Some((
+            Vec2D::new(rect.x() as f32, rect.y() as f32),
+            Vec2D::new(rect.width() as f32, rect.height() as f32),
+        ))
+    }
+
+    /// The callout box (pos, size) around the glyphs, padded relative to the text size.
+    fn callout_box(&self, text_rect: (Vec2D, Vec2D)) -> (Vec2D, Vec2D) {
+        let text_size = self
+            .style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32;
+        let padding = Vec2D::new(text_size * 0.4, text_size * 0.4);
+        (text_rect.0 - padding, text_rect.1 + padding * 2.0)
+    }
+
+    /// The outline of the callout box and its tail, None for plain text.
+    fn callout_outline(&self, text_rect: (Vec2D, Vec2D)) -> Option<Vec<Vec2D>> {
+        let anchor = self.callout?;
+        let text_size = self
+            .style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32;
+        Some(callout_outline(
+            self.callout_box(text_rect),
+            APP_CONFIG.read().corner_roundness(),
+            anchor,
+            text_size,
+        ))
+    }
+
+    /// The text color, which has to stand out against a filled callout box.
+    fn text_color(&self) -> crate::style::Color {
+        if self.callout.is_some() && self.style.fill {
+            self.style.color.contrast()
+        } else {
+            self.style.color
+        }
+    }
+
+    fn draw_callout(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        text_rect: (Vec2D, Vec2D),
+    ) {
+        let Some(outline) = self.callout_outline(text_rect) else {
+            return;
+        };
+
+        let mut path = Path::new();
+        for (i, p) in outline.iter().enumerate() {
+            if i == 0 {
+                path.move_to(p.x, p.y);
+            } else {
+                path.line_to(p.x, p.y);
+            }
+        }
+        path.close();
+
+        if self.style.fill {
+            canvas.fill_path(&path, &self.style.into());
+        } else {
+            canvas.stroke_path(&path, &self.style.into());
+        }
+    }
 }
 
 impl Drawable for Text {
@@ -236,12 +436,14 @@ impl Drawable for Text {
         let text = display.text.as_ref();
 
         let mut base_paint: Paint = self.style.into();
+        base_paint.set_color(self.text_color().into());
         base_paint.set_font(&[font]);
 
-        if self.font_ids.is_empty() {
-            base_paint.set_font(&[font]);
-        } else {
+        if !self.font_ids.is_empty() {
             base_paint.set_font(&self.font_ids);
+        } else if !femtovg_area::font_stack().is_empty() {
+            // texts restored from a project are created before the fonts are loaded
+            base_paint.set_font(femtovg_area::font_stack());
         }
 
         let transform = canvas.transform();
@@ -273,6 +475,7 @@ impl Drawable for Text {
 
         let cursor_top_offset = -line_height;
         line_height *= 1.2; // increase line height a bit
+        self.line_height.replace(line_height);
 
         let mut line_layouts: Vec<LineLayout> = Vec::with_capacity(lines.len());
         let mut baseline = self.pos.y;
@@ -387,6 +590,19 @@ impl Drawable for Text {
             rect.set_y(tl.y as i32);
         }
 
+        // the box goes below the text, an empty one still gets room for the cursor
+        if self.callout.is_some() {
+            let text_rect = if wh.x > 0.0 && wh.y > 0.0 {
+                (tl, wh)
+            } else {
+                (
+                    Vec2D::new(self.pos.x, self.pos.y + cursor_metrics.top_offset),
+                    Vec2D::new(1.0, cursor_metrics.line_height),
+                )
+            };
+            self.draw_callout(canvas, text_rect);
+        }
+
         // draw bounding rectangle
         if *self.draw_rect.borrow() {
             let mut rect_paint = Path::new();
@@ -403,7 +619,7 @@ impl Drawable for Text {
         // an offset duplicate.
         let decoration_paint = self
             .effect
-            .decoration(self.style.color, base_paint.line_width())
+            .decoration(self.text_color(), base_paint.line_width())
             .map(|dec| {
                 let mut paint = base_paint.clone();
                 paint.set_color(dec.color.into());
@@ -460,6 +676,129 @@ impl Drawable for Text {
 
         Ok(())
     }
+
+    fn draw_svg(&self, svg: &mut SvgDocument) -> Result<()> {
+        let gtext = self.get_text();
+        let text = gtext.as_str();
+        let text_size = self
+            .style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32;
+
+        // the line breaks are calculated when drawn, fall back to the explicit ones
+        let mut lines: Vec<&str> = self
+            .line_ranges
+            .borrow()
+            .iter()
+            .filter_map(|range| text.get(range.clone()))
+            .collect();
+        let mut line_height = *self.line_height.borrow();
+        if lines.is_empty() || line_height <= 0.0 {
+            lines = text.lines().collect();
+            line_height = text_size * 1.2;
+        }
+
+        let line_width = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor);
+        let decoration = self.effect.decoration(self.text_color(), line_width);
+
+        if let Some(outline) = self.text_rect().and_then(|rect| self.callout_outline(rect)) {
+            svg.push(Element::path(&[outline], true).paint(&self.style));
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                continue;
+            }
+            let baseline = self.pos.y + i as f32 * line_height;
+            let element = |offset: Vec2D| {
+                Element::new("text")
+                    .attr("x", self.pos.x + offset.x)
+                    .attr("y", baseline + offset.y)
+                    .attr("xml:space", "preserve")
+                    .font(text_size)
+            };
+
+            let fill = element(Vec2D::zero()).fill(self.text_color()).text(line);
+            match decoration {
+                Some(Decoration {
+                    color,
+                    kind: DecorationKind::Shadow { offset },
+                }) => {
+                    svg.push(element(offset).fill(color).text(line));
+                    svg.push(fill);
+                }
+                Some(Decoration {
+                    color,
+                    kind: DecorationKind::Outline { width },
+                }) => svg.push(
+                    fill.stroke_color(color)
+                        .attr("stroke-width", width)
+                        .attr("paint-order", "stroke"),
+                ),
+                None => svg.push(fill),
+            }
+        }
+        Ok(())
+    }
+
+    fn bounds(&self) -> Option<(Vec2D, Vec2D)> {
+        let text_rect = self.text_rect()?;
+        match self.callout_outline(text_rect) {
+            Some(outline) => math::rect_from_points(outline),
+            None => Some(text_rect),
+        }
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let Some(text_rect) = self.text_rect() else {
+            return false;
+        };
+        match self.callout_outline(text_rect) {
+            // the box and the tail, not the empty space around the tail
+            Some(outline) => {
+                math::rect_contains(self.callout_box(text_rect), pos, tolerance)
+                    || math::distance_to_polyline(pos, &outline) <= tolerance
+            }
+            None => math::rect_contains(text_rect, pos, tolerance),
+        }
+    }
+
+    fn transform(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        // the text size follows the style, so the text is only moved
+        let pos = math::rect_map_point(self.pos, from, to);
+        let offset = pos - self.pos;
+        self.pos = pos;
+        if let Some(anchor) = &mut self.callout {
+            *anchor = math::rect_map_point(*anchor, from, to);
+        }
+
+        // keep the bounding rectangle in sync until the next draw recalculates it
+        let rect = self.rect.get_mut();
+        rect.set_x(rect.x() + offset.x.round() as i32);
+        rect.set_y(rect.y() + offset.y.round() as i32);
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn to_saved(&self) -> Option<SavedDrawable> {
+        Some(SavedDrawable::Text(SavedText {
+            pos: self.pos,
+            text: self.get_text().to_string(),
+            style: self.style,
+            effect: self.effect,
+            callout: self.callout,
+        }))
+    }
 }
 
 impl Text {
@@ -749,6 +1088,7 @@ impl Text {
         let caret_height = cursor.line_height;
 
         let mut caret_paint: Paint = self.style.into();
+        caret_paint.set_color(self.text_color().into());
         caret_paint.set_font(&[font]);
 
         let extra_height = caret_height * 0.1;
@@ -793,6 +1133,18 @@ impl Text {
     }
 }
 
+/// The part of a callout moved by dragging.
+#[derive(Clone, Copy)]
+enum CalloutDrag {
+    /// The tip of the tail, the box stays in place.
+    Anchor,
+    /// The box of a new callout, with the tail pointing at where the drag started.
+    Placement,
+}
+
+/// The distance from the tail tip within which it can be grabbed.
+const CALLOUT_ANCHOR_GRAB_DISTANCE: f32 = 10.0;
+
 #[derive(Default)]
 pub struct TextTool {
     text: Option<Text>,
@@ -803,11 +1155,17 @@ pub struct TextTool {
     drag_start_pos: Vec2D,
     dragged: Rc<RefCell<bool>>,
     alt_tap: bool,
+    callout: bool,
+    callout_drag: Option<CalloutDrag>,
 }
 
 impl Tool for TextTool {
     fn get_tool_type(&self) -> super::Tools {
-        Tools::Text
+        if self.callout {
+            Tools::Callout
+        } else {
+            Tools::Text
+        }
     }
 
     fn input_enabled(&self) -> bool {
@@ -1198,6 +1556,10 @@ impl Tool for TextTool {
                 match event.button {
                     MouseButton::Primary => {
                         let pos = event.pos;
+                        if self.grabs_anchor(pos) {
+                            // keep editing, the following drag moves the tail
+                            return ToolUpdateResult::StopPropagation;
+                        }
                         if let Some(t) = &mut self.text {
                             let rect = t.rect.borrow();
                             if rect.contains_point(pos.x as i32, pos.y as i32) {
@@ -1257,6 +1619,14 @@ impl Tool for TextTool {
 
                                 return ToolUpdateResult::RedrawAndStopPropagation;
                             }
+
+                            // the padding of a callout box belongs to the text as well
+                            if let Some(text_rect) = t.text_rect()
+                                && t.callout.is_some()
+                                && math::rect_contains(t.callout_box(text_rect), pos, 0.0)
+                            {
+                                return ToolUpdateResult::StopPropagation;
+                            }
                         }
 
                         // create commit message if necessary
@@ -1281,7 +1651,17 @@ impl Tool for TextTool {
                         };
 
                         // create a new Text
-                        self.text = Some(Text::new(event.pos, self.style, self.im_context.clone()));
+                        let mut text = Text::new(event.pos, self.style, self.im_context.clone());
+                        if self.callout {
+                            // point down and to the left until the tail is dragged elsewhere
+                            let text_size = self
+                                .style
+                                .size
+                                .to_text_size(self.style.annotation_size_factor)
+                                as f32;
+                            text.callout = Some(event.pos + Vec2D::new(-1.5, 2.5) * text_size);
+                        }
+                        self.text = Some(text);
 
                         self.set_input_enabled(true);
 
@@ -1332,6 +1712,22 @@ impl Tool for TextTool {
             },
             MouseEventType::BeginDrag => {
                 self.drag_start_pos = event.pos;
+                if event.button == MouseButton::Primary
+                    && let Some(t) = &self.text
+                    && t.callout.is_some()
+                {
+                    // an empty callout was just created by the click starting this drag
+                    self.callout_drag = if self.grabs_anchor(event.pos) {
+                        Some(CalloutDrag::Anchor)
+                    } else if t.get_text().is_empty() {
+                        Some(CalloutDrag::Placement)
+                    } else {
+                        None
+                    };
+                    if self.callout_drag.is_some() {
+                        return ToolUpdateResult::StopPropagation;
+                    }
+                }
                 if let Some(t) = &mut self.text {
                     let rect = t.rect.borrow();
                     if rect.contains_point(event.pos.x as i32, event.pos.y as i32) {
@@ -1342,6 +1738,9 @@ impl Tool for TextTool {
             }
             MouseEventType::UpdateDrag => {
                 self.dragged = Rc::new(RefCell::new(true));
+                if self.drag_callout(event.pos) {
+                    return ToolUpdateResult::RedrawAndStopPropagation;
+                }
                 if event.button == MouseButton::Primary {
                     let global_pos = self.drag_start_pos + event.pos;
                     if let Some(t) = &mut self.text {
@@ -1392,6 +1791,10 @@ impl Tool for TextTool {
             }
             MouseEventType::EndDrag => {
                 self.dragged = Rc::new(RefCell::new(false));
+                if self.drag_callout(event.pos) {
+                    self.callout_drag = None;
+                    return ToolUpdateResult::RedrawAndStopPropagation;
+                }
                 if let Some(t) = &mut self.text {
                     let rect = t.rect.borrow();
                     if rect.contains_point(event.pos.x as i32, event.pos.y as i32) {
@@ -1489,6 +1892,40 @@ enum Action {
 }
 
 impl TextTool {
+    /// A text tool which puts its texts into callout boxes.
+    pub fn callout() -> Self {
+        Self {
+            callout: true,
+            ..Default::default()
+        }
+    }
+
+    fn grabs_anchor(&self, pos: Vec2D) -> bool {
+        self.text
+            .as_ref()
+            .and_then(|t| t.callout)
+            .is_some_and(|anchor| anchor.distance_to(&pos) <= CALLOUT_ANCHOR_GRAB_DISTANCE)
+    }
+
+    /// Apply a drag by `delta` to the callout being dragged, returns false if there is none.
+    fn drag_callout(&mut self, delta: Vec2D) -> bool {
+        let (Some(drag), Some(t)) = (self.callout_drag, &mut self.text) else {
+            return false;
+        };
+        let pos = self.drag_start_pos + delta;
+        match drag {
+            CalloutDrag::Anchor => t.callout = Some(pos),
+            CalloutDrag::Placement => {
+                if delta.is_zero() {
+                    return true;
+                }
+                t.pos = pos;
+                t.callout = Some(self.drag_start_pos);
+            }
+        }
+        true
+    }
+
     fn handle_text_buffer_action(
         text: &mut Text,
         action: Action,
@@ -1847,6 +2284,9 @@ impl TextTool {
                     ToolUpdateResult::StopPropagation
                 } else {
                     text.pos += offset;
+                    if let Some(anchor) = &mut text.callout {
+                        *anchor += offset;
+                    }
                     ToolUpdateResult::RedrawAndStopPropagation
                 }
             }
@@ -1,5 +1,5 @@
-use crate::tools::{GroupableTool, Tools};
-use crate::ui::toolbars::ToolsAction;
+use crate::tools::{BlurMode, GroupableTool, Tools};
+use crate::ui::toolbars::{BlurModeAction, ToolsAction};
 use relm4::actions::ActionablePlus;
 use relm4::factory::{DynamicIndex, FactoryComponent};
 use relm4::gtk::prelude::{BoxExt, ButtonExt, GestureExt, PopoverExt, WidgetExt};
@@ -9,6 +9,7 @@ use relm4::{FactorySender, RelmWidgetExt, view};
 pub struct ToolGroupInit {
     pub group: Vec<GroupableTool>,
     pub initial_tool: Tools,
+    pub initial_blur_mode: BlurMode,
 }
 
 pub struct ToolGroupWidgets {
@@ -28,6 +29,7 @@ pub struct ToolGroupButton {
 pub enum ToolGroupButtonInput {
     OpenPopover,
     SelectedToolChanged(Tools),
+    SelectedBlurModeChanged(BlurMode),
     SetEditing(bool),
 }
 
@@ -73,13 +75,22 @@ impl FactoryComponent for ToolGroupButton {
     type Index = DynamicIndex;
 
     fn init_model(init: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
-        let mut pos: usize = 0;
-        let mut is_active = false;
-
-        if let Some(p) = init.group.iter().position(|t| t.tool == init.initial_tool) {
-            pos = p;
-            is_active = true;
-        }
+        let is_active = init.group.iter().any(|t| t.tool == init.initial_tool);
+        // the variants of the blur tool start at the primary mode
+        let pos = init
+            .group
+            .iter()
+            .position(|t| {
+                t.tool == init.initial_tool
+                    && t.blur_mode
+                        .is_none_or(|mode| mode == init.initial_blur_mode)
+            })
+            .or_else(|| {
+                init.group
+                    .iter()
+                    .position(|t| t.blur_mode == Some(init.initial_blur_mode))
+            })
+            .unwrap_or(0);
 
         Self {
             group: init.group,
@@ -136,7 +147,10 @@ impl FactoryComponent for ToolGroupButton {
                 let button = ToggleButton::builder().focusable(false).build();
                 let inner_box = relm4::gtk::Box::new(relm4::gtk::Orientation::Horizontal, 2);
                 let icon = relm4::gtk::Image::from_icon_name(&tool.icon_name);
-                let label = relm4::gtk::Label::new(Some(&format!("{}", tool.tool)));
+                let label = match tool.blur_mode {
+                    Some(mode) => relm4::gtk::Label::new(Some(&format!("{}", mode))),
+                    None => relm4::gtk::Label::new(Some(&format!("{}", tool.tool))),
+                };
                 inner_box.append(&icon);
                 inner_box.append(&label);
                 button.set_child(Some(&inner_box));
@@ -145,7 +159,10 @@ impl FactoryComponent for ToolGroupButton {
                 }
                 let popover = self.popover.clone();
                 button.connect_clicked(move |_| popover.popdown());
-                ActionablePlus::set_action::<ToolsAction>(&button, tool.tool);
+                match tool.blur_mode {
+                    Some(mode) => ActionablePlus::set_action::<BlurModeAction>(&button, mode),
+                    None => ActionablePlus::set_action::<ToolsAction>(&button, tool.tool),
+                }
                 rows.append(&button);
             }
             self.popover.set_child(Some(&rows));
@@ -167,7 +184,10 @@ impl FactoryComponent for ToolGroupButton {
                 }
             }
             ToolGroupButtonInput::SelectedToolChanged(tools) => {
-                if let Some(i) = self.group.iter().position(|gt| gt.tool == tools) {
+                if self.group[self.current].tool == tools {
+                    // keep the selected variant of the tool
+                    self.is_active = true;
+                } else if let Some(i) = self.group.iter().position(|gt| gt.tool == tools) {
                     self.is_active = true;
                     self.current = i;
                 } else {
@@ -175,6 +195,11 @@ impl FactoryComponent for ToolGroupButton {
                     self.editing = false;
                 }
             }
+            ToolGroupButtonInput::SelectedBlurModeChanged(mode) => {
+                if let Some(i) = self.group.iter().position(|gt| gt.blur_mode == Some(mode)) {
+                    self.current = i;
+                }
+            }
             ToolGroupButtonInput::SetEditing(editing) => {
                 if self.is_active {
                     self.editing = editing;
@@ -3,8 +3,8 @@ use std::borrow::Cow;
 use crate::{
     configuration::{APP_CONFIG, Action},
     keybindings::{ShortcutCommand, ShortcutRegistry},
-    style::{Color, Size},
-    tools::Tools,
+    style::{ArrowHead, Color, Size, StrokePattern},
+    tools::{BlurMode, StampSymbol, Tools},
 };
 
 use crate::tools::GroupableTool;
@@ -18,7 +18,7 @@ use relm4::gtk::{
     },
 };
 use relm4::{
-    actions::{ActionablePlus, RelmAction, RelmActionGroup},
+    actions::{ActionName, ActionablePlus, RelmAction, RelmActionGroup},
     gtk::{Align, ColorChooserDialog, ResponseType, Window, gdk::RGBA, prelude::*},
     prelude::*,
 };
@@ -27,6 +27,7 @@ pub struct ToolsToolbar {
     visible: bool,
     tool_buttons: FactoryVecDeque<ToolGroupButton>,
     tool_action: SimpleAction,
+    blur_mode_action: SimpleAction,
 }
 
 pub struct StyleToolbar {
@@ -37,6 +38,10 @@ pub struct StyleToolbar {
     size_spin_button: gtk::SpinButton,
     fill_enabled: bool,
     round_caps_enabled: bool,
+    stroke_pattern: StrokePattern,
+    arrow_start: ArrowHead,
+    arrow_end: ArrowHead,
+    stamp: StampSymbol,
     visible: bool,
     output_dimensions: String,
     editing: bool,
@@ -46,6 +51,7 @@ pub struct StyleToolbar {
 pub enum ToolbarEvent {
     FocusCanvas,
     ToolSelected(Tools),
+    BlurModeSelected(BlurMode),
     ColorSelected(Color),
     SetFill(bool),
     SizeSelected(Size),
@@ -55,9 +61,14 @@ pub enum ToolbarEvent {
     CopyClipboard,
     ToggleFill,
     ToggleRoundCaps,
+    CycleStrokePattern,
+    ArrowStartSelected(ArrowHead),
+    ArrowEndSelected(ArrowHead),
+    StampSelected(StampSymbol),
     AnnotationSizeFactorChanged(f32),
     ClearAll,
     SaveFileAs,
+    InsertImage,
     ScaleFitToWindow,
     ScaleOriginal,
     ToolCommit,
@@ -69,6 +80,7 @@ pub enum ToolsToolbarInput {
     SetVisibility(bool),
     ToggleVisibility,
     SwitchSelectedTool(Tools),
+    SwitchBlurMode(BlurMode),
     SetToolEditing(bool),
 }
 
@@ -78,6 +90,10 @@ pub enum StyleToolbarInput {
     SetColor(Color),
     SetFill(bool),
     SetRoundCaps(bool),
+    SetStrokePattern(StrokePattern),
+    ArrowStartSelected(ArrowHead),
+    ArrowEndSelected(ArrowHead),
+    StampSelected(StampSymbol),
     SetSize(Size),
     ShowColorDialog,
     ColorDialogFinished(Option<Color>),
@@ -94,6 +110,49 @@ fn create_icon_pixbuf(color: Color) -> Pixbuf {
     pixbuf
 }
 
+/// A popover listing the arrow heads, selecting one through the action `A`.
+fn create_arrow_head_popover<A: ActionName<Target = ArrowHead>>() -> gtk::Popover {
+    let popover = gtk::Popover::new();
+    let rows = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    for head in ArrowHead::ALL {
+        let button = gtk::ToggleButton::builder().focusable(false).build();
+        let inner_box = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+        inner_box.append(&gtk::Image::from_icon_name(head.icon_name()));
+        inner_box.append(&gtk::Label::new(Some(&head.to_string())));
+        button.set_child(Some(&inner_box));
+        let popover_tmp = popover.clone();
+        button.connect_clicked(move |_| popover_tmp.popdown());
+        ActionablePlus::set_action::<A>(&button, head);
+        rows.append(&button);
+    }
+    popover.set_child(Some(&rows));
+    popover.set_position(gtk::PositionType::Top);
+    popover
+}
+
+/// A popover listing the symbols of the stamp tool.
+fn create_stamp_popover() -> gtk::Popover {
+    let popover = gtk::Popover::new();
+    let grid = gtk::Grid::builder()
+        .row_spacing(2)
+        .column_spacing(2)
+        .build();
+    for (index, symbol) in StampSymbol::ALL.into_iter().enumerate() {
+        let button = gtk::ToggleButton::builder()
+            .focusable(false)
+            .label(symbol.glyph())
+            .tooltip_text(symbol.to_string())
+            .build();
+        let popover_tmp = popover.clone();
+        button.connect_clicked(move |_| popover_tmp.popdown());
+        ActionablePlus::set_action::<StampAction>(&button, symbol);
+        grid.attach(&button, index as i32 % 5, index as i32 / 5, 1, 1);
+    }
+    popover.set_child(Some(&grid));
+    popover.set_position(gtk::PositionType::Top);
+    popover
+}
+
 fn create_icon(color: Color) -> gtk::Image {
     gtk::Image::from_pixbuf(Some(&create_icon_pixbuf(color)))
 }
@@ -177,6 +236,14 @@ impl SimpleComponent for ToolsToolbar {
                 set_spacing: 2,
             },
             gtk::Separator {},
+            #[name(insert_image_button)]
+            gtk::Button {
+                set_focusable: false,
+                set_hexpand: false,
+                set_icon_name: "image-regular",
+                connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::InsertImage);},
+            },
+            gtk::Separator {},
             #[name(copy_to_clipboard_button)]
             gtk::Button {
                 set_focusable: false,
@@ -214,6 +281,11 @@ impl SimpleComponent for ToolsToolbar {
                 self.tool_buttons
                     .broadcast(ToolGroupButtonInput::SelectedToolChanged(tool));
             }
+            ToolsToolbarInput::SwitchBlurMode(mode) => {
+                self.blur_mode_action.change_state(&mode.to_variant());
+                self.tool_buttons
+                    .broadcast(ToolGroupButtonInput::SelectedBlurModeChanged(mode));
+            }
             ToolsToolbarInput::SetToolEditing(editing) => {
                 self.tool_buttons
                     .broadcast(ToolGroupButtonInput::SetEditing(editing));
@@ -242,72 +314,165 @@ impl SimpleComponent for ToolsToolbar {
             },
         );
 
+        let sender_tmp: ComponentSender<ToolsToolbar> = sender.clone();
+        let blur_mode_action: RelmAction<BlurModeAction> =
+            RelmAction::new_stateful_with_target_value(
+                &APP_CONFIG.read().primary_blur_mode(),
+                move |_, state, value| {
+                    *state = value;
+                    // selecting a mode also selects the blur tool
+                    sender_tmp
+                        .output_sender()
+                        .emit(ToolbarEvent::BlurModeSelected(*state));
+                    sender_tmp
+                        .output_sender()
+                        .emit(ToolbarEvent::ToolSelected(Tools::Blur));
+                    sender_tmp
+                        .input_sender()
+                        .emit(ToolsToolbarInput::SwitchSelectedTool(Tools::Blur));
+                    sender_tmp
+                        .input_sender()
+                        .emit(ToolsToolbarInput::SwitchBlurMode(*state));
+                },
+            );
+
         let shortcut_registry = ShortcutRegistry::from_config();
 
         let tools = [
             vec![GroupableTool {
                 tool: Tools::Pointer,
+                blur_mode: None,
                 icon_name: "cursor-regular".into(),
                 tooltip: None,
             }],
             vec![GroupableTool {
                 tool: Tools::Crop,
+                blur_mode: None,
                 icon_name: "crop-filled".into(),
                 tooltip: None,
             }],
-            vec![GroupableTool {
-                tool: Tools::Brush,
-                icon_name: "pen-regular".into(),
-                tooltip: None,
-            }],
+            vec![
+                GroupableTool {
+                    tool: Tools::Brush,
+                    blur_mode: None,
+                    icon_name: "pen-regular".into(),
+                    tooltip: None,
+                },
+                GroupableTool {
+                    tool: Tools::Eraser,
+                    blur_mode: None,
+                    icon_name: "eraser".into(),
+                    tooltip: None,
+                },
+            ],
             vec![
                 GroupableTool {
                     tool: Tools::Line,
+                    blur_mode: None,
                     icon_name: "minus-large".into(),
                     tooltip: None,
                 },
                 GroupableTool {
                     tool: Tools::Arrow,
+                    blur_mode: None,
                     icon_name: "arrow-up-right-filled".into(),
                     tooltip: None,
                 },
+                GroupableTool {
+                    tool: Tools::Measure,
+                    blur_mode: None,
+                    icon_name: "ruler-regular".into(),
+                    tooltip: None,
+                },
             ],
             vec![
                 GroupableTool {
                     tool: Tools::Rectangle,
+                    blur_mode: None,
                     icon_name: "checkbox-unchecked-regular".into(),
                     tooltip: None,
                 },
                 GroupableTool {
                     tool: Tools::Ellipse,
+                    blur_mode: None,
                     icon_name: "circle-regular".into(),
                     tooltip: None,
                 },
             ],
+            vec![
+                GroupableTool {
+                    tool: Tools::Text,
+                    blur_mode: None,
+                    icon_name: "text-case-title-regular".into(),
+                    tooltip: None,
+                },
+                GroupableTool {
+                    tool: Tools::Callout,
+                    blur_mode: None,
+                    icon_name: "comment-regular".into(),
+                    tooltip: None,
+                },
+            ],
+            vec![
+                GroupableTool {
+                    tool: Tools::Marker,
+                    blur_mode: None,
+                    icon_name: "number-circle-1-regular".into(),
+                    tooltip: None,
+                },
+                GroupableTool {
+                    tool: Tools::Stamp,
+                    blur_mode: None,
+                    icon_name: "sticker-regular".into(),
+                    tooltip: None,
+                },
+            ],
+            BlurMode::ALL
+                .into_iter()
+                .map(|mode| GroupableTool {
+                    tool: Tools::Blur,
+                    blur_mode: Some(mode),
+                    icon_name: mode.icon_name().into(),
+                    tooltip: None,
+                })
+                .chain(std::iter::once(GroupableTool {
+                    tool: Tools::Redact,
+                    blur_mode: None,
+                    icon_name: "shield-regular".into(),
+                    tooltip: None,
+                }))
+                .collect(),
+            vec![
+                GroupableTool {
+                    tool: Tools::Highlight,
+                    blur_mode: None,
+                    icon_name: "highlight-regular".into(),
+                    tooltip: None,
+                },
+                GroupableTool {
+                    tool: Tools::Spotlight,
+                    blur_mode: None,
+                    icon_name: "flashlight-regular".into(),
+                    tooltip: None,
+                },
+            ],
             vec![GroupableTool {
-                tool: Tools::Text,
-                icon_name: "text-case-title-regular".into(),
-                tooltip: None,
-            }],
-            vec![GroupableTool {
-                tool: Tools::Marker,
-                icon_name: "number-circle-1-regular".into(),
-                tooltip: None,
-            }],
-            vec![GroupableTool {
-                tool: Tools::Blur,
-                icon_name: "drop-regular".into(),
+                tool: Tools::Magnifier,
+                blur_mode: None,
+                icon_name: "zoom-in-regular".into(),
                 tooltip: None,
             }],
             vec![GroupableTool {
-                tool: Tools::Highlight,
-                icon_name: "highlight-regular".into(),
+                tool: Tools::Eyedropper,
+                blur_mode: None,
+                icon_name: "eyedropper-regular".into(),
                 tooltip: None,
             }],
         ];
 
         // Set initial active button correctly
         let initial_tool = APP_CONFIG.read().initial_tool();
+        let initial_blur_mode = APP_CONFIG.read().primary_blur_mode();
         let mut tool_buttons = FactoryVecDeque::<ToolGroupButton>::builder()
             .launch(relm4::gtk::Box::default())
             .detach();
@@ -323,6 +488,7 @@ impl SimpleComponent for ToolsToolbar {
             let init = ToolGroupInit {
                 group: tg,
                 initial_tool,
+                initial_blur_mode,
             };
             guard.push_back(init);
         }
@@ -332,6 +498,7 @@ impl SimpleComponent for ToolsToolbar {
             visible: !APP_CONFIG.read().default_hide_toolbars(),
             tool_buttons,
             tool_action: tool_action.clone().into(),
+            blur_mode_action: blur_mode_action.clone().into(),
         };
 
         let tools_box = model.tool_buttons.widget();
@@ -359,6 +526,7 @@ impl SimpleComponent for ToolsToolbar {
 
         let mut group = RelmActionGroup::<ToolsToolbarActionGroup>::new();
         group.add_action(tool_action);
+        group.add_action(blur_mode_action);
         group.register_for_widget(&widgets.root);
 
         ComponentParts { model, widgets }
@@ -594,6 +762,42 @@ impl Component for StyleToolbar {
                     sender.output_sender().emit(ToolbarEvent::ToggleRoundCaps);
                 },
             },
+            #[name(stroke_pattern_button)]
+            gtk::Button {
+                set_focusable: false,
+                set_hexpand: false,
+                set_tooltip: "Cycle Stroke Pattern",
+                #[watch]
+                set_icon_name: model.stroke_pattern.icon_name(),
+                connect_clicked[sender] => move |_| {
+                    sender.output_sender().emit(ToolbarEvent::CycleStrokePattern);
+                },
+            },
+            gtk::Separator {},
+            gtk::MenuButton {
+                set_focusable: false,
+                set_hexpand: false,
+                set_tooltip: "Arrow Start",
+                #[watch]
+                set_icon_name: model.arrow_start.icon_name(),
+                set_popover: Some(&create_arrow_head_popover::<ArrowStartAction>()),
+            },
+            gtk::MenuButton {
+                set_focusable: false,
+                set_hexpand: false,
+                set_tooltip: "Arrow End",
+                #[watch]
+                set_icon_name: model.arrow_end.icon_name(),
+                set_popover: Some(&create_arrow_head_popover::<ArrowEndAction>()),
+            },
+            gtk::MenuButton {
+                set_focusable: false,
+                set_hexpand: false,
+                set_tooltip: "Stamp",
+                #[watch]
+                set_label: model.stamp.glyph(),
+                set_popover: Some(&create_stamp_popover()),
+            },
             gtk::Separator {},
             gtk::Button {
                 set_focusable: false,
@@ -671,6 +875,27 @@ impl Component for StyleToolbar {
             StyleToolbarInput::SetRoundCaps(round_caps_enabled) => {
                 self.round_caps_enabled = round_caps_enabled;
             }
+            StyleToolbarInput::SetStrokePattern(pattern) => {
+                self.stroke_pattern = pattern;
+            }
+            StyleToolbarInput::ArrowStartSelected(head) => {
+                self.arrow_start = head;
+                sender
+                    .output_sender()
+                    .emit(ToolbarEvent::ArrowStartSelected(head));
+            }
+            StyleToolbarInput::ArrowEndSelected(head) => {
+                self.arrow_end = head;
+                sender
+                    .output_sender()
+                    .emit(ToolbarEvent::ArrowEndSelected(head));
+            }
+            StyleToolbarInput::StampSelected(symbol) => {
+                self.stamp = symbol;
+                sender
+                    .output_sender()
+                    .emit(ToolbarEvent::StampSelected(symbol));
+            }
             StyleToolbarInput::SetSize(size) => {
                 self.size_action.change_state(&size.to_variant());
             }
@@ -743,6 +968,36 @@ impl Component for StyleToolbar {
                     .emit(ToolbarEvent::SizeSelected(*state));
             });
 
+        // Arrow head Actions for selecting the decorations at both ends of arrows
+        let sender_tmp = sender.clone();
+        let arrow_start_action: RelmAction<ArrowStartAction> =
+            RelmAction::new_stateful_with_target_value(
+                &APP_CONFIG.read().default_arrow_start(),
+                move |_, state, value| {
+                    *state = value;
+                    sender_tmp.input(StyleToolbarInput::ArrowStartSelected(value));
+                },
+            );
+        let sender_tmp = sender.clone();
+        let arrow_end_action: RelmAction<ArrowEndAction> =
+            RelmAction::new_stateful_with_target_value(
+                &APP_CONFIG.read().default_arrow_end(),
+                move |_, state, value| {
+                    *state = value;
+                    sender_tmp.input(StyleToolbarInput::ArrowEndSelected(value));
+                },
+            );
+
+        // Stamp Action for selecting the symbol of the stamp tool
+        let sender_tmp = sender.clone();
+        let stamp_action: RelmAction<StampAction> = RelmAction::new_stateful_with_target_value(
+            &StampSymbol::default(),
+            move |_, state, value| {
+                *state = value;
+                sender_tmp.input(StyleToolbarInput::StampSelected(value));
+            },
+        );
+
         let custom_color = APP_CONFIG
             .read()
             .color_palette()
@@ -761,6 +1016,10 @@ impl Component for StyleToolbar {
             size_spin_button: gtk::SpinButton::new(None::<&gtk::Adjustment>, 0.1, 2),
             fill_enabled: APP_CONFIG.read().default_fill_shapes(),
             round_caps_enabled: APP_CONFIG.read().default_round_caps(),
+            stroke_pattern: APP_CONFIG.read().default_stroke_pattern(),
+            arrow_start: APP_CONFIG.read().default_arrow_start(),
+            arrow_end: APP_CONFIG.read().default_arrow_end(),
+            stamp: StampSymbol::default(),
             visible: !APP_CONFIG.read().default_hide_toolbars(),
             output_dimensions: String::new(),
             editing: false,
@@ -800,10 +1059,18 @@ impl Component for StyleToolbar {
             &widgets.round_caps_button,
             ShortcutCommand::ToggleRoundCaps,
         );
+        update_hint(
+            &shortcut_registry,
+            &widgets.stroke_pattern_button,
+            ShortcutCommand::CycleStrokePattern,
+        );
 
         let mut group = RelmActionGroup::<StyleToolbarActionGroup>::new();
         group.add_action(color_action);
         group.add_action(size_action);
+        group.add_action(arrow_start_action);
+        group.add_action(arrow_end_action);
+        group.add_action(stamp_action);
 
         group.register_for_widget(&widgets.root);
 
@@ -812,6 +1079,13 @@ impl Component for StyleToolbar {
 }
 relm4::new_action_group!(pub ToolsToolbarActionGroup, "tools-toolbars");
 relm4::new_stateful_action!(pub ToolsAction, ToolsToolbarActionGroup, "tools", Tools, Tools);
+relm4::new_stateful_action!(
+    pub BlurModeAction,
+    ToolsToolbarActionGroup,
+    "blur-modes",
+    BlurMode,
+    BlurMode
+);
 
 relm4::new_action_group!(StyleToolbarActionGroup, "style-toolbars");
 relm4::new_stateful_action!(
@@ -829,6 +1103,28 @@ impl Clone for ColorAction {
 }
 
 relm4::new_stateful_action!(SizeAction, StyleToolbarActionGroup, "sizes", Size, Size);
+relm4::new_stateful_action!(
+    ArrowStartAction,
+    StyleToolbarActionGroup,
+    "arrow-starts",
+    ArrowHead,
+    ArrowHead
+);
+relm4::new_stateful_action!(
+    ArrowEndAction,
+    StyleToolbarActionGroup,
+    "arrow-ends",
+    ArrowHead,
+    ArrowHead
+);
+
+relm4::new_stateful_action!(
+    StampAction,
+    StyleToolbarActionGroup,
+    "stamps",
+    StampSymbol,
+    StampSymbol
+);
 
 impl StaticVariantType for ColorButtons {
     fn static_variant_type() -> Cow<'static, VariantTy> {